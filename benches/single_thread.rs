@@ -43,6 +43,28 @@ fn compare(c: &mut Criterion) {
         });
     });
 
+    group.bench_function("papaya_batch", |b| {
+        let m = papaya::HashMap::<usize, usize>::builder()
+            .collector(seize::Collector::new())
+            .build();
+
+        for i in RandomKeys::new().take(SIZE) {
+            m.pin().insert(i, i);
+        }
+
+        let keys = RandomKeys::new().take(SIZE).collect::<Vec<_>>();
+
+        b.iter(|| {
+            let m = m.pin();
+            for keys in keys.chunks_exact(16) {
+                let keys: [&usize; 16] = std::array::from_fn(|i| &keys[i]);
+                for (key, value) in keys.iter().zip(black_box(m.get_many(keys))) {
+                    assert_eq!(value, Some(*key));
+                }
+            }
+        });
+    });
+
     group.bench_function("std", |b| {
         let mut m = HashMap::<usize, usize>::default();
         for i in RandomKeys::new().take(SIZE) {
@@ -72,5 +94,57 @@ fn compare(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, compare);
+// Compares batched lookups with a loop of `get` on a table that does not fit in cache.
+fn batch(c: &mut Criterion) {
+    const SIZE: usize = 1 << 20;
+
+    let mut group = c.benchmark_group("read_batch");
+    group.sample_size(20);
+
+    let m = papaya::HashMap::<usize, usize>::builder()
+        .collector(seize::Collector::new())
+        .build();
+
+    let keys = (0..SIZE)
+        .map(|i| i.wrapping_mul(3_787_392_781))
+        .collect::<Vec<_>>();
+
+    for &key in &keys {
+        m.pin().insert(key, key);
+    }
+
+    group.bench_function("papaya_get", |b| {
+        b.iter(|| {
+            let m = m.pin();
+            for key in &keys {
+                assert_eq!(black_box(m.get(key)), Some(key));
+            }
+        });
+    });
+
+    group.bench_function("papaya_get_many", |b| {
+        b.iter(|| {
+            let m = m.pin();
+            for keys in keys.chunks_exact(16) {
+                let keys: [&usize; 16] = std::array::from_fn(|i| &keys[i]);
+                for (key, value) in keys.iter().zip(black_box(m.get_many(keys))) {
+                    assert_eq!(value, Some(*key));
+                }
+            }
+        });
+    });
+
+    group.bench_function("papaya_get_batch", |b| {
+        b.iter(|| {
+            let m = m.pin();
+            for (key, value) in keys.iter().zip(black_box(m.get_batch(&keys))) {
+                assert_eq!(value, Some(key));
+            }
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, compare, batch);
 criterion_main!(benches);
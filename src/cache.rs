@@ -0,0 +1,796 @@
+use crate::map::{Compute, HashMap, Iter, Operation, ResizeMode};
+use crate::raw::utils::Counter;
+use crate::Equivalent;
+use seize::{Collector, Guard, LocalGuard, OwnedGuard};
+
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A concurrent cache with a bounded number of entries.
+///
+/// A `Cache` is a hash table that evicts entries once it exceeds its maximum capacity. Entries
+/// are evicted using the CLOCK algorithm, an approximation of least-recently-used eviction.
+/// Every slot in the table has an access bit, which is set when the entry in that slot is read.
+/// When an insertion causes the cache to exceed its capacity, a clock hand sweeps over the
+/// table, clearing access bits, and evicts the first entry that has not been accessed since
+/// the hand last passed it.
+///
+/// Newly inserted entries start out unaccessed, so entries that are only inserted once, such
+/// as during a scan, are evicted before entries that are read repeatedly.
+///
+/// Reads remain lock-free, and only write to the access bit of an entry if it is not already
+/// set. As with [`HashMap`], most operations require a [`Guard`], which can be acquired through
+/// [`Cache::guard`] or using the [`Cache::pin`] API.
+///
+/// # Capacity
+///
+/// By default, the capacity of a cache is the maximum number of entries it can hold. Caches
+/// holding values of varying size can instead be bounded by weight, where each entry is
+/// assigned a weight by a [weigher](CacheBuilder::weigher), and the total weight of all entries
+/// is bounded by a [maximum weight](CacheBuilder::max_weight).
+///
+/// The capacity of the cache is maintained approximately. Concurrent insertions may briefly
+/// exceed the capacity before evicting, and eviction may fail to make progress while the
+/// table is being resized.
+///
+/// # Examples
+///
+/// ```
+/// use papaya::Cache;
+///
+/// let cache = Cache::new(2);
+/// let cache = cache.pin();
+///
+/// cache.insert("a", 1);
+/// cache.insert("b", 2);
+///
+/// // Access `a`, protecting it from eviction.
+/// assert_eq!(cache.get(&"a"), Some(&1));
+///
+/// // Either `b` or `c` is evicted.
+/// cache.insert("c", 3);
+/// assert_eq!(cache.len(), 2);
+/// assert_eq!(cache.get(&"a"), Some(&1));
+/// ```
+pub struct Cache<K, V, S = RandomState> {
+    map: HashMap<K, V, S>,
+    capacity: usize,
+    max_weight: u64,
+    weigher: Box<Weigher<K, V>>,
+    weight: Counter,
+    hand: AtomicUsize,
+}
+
+/// A function that computes the weight of an entry.
+type Weigher<K, V> = dyn Fn(&K, &V) -> u64 + Send + Sync;
+
+/// A builder for a [`Cache`].
+///
+/// # Examples
+///
+/// ```rust
+/// use papaya::Cache;
+/// use seize::Collector;
+/// use std::collections::hash_map::RandomState;
+///
+/// let cache: Cache<i32, i32> = Cache::builder()
+///     // Set the maximum capacity.
+///     .capacity(2048)
+///     // Set the hasher.
+///     .hasher(RandomState::new())
+///     // Set a custom garbage collector.
+///     .collector(Collector::new().batch_size(128))
+///     // Construct the cache.
+///     .build();
+/// ```
+pub struct CacheBuilder<K, V, S = RandomState> {
+    hasher: S,
+    capacity: usize,
+    collector: Collector,
+    max_weight: Option<u64>,
+    weigher: Option<Box<Weigher<K, V>>>,
+    _kv: PhantomData<(K, V)>,
+}
+
+impl<K, V> CacheBuilder<K, V> {
+    /// Set the hash builder used to hash keys.
+    ///
+    /// See [`HashMapBuilder::hasher`](crate::HashMapBuilder::hasher) for details.
+    pub fn hasher<S>(self, hasher: S) -> CacheBuilder<K, V, S> {
+        CacheBuilder {
+            hasher,
+            capacity: self.capacity,
+            collector: self.collector,
+            max_weight: self.max_weight,
+            weigher: self.weigher,
+            _kv: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> CacheBuilder<K, V, S> {
+    /// Set the maximum capacity of the cache.
+    ///
+    /// Space for `capacity` entries is allocated up-front. If a [maximum
+    /// weight](CacheBuilder::max_weight) is configured, the capacity is only used as the
+    /// initial capacity of the cache, and does not bound the number of entries.
+    pub fn capacity(self, capacity: usize) -> CacheBuilder<K, V, S> {
+        CacheBuilder { capacity, ..self }
+    }
+
+    /// Set the maximum total weight of the entries in the cache.
+    ///
+    /// Entries are weighed with the configured [weigher](CacheBuilder::weigher), or have a
+    /// weight of `1` if no weigher is configured.
+    pub fn max_weight(self, max_weight: u64) -> CacheBuilder<K, V, S> {
+        CacheBuilder {
+            max_weight: Some(max_weight),
+            ..self
+        }
+    }
+
+    /// Set the function used to compute the weight of an entry.
+    ///
+    /// The weigher is called once when an entry is inserted and once when it is removed, so
+    /// it must return the same weight for a given key and value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::Cache;
+    ///
+    /// // Limit the cache to 1KB of values.
+    /// let cache = Cache::builder()
+    ///     .max_weight(1024)
+    ///     .weigher(|_key: &usize, value: &Vec<u8>| value.len() as u64)
+    ///     .build();
+    /// let cache = cache.pin();
+    ///
+    /// cache.insert(0, vec![0; 512]);
+    /// cache.insert(1, vec![0; 256]);
+    /// assert_eq!(cache.total_weight(), 768);
+    ///
+    /// cache.insert(2, vec![0; 512]);
+    /// assert!(cache.total_weight() <= 1024);
+    /// ```
+    pub fn weigher<F>(self, weigher: F) -> CacheBuilder<K, V, S>
+    where
+        F: Fn(&K, &V) -> u64 + Send + Sync + 'static,
+    {
+        CacheBuilder {
+            weigher: Some(Box::new(weigher)),
+            ..self
+        }
+    }
+
+    /// Set the [`seize::Collector`] used for garbage collection.
+    ///
+    /// This method may be useful when you want more control over garbage collection.
+    ///
+    /// Note that all `Guard` references used to access the cache must be produced by
+    /// the provided `collector`.
+    pub fn collector(self, collector: Collector) -> CacheBuilder<K, V, S> {
+        CacheBuilder { collector, ..self }
+    }
+
+    /// Construct a [`Cache`] from the builder, using the configured options.
+    ///
+    /// # Panics
+    ///
+    /// Panics if neither the capacity nor the maximum weight is non-zero.
+    pub fn build(self) -> Cache<K, V, S>
+    where
+        K: 'static,
+        V: 'static,
+    {
+        let max_weight = self.max_weight.unwrap_or(self.capacity as u64);
+        assert!(max_weight > 0, "`Cache` capacity must be non-zero");
+
+        let mut map = HashMap::builder()
+            .hasher(self.hasher)
+            .capacity(self.capacity)
+            .collector(self.collector)
+            // Eviction relies on the root table being the source of truth.
+            .resize_mode(ResizeMode::Blocking)
+            .build();
+        map.raw = map.raw.track_access(true);
+
+        Cache {
+            map,
+            max_weight,
+            capacity: self.capacity,
+            weigher: self.weigher.unwrap_or_else(|| Box::new(|_, _| 1)),
+            weight: Counter::default(),
+            hand: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<K, V, S> fmt::Debug for CacheBuilder<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CacheBuilder")
+            .field("capacity", &self.capacity)
+            .field("collector", &self.collector)
+            .field("max_weight", &self.max_weight)
+            .finish()
+    }
+}
+
+impl<K, V> Cache<K, V> {
+    /// Creates an empty `Cache` with the given maximum capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the capacity is `0`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::Cache;
+    /// let cache: Cache<&str, i32> = Cache::new(1024);
+    /// ```
+    pub fn new(capacity: usize) -> Cache<K, V>
+    where
+        K: 'static,
+        V: 'static,
+    {
+        Cache::builder().capacity(capacity).build()
+    }
+
+    /// Returns a builder for a `Cache`.
+    ///
+    /// The builder can be used for more complex configuration, such as using
+    /// a custom [`Collector`].
+    pub fn builder() -> CacheBuilder<K, V> {
+        CacheBuilder {
+            capacity: 0,
+            hasher: RandomState::default(),
+            collector: Collector::default(),
+            max_weight: None,
+            weigher: None,
+            _kv: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> Cache<K, V, S> {
+    /// Returns a pinned reference to the cache.
+    ///
+    /// The returned reference manages a guard internally, preventing garbage collection
+    /// for as long as it is held. See the [crate-level documentation](crate#usage) for details.
+    #[inline]
+    pub fn pin(&self) -> CacheRef<'_, K, V, S, LocalGuard<'_>> {
+        CacheRef {
+            guard: self.guard(),
+            cache: self,
+        }
+    }
+
+    /// Returns a pinned reference to the cache.
+    ///
+    /// Unlike [`Cache::pin`], the returned reference implements `Send` and `Sync`,
+    /// allowing it to be held across `.await` points in work-stealing schedulers.
+    #[inline]
+    pub fn pin_owned(&self) -> CacheRef<'_, K, V, S, OwnedGuard<'_>> {
+        CacheRef {
+            guard: self.owned_guard(),
+            cache: self,
+        }
+    }
+
+    /// Returns a guard for use with this cache.
+    ///
+    /// Note that holding on to a guard prevents garbage collection.
+    /// See the [crate-level documentation](crate#usage) for details.
+    #[inline]
+    pub fn guard(&self) -> LocalGuard<'_> {
+        self.map.guard()
+    }
+
+    /// Returns an owned guard for use with this cache.
+    ///
+    /// Note that holding on to a guard prevents garbage collection.
+    /// See the [crate-level documentation](crate#usage) for details.
+    #[inline]
+    pub fn owned_guard(&self) -> OwnedGuard<'_> {
+        self.map.owned_guard()
+    }
+
+    /// Returns the configured capacity of the cache.
+    ///
+    /// See [`CacheBuilder::capacity`] for details.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the maximum total weight of the entries in the cache.
+    ///
+    /// For caches without a [maximum weight](CacheBuilder::max_weight), this is equal to
+    /// the capacity.
+    #[inline]
+    pub fn max_weight(&self) -> u64 {
+        self.max_weight
+    }
+
+    /// Returns the total weight of the entries in the cache.
+    ///
+    /// For caches without a [weigher](CacheBuilder::weigher), this is equal to the number of
+    /// entries in the cache. Similar to [`len`](Cache::len), the weight is maintained across
+    /// shards and may not reflect modifications that are concurrently in progress.
+    #[inline]
+    pub fn total_weight(&self) -> u64 {
+        self.weight.sum() as u64
+    }
+
+    /// Returns the weight of an entry.
+    #[inline]
+    fn weigh(&self, key: &K, value: &V) -> isize {
+        isize::try_from((self.weigher)(key, value)).unwrap_or(isize::MAX)
+    }
+
+    /// Adds to the total weight of the cache.
+    #[inline]
+    fn add_weight(&self, weight: isize, guard: &impl Guard) {
+        if weight != 0 {
+            self.weight.get(guard).fetch_add(weight, Ordering::Relaxed);
+        }
+    }
+
+    /// Subtracts the weight of an entry from the total weight of the cache.
+    #[inline]
+    fn sub_weight(&self, key: &K, value: &V, guard: &impl Guard) {
+        self.add_weight(-self.weigh(key, value), guard);
+    }
+}
+
+impl<K, V, S> Cache<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Returns the number of entries in the cache.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the cache is empty. Otherwise returns `false`.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key, marking the
+    /// entry as accessed.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q, guard: &impl Guard) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.map.contains_key(key, guard)
+    }
+
+    /// Returns a reference to the value corresponding to the key, marking the entry as
+    /// accessed.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::Cache;
+    ///
+    /// let cache = Cache::new(16);
+    /// cache.pin().insert(1, "a");
+    /// assert_eq!(cache.pin().get(&1), Some(&"a"));
+    /// assert_eq!(cache.pin().get(&2), None);
+    /// ```
+    #[inline]
+    pub fn get<'g, Q>(&self, key: &Q, guard: &'g impl Guard) -> Option<&'g V>
+    where
+        K: 'g,
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.map.get(key, guard)
+    }
+
+    /// Inserts a key-value pair into the cache, evicting entries if the cache is full.
+    ///
+    /// If the cache did not have this key present, `None` is returned. Otherwise, the
+    /// value is updated, and a reference to the old value is returned.
+    ///
+    /// Note that the inserted entry is not marked as accessed, and may itself be evicted.
+    /// Entries that weigh more than the [maximum weight](CacheBuilder::max_weight) of the
+    /// cache are never inserted. Instead, `None` is returned and any existing value for the
+    /// key is left in the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::Cache;
+    ///
+    /// let cache = Cache::new(16);
+    /// assert_eq!(cache.pin().insert(37, "a"), None);
+    /// assert_eq!(cache.pin().is_empty(), false);
+    ///
+    /// cache.pin().insert(37, "b");
+    /// assert_eq!(cache.pin().insert(37, "c"), Some(&"b"));
+    /// assert_eq!(cache.pin().get(&37), Some(&"c"));
+    /// ```
+    #[inline]
+    pub fn insert<'g>(&self, key: K, value: V, guard: &'g impl Guard) -> Option<&'g V>
+    where
+        K: 'g,
+    {
+        // The entry could never fit in the cache.
+        if (self.weigher)(&key, &value) > self.max_weight {
+            return None;
+        }
+
+        // Reserve the weight of the new entry before it is inserted, so that concurrent
+        // evictions account for it. The weight of the replaced entry is only known after
+        // the swap, so the total weight may briefly be overestimated.
+        self.add_weight(self.weigh(&key, &value), guard);
+
+        // Note that the raw insert reuses the same allocation if it is retried, so the value
+        // is moved into the cache exactly once, regardless of concurrent writers.
+        let old = self
+            .map
+            .raw
+            .insert_entry(key, value, self.map.raw.verify(guard))
+            .map(|(old_key, old_value)| {
+                self.sub_weight(old_key, old_value, guard);
+                old_value
+            });
+
+        self.evict(guard);
+        old
+    }
+
+    /// Updates an existing entry atomically, returning the updated value.
+    ///
+    /// If the value is not present, `None` is returned. The weight of the cache is adjusted
+    /// for the new value, evicting entries if necessary.
+    ///
+    /// See [`HashMap::update`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::Cache;
+    ///
+    /// let cache = Cache::new(16);
+    /// cache.pin().insert("a", 1);
+    /// assert_eq!(cache.pin().update("a", |v| v + 1), Some(&2));
+    /// assert_eq!(cache.pin().update("b", |v| v + 1), None);
+    /// ```
+    #[inline]
+    pub fn update<'g, F>(&self, key: K, update: F, guard: &'g impl Guard) -> Option<&'g V>
+    where
+        F: Fn(&V) -> V,
+        K: 'g,
+    {
+        let compute = |entry| match entry {
+            Some((_, value)) => Operation::Insert(update(value)),
+            None => Operation::Abort(()),
+        };
+
+        match self.compute(key, compute, guard) {
+            Compute::Updated {
+                new: (_, value), ..
+            } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Updates an entry with a compare-and-swap (CAS) function.
+    ///
+    /// The weight of the cache is adjusted for the inserted or removed entry, evicting
+    /// entries if necessary. Note that unlike [`insert`](Cache::insert), entries that weigh
+    /// more than the maximum weight of the cache are inserted, and may be evicted by the
+    /// next insertion.
+    ///
+    /// See [`HashMap::compute`] for details.
+    #[inline]
+    pub fn compute<'g, F, T>(
+        &self,
+        key: K,
+        compute: F,
+        guard: &'g impl Guard,
+    ) -> Compute<'g, K, V, T>
+    where
+        F: FnMut(Option<(&'g K, &'g V)>) -> Operation<V, T>,
+        K: 'g,
+    {
+        self.compute_inner(key, compute, guard)
+    }
+
+    /// Performs a compute operation, adjusting the weight of the cache and evicting entries
+    /// if necessary.
+    #[inline]
+    fn compute_inner<'g, F, T>(
+        &self,
+        key: K,
+        compute: F,
+        guard: &'g impl Guard,
+    ) -> Compute<'g, K, V, T>
+    where
+        F: FnMut(Option<(&'g K, &'g V)>) -> Operation<V, T>,
+        K: 'g,
+    {
+        let mut compute = compute;
+
+        // The change in weight of the cached insert and update transitions.
+        let (insert_weight, update_weight) = (Cell::new(0), Cell::new(0));
+
+        // Reserve any increase in weight before a transition is attempted, so that concurrent
+        // evictions account for it. Decreases in weight are applied once the transition
+        // succeeds, so the total weight is never underestimated.
+        let compute = |key: &K, entry: Option<(&'g K, &'g V)>| {
+            let operation = compute(entry);
+
+            let weight = match (&operation, entry) {
+                (Operation::Insert(value), None) => self.weigh(key, value),
+                (Operation::Insert(value), Some((old_key, old_value))) => {
+                    self.weigh(key, value) - self.weigh(old_key, old_value)
+                }
+                (Operation::Remove, Some((old_key, old_value))) => -self.weigh(old_key, old_value),
+                _ => 0,
+            };
+
+            // The insert transition is computed at most once, but the update transition is
+            // recomputed whenever the entry changes, replacing the previous reservation.
+            let transition = if entry.is_none() {
+                &insert_weight
+            } else {
+                &update_weight
+            };
+            let previous = transition.replace(weight);
+            self.add_weight(weight.max(0) - previous.max(0), guard);
+
+            operation
+        };
+
+        let result = self
+            .map
+            .raw
+            .compute_with_key(key, compute, self.map.raw.verify(guard));
+
+        let (insert_weight, update_weight) = (insert_weight.get(), update_weight.get());
+
+        // Apply the remaining weight of the transition that was performed, and roll back the
+        // reservations of any that were not.
+        let weight = match result {
+            Compute::Inserted(..) => insert_weight.min(0) - update_weight.max(0),
+            Compute::Updated { .. } | Compute::Removed(..) => {
+                update_weight.min(0) - insert_weight.max(0)
+            }
+            Compute::Aborted(_) => -insert_weight.max(0) - update_weight.max(0),
+        };
+        self.add_weight(weight, guard);
+
+        if !matches!(result, Compute::Aborted(_)) {
+            self.evict(guard);
+        }
+
+        result
+    }
+
+    /// Evicts entries until the cache is within its capacity.
+    #[inline]
+    fn evict(&self, guard: &impl Guard) {
+        let verified = self.map.raw.verify(guard);
+
+        while self.total_weight() > self.max_weight {
+            match self.map.raw.evict(&self.hand, verified) {
+                Some((key, value)) => self.sub_weight(key, value, guard),
+
+                // The table is being resized, give up.
+                None => break,
+            }
+        }
+    }
+
+    /// Removes a key from the cache, returning the value at the key if the key
+    /// was previously in the cache.
+    ///
+    /// The key may be any borrowed form of the cache's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    #[inline]
+    pub fn remove<'g, Q>(&self, key: &Q, guard: &'g impl Guard) -> Option<&'g V>
+    where
+        K: 'g,
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let (key, value) = self.map.raw.remove(key, self.map.raw.verify(guard))?;
+        self.sub_weight(key, value, guard);
+        Some(value)
+    }
+
+    /// Clears the cache, removing all key-value pairs.
+    ///
+    /// Note that unlike [`HashMap::clear`], entries are removed one at a time, and entries
+    /// inserted concurrently may not be removed.
+    #[inline]
+    pub fn clear(&self, guard: &impl Guard) {
+        for (key, _) in self.map.iter(guard) {
+            self.remove(key, guard);
+        }
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order.
+    /// The iterator element type is `(&K, &V)`.
+    ///
+    /// Note that iteration does not mark entries as accessed.
+    #[inline]
+    pub fn iter<'g, G>(&self, guard: &'g G) -> Iter<'g, K, V, G>
+    where
+        G: Guard,
+    {
+        self.map.iter(guard)
+    }
+}
+
+impl<K, V, S> fmt::Debug for Cache<K, V, S>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let guard = self.guard();
+        f.debug_map().entries(self.iter(&guard)).finish()
+    }
+}
+
+/// A pinned reference to a [`Cache`].
+///
+/// This type is created with [`Cache::pin`] and can be used to easily access a [`Cache`]
+/// without explicitly managing a guard. See the [crate-level documentation](crate#usage) for details.
+pub struct CacheRef<'cache, K, V, S, G> {
+    guard: G,
+    cache: &'cache Cache<K, V, S>,
+}
+
+impl<'cache, K, V, S, G> CacheRef<'cache, K, V, S, G>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    G: Guard,
+{
+    /// Returns a reference to the inner [`Cache`].
+    #[inline]
+    pub fn cache(&self) -> &'cache Cache<K, V, S> {
+        self.cache
+    }
+
+    /// Returns the number of entries in the cache.
+    ///
+    /// See [`Cache::len`] for details.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Returns `true` if the cache is empty. Otherwise returns `false`.
+    ///
+    /// See [`Cache::is_empty`] for details.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the total weight of the entries in the cache.
+    ///
+    /// See [`Cache::total_weight`] for details.
+    #[inline]
+    pub fn total_weight(&self) -> u64 {
+        self.cache.total_weight()
+    }
+
+    /// Returns `true` if the cache contains a value for the specified key, marking the
+    /// entry as accessed.
+    ///
+    /// See [`Cache::contains_key`] for details.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.cache.contains_key(key, &self.guard)
+    }
+
+    /// Returns a reference to the value corresponding to the key, marking the entry as
+    /// accessed.
+    ///
+    /// See [`Cache::get`] for details.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.cache.get(key, &self.guard)
+    }
+
+    /// Inserts a key-value pair into the cache, evicting entries if the cache is full.
+    ///
+    /// See [`Cache::insert`] for details.
+    #[inline]
+    pub fn insert(&self, key: K, value: V) -> Option<&V> {
+        self.cache.insert(key, value, &self.guard)
+    }
+
+    /// Updates an existing entry atomically, returning the updated value.
+    ///
+    /// See [`Cache::update`] for details.
+    #[inline]
+    pub fn update<F>(&self, key: K, update: F) -> Option<&V>
+    where
+        F: Fn(&V) -> V,
+    {
+        self.cache.update(key, update, &self.guard)
+    }
+
+    /// Updates an entry with a compare-and-swap (CAS) function.
+    ///
+    /// See [`Cache::compute`] for details.
+    #[inline]
+    pub fn compute<'g, F, T>(&'g self, key: K, compute: F) -> Compute<'g, K, V, T>
+    where
+        F: FnMut(Option<(&'g K, &'g V)>) -> Operation<V, T>,
+    {
+        self.cache.compute(key, compute, &self.guard)
+    }
+
+    /// Removes a key from the cache, returning the value at the key if the key
+    /// was previously in the cache.
+    ///
+    /// See [`Cache::remove`] for details.
+    #[inline]
+    pub fn remove<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.cache.remove(key, &self.guard)
+    }
+
+    /// Clears the cache, removing all key-value pairs.
+    ///
+    /// See [`Cache::clear`] for details.
+    #[inline]
+    pub fn clear(&self) {
+        self.cache.clear(&self.guard)
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order.
+    ///
+    /// See [`Cache::iter`] for details.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V, G> {
+        self.cache.iter(&self.guard)
+    }
+}
+
+impl<K, V, S, G> fmt::Debug for CacheRef<'_, K, V, S, G>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    G: Guard,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
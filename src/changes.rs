@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+
+/// A change made to a [`HashMap`](crate::HashMap), yielded by a [`ChangeStream`].
+///
+/// Keys and values are cloned into the event. Maps with keys or values that are expensive to
+/// clone can store them behind an [`Arc`] to share them with subscribers instead.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change<K, V> {
+    /// A new entry was inserted into the map.
+    Inserted {
+        /// The key that was inserted.
+        key: K,
+        /// The value that was inserted.
+        value: V,
+    },
+
+    /// The value of an existing entry was replaced.
+    Updated {
+        /// The key that was updated.
+        key: K,
+        /// The previous value of the entry.
+        old: V,
+        /// The new value of the entry.
+        new: V,
+    },
+
+    /// An entry was removed from the map.
+    Removed {
+        /// The key that was removed.
+        key: K,
+        /// The value of the entry that was removed.
+        value: V,
+    },
+}
+
+impl<K, V> Change<K, V> {
+    /// Returns the key this change was made to.
+    #[inline]
+    pub fn key(&self) -> &K {
+        match self {
+            Change::Inserted { key, .. }
+            | Change::Updated { key, .. }
+            | Change::Removed { key, .. } => key,
+        }
+    }
+}
+
+/// A stream of changes made to a [`HashMap`](crate::HashMap).
+///
+/// This type is created by [`HashMap::subscribe`](crate::HashMap::subscribe). See its
+/// documentation for details.
+///
+/// Changes are buffered until they are received, so a stream that is not consumed will grow
+/// without bound. Dropping the stream unsubscribes it from the map.
+pub struct ChangeStream<K, V> {
+    channel: Arc<Channel<K, V>>,
+    active: Arc<AtomicUsize>,
+}
+
+impl<K, V> ChangeStream<K, V> {
+    /// Returns the next change, if one is immediately available.
+    #[inline]
+    pub fn try_recv(&self) -> Option<Change<K, V>> {
+        self.channel.lock().queue.pop_front()
+    }
+
+    /// Blocks until the next change is available.
+    ///
+    /// Returns `None` once the map has been dropped and all buffered changes have been received.
+    pub fn recv(&self) -> Option<Change<K, V>> {
+        let mut state = self.channel.lock();
+
+        loop {
+            if let Some(change) = state.queue.pop_front() {
+                return Some(change);
+            }
+
+            if state.disconnected {
+                return None;
+            }
+
+            state = (self.channel.ready.wait(state)).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Blocks until the next change is available, or the timeout elapses.
+    ///
+    /// Returns `None` if the timeout elapses, or if the map has been dropped and all buffered
+    /// changes have been received.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Change<K, V>> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.channel.lock();
+
+        loop {
+            if let Some(change) = state.queue.pop_front() {
+                return Some(change);
+            }
+
+            let now = Instant::now();
+            if state.disconnected || now >= deadline {
+                return None;
+            }
+
+            state = (self.channel.ready.wait_timeout(state, deadline - now))
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+    }
+
+    /// Returns the number of changes that are buffered and have not yet been received.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.channel.lock().queue.len()
+    }
+
+    /// Returns `true` if there are no buffered changes.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K, V> Iterator for ChangeStream<K, V> {
+    type Item = Change<K, V>;
+
+    /// Blocks until the next change is available.
+    ///
+    /// See [`ChangeStream::recv`] for details.
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.recv()
+    }
+}
+
+impl<K, V> Drop for ChangeStream<K, V> {
+    fn drop(&mut self) {
+        self.channel.closed.store(true, Ordering::Relaxed);
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<K, V> fmt::Debug for ChangeStream<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeStream")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// A buffer of changes shared between a map and a single stream.
+struct Channel<K, V> {
+    state: Mutex<ChannelState<K, V>>,
+    ready: Condvar,
+    closed: AtomicBool,
+}
+
+struct ChannelState<K, V> {
+    queue: VecDeque<Change<K, V>>,
+    disconnected: bool,
+}
+
+impl<K, V> Channel<K, V> {
+    #[inline]
+    fn lock(&self) -> MutexGuard<'_, ChannelState<K, V>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A change made to the map, by reference.
+pub(crate) enum RawChange<'a, K, V> {
+    Inserted(&'a K, &'a V),
+    Updated(&'a K, &'a V, &'a V),
+    Removed(&'a K, &'a V),
+}
+
+impl<K, V> Clone for RawChange<'_, K, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for RawChange<'_, K, V> {}
+
+/// The number of locks used to order changes to the same key.
+const STRIPES: usize = 64;
+
+/// The set of streams subscribed to a map.
+pub(crate) struct Subscribers<K, V> {
+    /// The number of live streams.
+    active: Arc<AtomicUsize>,
+
+    /// The buffers of every stream, including those that may have been dropped.
+    channels: RwLock<Vec<Arc<Channel<K, V>>>>,
+
+    /// Locks held across a mutation and its emission, ensuring that changes to a given
+    /// key are observed in the order they were made.
+    stripes: Box<[Mutex<()>]>,
+
+    /// Functions used to clone keys and values into events.
+    clone_key: fn(&K) -> K,
+    clone_value: fn(&V) -> V,
+}
+
+impl<K, V> Subscribers<K, V> {
+    /// Creates an empty set of subscribers.
+    pub fn new() -> Subscribers<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        Subscribers {
+            active: Arc::new(AtomicUsize::new(0)),
+            channels: RwLock::new(Vec::new()),
+            stripes: (0..STRIPES).map(|_| Mutex::new(())).collect(),
+            clone_key: K::clone,
+            clone_value: V::clone,
+        }
+    }
+
+    /// Returns `true` if there are any live streams.
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed) != 0
+    }
+
+    /// Subscribes a new stream.
+    pub fn subscribe(&self) -> ChangeStream<K, V> {
+        let channel = Arc::new(Channel {
+            state: Mutex::new(ChannelState {
+                queue: VecDeque::new(),
+                disconnected: false,
+            }),
+            ready: Condvar::new(),
+            closed: AtomicBool::new(false),
+        });
+
+        let mut channels = self
+            .channels
+            .write()
+            .unwrap_or_else(PoisonError::into_inner);
+
+        // Clean up any streams that have been dropped.
+        channels.retain(|channel| !channel.closed.load(Ordering::Relaxed));
+        channels.push(channel.clone());
+        self.active.fetch_add(1, Ordering::Relaxed);
+
+        ChangeStream {
+            channel,
+            active: self.active.clone(),
+        }
+    }
+
+    /// Acquires the lock for changes to a key with the given hash.
+    #[inline]
+    pub fn lock(&self, hash: u64) -> MutexGuard<'_, ()> {
+        self.stripes[hash as usize % STRIPES]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Sends a change to every live stream.
+    pub fn emit(&self, change: RawChange<'_, K, V>) {
+        let channels = self.channels.read().unwrap_or_else(PoisonError::into_inner);
+
+        for channel in channels.iter() {
+            if channel.closed.load(Ordering::Relaxed) {
+                continue;
+            }
+
+            let (key, value) = (self.clone_key, self.clone_value);
+            let change = match change {
+                RawChange::Inserted(k, v) => Change::Inserted {
+                    key: key(k),
+                    value: value(v),
+                },
+                RawChange::Updated(k, old, new) => Change::Updated {
+                    key: key(k),
+                    old: value(old),
+                    new: value(new),
+                },
+                RawChange::Removed(k, v) => Change::Removed {
+                    key: key(k),
+                    value: value(v),
+                },
+            };
+
+            channel.lock().queue.push_back(change);
+            channel.ready.notify_one();
+        }
+    }
+}
+
+impl<K, V> Drop for Subscribers<K, V> {
+    fn drop(&mut self) {
+        // The map was dropped, wake up any blocked streams.
+        for channel in self
+            .channels
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+        {
+            channel.lock().disconnected = true;
+            channel.ready.notify_all();
+        }
+    }
+}
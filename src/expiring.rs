@@ -0,0 +1,913 @@
+//! A concurrent hash map with per-entry expiration.
+//!
+//! An [`ExpiringMap`] associates every entry with an optional time-to-live. Expired entries are
+//! treated as absent by all operations as soon as their deadline passes, regardless of whether
+//! they have been removed from the underlying table yet.
+//!
+//! Expired entries are reclaimed in two ways. By default, every write to the map sweeps a
+//! small chunk of the table and removes any expired entries it finds, similar to
+//! [`ResizeMode::Incremental`]. This spreads the cost of expiration across writers instead of
+//! requiring periodic scans of the entire table. Expired entries can also be removed eagerly
+//! with [`ExpiringMap::reap_expired`], for example from a background thread. See [`SweepMode`]
+//! for details.
+//!
+//! Time is read from a [`Clock`], which defaults to the system's monotonic clock. A
+//! [`ManualClock`] can be used to advance time deterministically in tests.
+//!
+//! # Examples
+//!
+//! ```
+//! use papaya::expiring::{ExpiringMap, ManualClock};
+//! use std::time::Duration;
+//!
+//! let clock = ManualClock::new();
+//! let map = ExpiringMap::builder().clock(clock.clone()).build();
+//! let map = map.pin();
+//!
+//! map.insert_with_ttl("a", 1, Duration::from_secs(10));
+//! map.insert("b", 2);
+//!
+//! clock.advance(Duration::from_secs(5));
+//! assert_eq!(map.get(&"a"), Some(&1));
+//!
+//! // Extend the lifetime of the entry.
+//! assert!(map.refresh_ttl(&"a", Duration::from_secs(10)));
+//!
+//! clock.advance(Duration::from_secs(10));
+//! assert_eq!(map.get(&"a"), None);
+//!
+//! // Entries inserted without a TTL never expire.
+//! assert_eq!(map.get(&"b"), Some(&2));
+//! ```
+
+use crate::map::{self, HashMap, HashMapBuilder, ResizeMode};
+use crate::Equivalent;
+use seize::{Collector, Guard, LocalGuard};
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A source of time for an [`ExpiringMap`].
+pub trait Clock {
+    /// Returns the current time.
+    ///
+    /// The returned time must never decrease.
+    fn now(&self) -> Instant;
+}
+
+/// A [`Clock`] that reads the system's monotonic clock.
+///
+/// This is the default clock of an [`ExpiringMap`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A [`Clock`] that only advances when requested.
+///
+/// Clones of a `ManualClock` share the same time, so a clone can be passed to a map while the
+/// original is used to control it.
+///
+/// # Examples
+///
+/// ```
+/// use papaya::expiring::{Clock, ManualClock};
+/// use std::time::Duration;
+///
+/// let clock = ManualClock::new();
+/// let start = clock.now();
+///
+/// clock.clone().advance(Duration::from_secs(1));
+/// assert_eq!(clock.now() - start, Duration::from_secs(1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    start: Instant,
+    elapsed: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    /// Creates a new clock, starting at the current time.
+    pub fn new() -> ManualClock {
+        ManualClock {
+            start: Instant::now(),
+            elapsed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Advances the clock by the given duration.
+    pub fn advance(&self, duration: Duration) {
+        self.elapsed
+            .fetch_add(saturating_nanos(duration), Ordering::Relaxed);
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new()
+    }
+}
+
+impl Clock for ManualClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        self.start + Duration::from_nanos(self.elapsed.load(Ordering::Relaxed))
+    }
+}
+
+/// Sweeping behavior for an [`ExpiringMap`].
+///
+/// This type allows you to configure how expired entries are removed from the map when passed
+/// to [`ExpiringMapBuilder::sweep_mode`]. Note that expired entries are never visible,
+/// regardless of the sweep mode.
+#[derive(Debug)]
+pub enum SweepMode {
+    /// Writers scan a constant number of table slots for expired entries after every write.
+    ///
+    /// Incremental sweeping bounds the amount of memory held by expired entries without
+    /// requiring scans of the entire table, at the cost of slightly slower writes.
+    ///
+    /// This is the default sweep mode, with a chunk size of `64`.
+    Incremental(usize),
+
+    /// Expired entries are only removed when they are overwritten, or by calls to
+    /// [`ExpiringMap::reap_expired`].
+    ///
+    /// This mode may be preferable for maps with few expiring entries, or if expired
+    /// entries are reaped by a background thread.
+    Manual,
+}
+
+impl Default for SweepMode {
+    fn default() -> Self {
+        SweepMode::Incremental(64)
+    }
+}
+
+/// A concurrent hash map with per-entry expiration.
+///
+/// See the [module-level documentation](self) for details.
+///
+/// Most operations require a [`Guard`], which can be acquired through [`ExpiringMap::guard`]
+/// or using the [`ExpiringMap::pin`] API, similar to [`HashMap`].
+pub struct ExpiringMap<K, V, S = RandomState, T = SystemClock> {
+    map: HashMap<K, Slot<V>, S>,
+    clock: T,
+    start: Instant,
+    default_ttl: Option<Duration>,
+    sweep_mode: SweepMode,
+    cursor: AtomicUsize,
+}
+
+/// A value stored in an [`ExpiringMap`].
+struct Slot<V> {
+    value: V,
+
+    /// The deadline of the entry, in nanoseconds since the creation of the map.
+    deadline: AtomicU64,
+}
+
+/// The deadline of an entry that never expires.
+const NEVER: u64 = u64::MAX;
+
+/// The deadline of an entry that has been claimed for removal.
+///
+/// Claimed entries are always expired, and can never be refreshed.
+const REAPED: u64 = 0;
+
+impl<V> Slot<V> {
+    /// Returns `true` if the entry has expired at time `now`.
+    #[inline]
+    fn is_expired(&self, now: impl FnOnce() -> u64) -> bool {
+        match self.deadline.load(Ordering::Acquire) {
+            NEVER => false,
+            deadline => deadline <= now(),
+        }
+    }
+
+    /// Claims an expired entry for removal, returning `false` if it has not expired.
+    ///
+    /// Once an entry is claimed, concurrent calls to `refresh` are guaranteed to fail.
+    #[inline]
+    fn try_reap(&self, now: u64) -> bool {
+        let mut deadline = self.deadline.load(Ordering::Acquire);
+
+        loop {
+            if deadline > now {
+                return false;
+            }
+
+            match self.deadline.compare_exchange_weak(
+                deadline,
+                REAPED,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return true,
+                Err(found) => deadline = found,
+            }
+        }
+    }
+
+    /// Sets the deadline of the entry, returning `false` if it has already expired.
+    #[inline]
+    fn refresh(&self, now: u64, new: u64) -> bool {
+        let mut deadline = self.deadline.load(Ordering::Acquire);
+
+        loop {
+            if deadline <= now {
+                return false;
+            }
+
+            match self.deadline.compare_exchange_weak(
+                deadline,
+                new,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return true,
+                Err(found) => deadline = found,
+            }
+        }
+    }
+}
+
+/// Returns the number of nanoseconds in a duration, saturating at `u64::MAX`.
+#[inline]
+fn saturating_nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// A builder for an [`ExpiringMap`].
+///
+/// # Examples
+///
+/// ```rust
+/// use papaya::expiring::{ExpiringMap, SweepMode};
+/// use papaya::ResizeMode;
+/// use std::time::Duration;
+///
+/// let map: ExpiringMap<i32, i32> = ExpiringMap::builder()
+///     // Set the initial capacity.
+///     .capacity(2048)
+///     // Set the TTL used by `insert`.
+///     .default_ttl(Duration::from_secs(60))
+///     // Set the sweep mode.
+///     .sweep_mode(SweepMode::Incremental(128))
+///     // Set the resize mode.
+///     .resize_mode(ResizeMode::Blocking)
+///     // Construct the map.
+///     .build();
+/// ```
+pub struct ExpiringMapBuilder<K, V, S = RandomState, T = SystemClock> {
+    builder: HashMapBuilder<K, Slot<V>, S>,
+    clock: T,
+    default_ttl: Option<Duration>,
+    sweep_mode: SweepMode,
+}
+
+impl<K, V> ExpiringMapBuilder<K, V> {
+    /// Set the hash builder used to hash keys.
+    ///
+    /// See [`HashMapBuilder::hasher`] for details.
+    pub fn hasher<S>(self, hasher: S) -> ExpiringMapBuilder<K, V, S> {
+        ExpiringMapBuilder {
+            builder: self.builder.hasher(hasher),
+            clock: self.clock,
+            default_ttl: self.default_ttl,
+            sweep_mode: self.sweep_mode,
+        }
+    }
+}
+
+impl<K, V, S, T> ExpiringMapBuilder<K, V, S, T> {
+    /// Set the [`seize::Collector`] used for garbage collection.
+    ///
+    /// See [`HashMapBuilder::collector`] for details.
+    pub fn collector(self, collector: Collector) -> ExpiringMapBuilder<K, V, S, T> {
+        ExpiringMapBuilder {
+            builder: self.builder.collector(collector),
+            ..self
+        }
+    }
+
+    /// Set the initial capacity of the map.
+    ///
+    /// See [`HashMapBuilder::capacity`] for details.
+    pub fn capacity(self, capacity: usize) -> ExpiringMapBuilder<K, V, S, T> {
+        ExpiringMapBuilder {
+            builder: self.builder.capacity(capacity),
+            ..self
+        }
+    }
+
+    /// Set the resizing mode of the map. See [`ResizeMode`] for details.
+    pub fn resize_mode(self, resize_mode: ResizeMode) -> ExpiringMapBuilder<K, V, S, T> {
+        ExpiringMapBuilder {
+            builder: self.builder.resize_mode(resize_mode),
+            ..self
+        }
+    }
+
+    /// Set the time-to-live of entries inserted with [`ExpiringMap::insert`].
+    ///
+    /// By default, entries inserted without an explicit TTL never expire.
+    pub fn default_ttl(self, ttl: Duration) -> ExpiringMapBuilder<K, V, S, T> {
+        ExpiringMapBuilder {
+            default_ttl: Some(ttl),
+            ..self
+        }
+    }
+
+    /// Set the sweeping mode of the map. See [`SweepMode`] for details.
+    pub fn sweep_mode(self, sweep_mode: SweepMode) -> ExpiringMapBuilder<K, V, S, T> {
+        ExpiringMapBuilder { sweep_mode, ..self }
+    }
+
+    /// Set the [`Clock`] used to determine whether entries have expired.
+    pub fn clock<T2>(self, clock: T2) -> ExpiringMapBuilder<K, V, S, T2>
+    where
+        T2: Clock,
+    {
+        ExpiringMapBuilder {
+            clock,
+            builder: self.builder,
+            default_ttl: self.default_ttl,
+            sweep_mode: self.sweep_mode,
+        }
+    }
+}
+
+impl<K, V, S, T> ExpiringMapBuilder<K, V, S, T>
+where
+    T: Clock,
+{
+    /// Construct an [`ExpiringMap`] from the builder, using the configured options.
+    pub fn build(self) -> ExpiringMap<K, V, S, T> {
+        ExpiringMap {
+            map: self.builder.build(),
+            start: self.clock.now(),
+            clock: self.clock,
+            default_ttl: self.default_ttl,
+            sweep_mode: self.sweep_mode,
+            cursor: AtomicUsize::new(0),
+        }
+    }
+}
+
+impl<K, V, S, T> fmt::Debug for ExpiringMapBuilder<K, V, S, T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExpiringMapBuilder")
+            .field("builder", &self.builder)
+            .field("clock", &self.clock)
+            .field("default_ttl", &self.default_ttl)
+            .field("sweep_mode", &self.sweep_mode)
+            .finish()
+    }
+}
+
+impl<K, V> ExpiringMap<K, V> {
+    /// Creates an empty `ExpiringMap`.
+    ///
+    /// Entries inserted with [`insert`](ExpiringMap::insert) into the returned map never
+    /// expire. Use [`builder`](ExpiringMap::builder) to configure a default TTL.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::expiring::ExpiringMap;
+    /// let map: ExpiringMap<&str, i32> = ExpiringMap::new();
+    /// ```
+    pub fn new() -> ExpiringMap<K, V> {
+        ExpiringMap::builder().build()
+    }
+
+    /// Returns a builder for an `ExpiringMap`.
+    ///
+    /// The builder can be used for more complex configuration, such as setting a default TTL,
+    /// or using a custom [`Clock`].
+    pub fn builder() -> ExpiringMapBuilder<K, V> {
+        ExpiringMapBuilder {
+            builder: HashMap::builder(),
+            clock: SystemClock,
+            default_ttl: None,
+            sweep_mode: SweepMode::default(),
+        }
+    }
+}
+
+impl<K, V> Default for ExpiringMap<K, V> {
+    fn default() -> Self {
+        ExpiringMap::new()
+    }
+}
+
+impl<K, V, S, T> ExpiringMap<K, V, S, T> {
+    /// Returns a pinned reference to the map.
+    ///
+    /// The returned reference manages a guard internally, preventing garbage collection
+    /// for as long as it is held. See the [crate-level documentation](crate#usage) for details.
+    #[inline]
+    pub fn pin(&self) -> ExpiringMapRef<'_, K, V, S, T, LocalGuard<'_>> {
+        ExpiringMapRef {
+            guard: self.guard(),
+            map: self,
+        }
+    }
+
+    /// Returns a guard for use with this map.
+    ///
+    /// Note that holding on to a guard prevents garbage collection.
+    /// See the [crate-level documentation](crate#usage) for details.
+    #[inline]
+    pub fn guard(&self) -> LocalGuard<'_> {
+        self.map.guard()
+    }
+}
+
+impl<K, V, S, T> ExpiringMap<K, V, S, T>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    T: Clock,
+{
+    /// Returns the number of entries in the map.
+    ///
+    /// Note that this includes expired entries that have not yet been removed from the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map is empty. Otherwise returns `false`.
+    ///
+    /// Note that a map containing only expired entries is not considered empty until the
+    /// entries are removed.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the current time, in nanoseconds since the creation of the map.
+    #[inline]
+    fn now(&self) -> u64 {
+        saturating_nanos(self.clock.now().saturating_duration_since(self.start))
+    }
+
+    /// Returns the deadline of an entry inserted now with the given TTL.
+    #[inline]
+    fn deadline(&self, ttl: Option<Duration>) -> u64 {
+        match ttl {
+            Some(ttl) => self
+                .now()
+                .saturating_add(saturating_nanos(ttl))
+                .min(NEVER - 1),
+            None => NEVER,
+        }
+    }
+
+    /// Returns `true` if the map contains an unexpired value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q, guard: &impl Guard) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.get(key, guard).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key, if it has not expired.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::expiring::{ExpiringMap, ManualClock};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let map = ExpiringMap::builder().clock(clock.clone()).build();
+    ///
+    /// map.pin().insert_with_ttl(1, "a", Duration::from_secs(1));
+    /// assert_eq!(map.pin().get(&1), Some(&"a"));
+    ///
+    /// clock.advance(Duration::from_secs(1));
+    /// assert_eq!(map.pin().get(&1), None);
+    /// ```
+    #[inline]
+    pub fn get<'g, Q>(&self, key: &Q, guard: &'g impl Guard) -> Option<&'g V>
+    where
+        K: 'g,
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let slot = self.map.get(key, guard)?;
+        (!slot.is_expired(|| self.now())).then_some(&slot.value)
+    }
+
+    /// Inserts a key-value pair into the map, expiring after the default TTL.
+    ///
+    /// The entry never expires if the map was not configured with a
+    /// [default TTL](ExpiringMapBuilder::default_ttl).
+    ///
+    /// If the map did not have an unexpired value for this key, `None` is returned. Otherwise,
+    /// the value is updated, and a reference to the old value is returned.
+    #[inline]
+    pub fn insert<'g>(&self, key: K, value: V, guard: &'g impl Guard) -> Option<&'g V> {
+        self.insert_inner(key, value, self.default_ttl, guard)
+    }
+
+    /// Inserts a key-value pair into the map, expiring after the given TTL.
+    ///
+    /// If the map did not have an unexpired value for this key, `None` is returned. Otherwise,
+    /// the value is updated, and a reference to the old value is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::expiring::{ExpiringMap, ManualClock};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let map = ExpiringMap::builder().clock(clock.clone()).build();
+    /// let map = map.pin();
+    ///
+    /// assert_eq!(map.insert_with_ttl(1, "a", Duration::from_secs(1)), None);
+    /// assert_eq!(map.insert_with_ttl(1, "b", Duration::from_secs(1)), Some(&"a"));
+    ///
+    /// // Expired entries are not returned.
+    /// clock.advance(Duration::from_secs(1));
+    /// assert_eq!(map.insert_with_ttl(1, "c", Duration::from_secs(1)), None);
+    /// ```
+    #[inline]
+    pub fn insert_with_ttl<'g>(
+        &self,
+        key: K,
+        value: V,
+        ttl: Duration,
+        guard: &'g impl Guard,
+    ) -> Option<&'g V> {
+        self.insert_inner(key, value, Some(ttl), guard)
+    }
+
+    #[inline]
+    fn insert_inner<'g>(
+        &self,
+        key: K,
+        value: V,
+        ttl: Option<Duration>,
+        guard: &'g impl Guard,
+    ) -> Option<&'g V> {
+        let slot = Slot {
+            value,
+            deadline: AtomicU64::new(self.deadline(ttl)),
+        };
+
+        let old = self.map.insert(key, slot, guard);
+        self.sweep(guard);
+
+        let old = old?;
+        (!old.is_expired(|| self.now())).then_some(&old.value)
+    }
+
+    /// Resets the time-to-live of an entry, returning `true` if the entry was refreshed.
+    ///
+    /// The entry expires after `ttl` from now, regardless of its previous deadline. Expired
+    /// entries cannot be refreshed, in which case `false` is returned.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::expiring::{ExpiringMap, ManualClock};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let map = ExpiringMap::builder().clock(clock.clone()).build();
+    /// let map = map.pin();
+    ///
+    /// map.insert_with_ttl(1, "a", Duration::from_secs(2));
+    /// clock.advance(Duration::from_secs(1));
+    /// assert!(map.refresh_ttl(&1, Duration::from_secs(2)));
+    ///
+    /// clock.advance(Duration::from_secs(1));
+    /// assert_eq!(map.get(&1), Some(&"a"));
+    ///
+    /// clock.advance(Duration::from_secs(1));
+    /// assert!(!map.refresh_ttl(&1, Duration::from_secs(2)));
+    /// assert_eq!(map.get(&1), None);
+    /// ```
+    #[inline]
+    pub fn refresh_ttl<Q>(&self, key: &Q, ttl: Duration, guard: &impl Guard) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        match self.map.get(key, guard) {
+            Some(slot) => {
+                let now = self.now();
+                let deadline = now.saturating_add(saturating_nanos(ttl)).min(NEVER - 1);
+                slot.refresh(now, deadline)
+            }
+            None => false,
+        }
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was previously
+    /// in the map and had not expired.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    #[inline]
+    pub fn remove<'g, Q>(&self, key: &Q, guard: &'g impl Guard) -> Option<&'g V>
+    where
+        K: 'g,
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let old = self.map.remove(key, guard);
+        self.sweep(guard);
+
+        let old = old?;
+        (!old.is_expired(|| self.now())).then_some(&old.value)
+    }
+
+    /// Removes all expired entries from the map.
+    ///
+    /// Unlike incremental sweeping, this method scans the entire table, and will block until
+    /// any in-progress resizes are completed. It may be called periodically from a background
+    /// thread, especially if the map is configured with [`SweepMode::Manual`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::expiring::{ExpiringMap, ManualClock, SweepMode};
+    /// use std::time::Duration;
+    ///
+    /// let clock = ManualClock::new();
+    /// let map = ExpiringMap::builder()
+    ///     .clock(clock.clone())
+    ///     .sweep_mode(SweepMode::Manual)
+    ///     .build();
+    /// let map = map.pin();
+    ///
+    /// map.insert_with_ttl(1, "a", Duration::from_secs(1));
+    /// map.insert(2, "b");
+    ///
+    /// clock.advance(Duration::from_secs(1));
+    /// assert_eq!(map.len(), 2);
+    ///
+    /// map.reap_expired();
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    #[inline]
+    pub fn reap_expired(&self, guard: &impl Guard) {
+        let now = self.now();
+        self.map.retain(|_, slot| !slot.try_reap(now), guard);
+    }
+
+    /// Sweeps a chunk of the table for expired entries, if incremental sweeping is enabled.
+    #[inline]
+    fn sweep(&self, guard: &impl Guard) {
+        let SweepMode::Incremental(chunk) = self.sweep_mode else {
+            return;
+        };
+
+        let cursor = self.cursor.fetch_add(chunk, Ordering::Relaxed);
+        let now = self.now();
+
+        self.map.raw.retain_chunk(
+            cursor,
+            chunk,
+            |_, slot| !slot.try_reap(now),
+            self.map.raw.verify(guard),
+        );
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    #[inline]
+    pub fn clear(&self, guard: &impl Guard) {
+        self.map.clear(guard)
+    }
+
+    /// An iterator visiting all unexpired key-value pairs in arbitrary order.
+    /// The iterator element type is `(&K, &V)`.
+    ///
+    /// Entries are checked for expiration against the time at which the iterator was created.
+    ///
+    /// Note that this method will block until any in-progress resizes are
+    /// completed before proceeding. See the [consistency](crate#consistency)
+    /// section for details.
+    #[inline]
+    pub fn iter<'g, G>(&self, guard: &'g G) -> Iter<'g, K, V, G>
+    where
+        G: Guard,
+    {
+        Iter {
+            now: self.now(),
+            raw: self.map.iter(guard),
+        }
+    }
+}
+
+impl<K, V, S, T> fmt::Debug for ExpiringMap<K, V, S, T>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    T: Clock,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let guard = self.guard();
+        f.debug_map().entries(self.iter(&guard)).finish()
+    }
+}
+
+/// A pinned reference to an [`ExpiringMap`].
+///
+/// This type is created with [`ExpiringMap::pin`] and can be used to easily access an
+/// [`ExpiringMap`] without explicitly managing a guard. See the
+/// [crate-level documentation](crate#usage) for details.
+pub struct ExpiringMapRef<'map, K, V, S, T, G> {
+    guard: G,
+    map: &'map ExpiringMap<K, V, S, T>,
+}
+
+impl<'map, K, V, S, T, G> ExpiringMapRef<'map, K, V, S, T, G>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    T: Clock,
+    G: Guard,
+{
+    /// Returns a reference to the inner [`ExpiringMap`].
+    #[inline]
+    pub fn map(&self) -> &'map ExpiringMap<K, V, S, T> {
+        self.map
+    }
+
+    /// Returns the number of entries in the map.
+    ///
+    /// See [`ExpiringMap::len`] for details.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map is empty. Otherwise returns `false`.
+    ///
+    /// See [`ExpiringMap::is_empty`] for details.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns `true` if the map contains an unexpired value for the specified key.
+    ///
+    /// See [`ExpiringMap::contains_key`] for details.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key, if it has not expired.
+    ///
+    /// See [`ExpiringMap::get`] for details.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.map.get(key, &self.guard)
+    }
+
+    /// Inserts a key-value pair into the map, expiring after the default TTL.
+    ///
+    /// See [`ExpiringMap::insert`] for details.
+    #[inline]
+    pub fn insert(&self, key: K, value: V) -> Option<&V> {
+        self.map.insert(key, value, &self.guard)
+    }
+
+    /// Inserts a key-value pair into the map, expiring after the given TTL.
+    ///
+    /// See [`ExpiringMap::insert_with_ttl`] for details.
+    #[inline]
+    pub fn insert_with_ttl(&self, key: K, value: V, ttl: Duration) -> Option<&V> {
+        self.map.insert_with_ttl(key, value, ttl, &self.guard)
+    }
+
+    /// Resets the time-to-live of an entry, returning `true` if the entry was refreshed.
+    ///
+    /// See [`ExpiringMap::refresh_ttl`] for details.
+    #[inline]
+    pub fn refresh_ttl<Q>(&self, key: &Q, ttl: Duration) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.map.refresh_ttl(key, ttl, &self.guard)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key was previously
+    /// in the map and had not expired.
+    ///
+    /// See [`ExpiringMap::remove`] for details.
+    #[inline]
+    pub fn remove<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.map.remove(key, &self.guard)
+    }
+
+    /// Removes all expired entries from the map.
+    ///
+    /// See [`ExpiringMap::reap_expired`] for details.
+    #[inline]
+    pub fn reap_expired(&self) {
+        self.map.reap_expired(&self.guard)
+    }
+
+    /// Clears the map, removing all key-value pairs.
+    ///
+    /// See [`ExpiringMap::clear`] for details.
+    #[inline]
+    pub fn clear(&self) {
+        self.map.clear(&self.guard)
+    }
+
+    /// An iterator visiting all unexpired key-value pairs in arbitrary order.
+    ///
+    /// See [`ExpiringMap::iter`] for details.
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, K, V, G> {
+        self.map.iter(&self.guard)
+    }
+}
+
+impl<K, V, S, T, G> fmt::Debug for ExpiringMapRef<'_, K, V, S, T, G>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    T: Clock,
+    G: Guard,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// An iterator over the unexpired entries of an [`ExpiringMap`].
+///
+/// This struct is created by the [`iter`](ExpiringMap::iter) method on [`ExpiringMap`].
+/// See its documentation for details.
+pub struct Iter<'g, K, V, G> {
+    now: u64,
+    raw: map::Iter<'g, K, Slot<V>, G>,
+}
+
+impl<'g, K: 'g, V: 'g, G> Iterator for Iter<'g, K, V, G>
+where
+    G: Guard,
+{
+    type Item = (&'g K, &'g V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (key, slot) = self.raw.next()?;
+            if !slot.is_expired(|| self.now) {
+                return Some((key, &slot.value));
+            }
+        }
+    }
+}
+
+impl<K, V, G> fmt::Debug for Iter<'_, K, V, G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Iter").field("now", &self.now).finish()
+    }
+}
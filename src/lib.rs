@@ -80,6 +80,8 @@
 //!
 //! Aggregate operations, such as iterators, rely on a weak snapshot of the table and return results reflecting the state of the table at or some point after the creation of the iterator. This means that they may, but are not guaranteed to, reflect concurrent modifications to the table that occur during iteration. Similarly, operations such as `clear` and `clone` rely on iteration and may not produce "perfect" results if the map is being concurrently modified.
 //!
+//! A consistent view of the map as of a single point in time can instead be obtained with [`HashMap::snapshot`], which requires the map to be [versioned](HashMapBuilder::versioned).
+//!
 //! Note that to obtain a stable snapshot of the table, aggregate table operations require completing any in-progress resizes. If you rely heavily on iteration or similar operations you should consider configuring [`ResizeMode::Blocking`].
 //!
 //! # Atomic Operations
@@ -129,6 +131,8 @@
 //!
 //! Atomic operations are extremely powerful but also easy to misuse. They may be less efficient than update mechanisms tailored for the specific type of data in the map. For example, concurrent counters should avoid using `update` and instead use `AtomicUsize`. Entries that are frequently modified may also benefit from fine-grained locking.
 //!
+//! Operations that must atomically modify multiple keys, possibly across multiple maps, can be performed with optimistic transactions. See the [`txn`] module for details.
+//!
 //! # Async Support
 //!
 //! By default, a pinned map guard does not implement `Send` as it is tied to the current thread, similar to a lock guard. This leads to an issue in work-stealing schedulers as guards are not valid across `.await` points.
@@ -212,6 +216,11 @@
 //!
 //! `papaya` aims to provide predictable and consistent latency across all operations. Most operations are lock-free, and those that aren't only block under rare and constrained conditions. `papaya` also features [incremental resizing](ResizeMode). Predictable latency is an important part of performance that doesn't often show up in benchmarks, but has significant implications for real-world usage.
 //!
+//! Resizes are the main source of latency spikes, and can be monitored with a [`MapObserver`]. Two optional features also provide ready-made integrations:
+//!
+//! - `tracing` emits [`tracing`](https://docs.rs/tracing) spans for table allocation (`alloc_table`), every chunk copied during a resize (`copy_chunk`), and for threads that are blocked (`park`).
+//! - `metrics` exports gauges and counters through the [`metrics`](https://docs.rs/metrics) crate for maps configured with `HashMapBuilder::metrics`.
+//!
 //! [benchmarks]: https://github.com/ibraheemdev/papaya/blob/master/BENCHMARKS.md
 
 #![deny(
@@ -227,17 +236,34 @@
 // Clippy trips up with pollyfills.
 #![allow(clippy::incompatible_msrv)]
 
+mod cache;
+mod changes;
 mod map;
+mod observer;
 mod raw;
 mod set;
+mod snapshot;
+mod stats;
+
+pub mod expiring;
+pub mod txn;
 
+#[cfg(feature = "rayon")]
+mod rayon_impls;
 #[cfg(feature = "serde")]
 mod serde_impls;
 
+pub use cache::{Cache, CacheBuilder, CacheRef};
+pub use changes::{Change, ChangeStream};
 pub use equivalent::Equivalent;
 pub use map::{
-    Compute, HashMap, HashMapBuilder, HashMapRef, Iter, Keys, OccupiedError, Operation, ResizeMode,
-    Values,
+    CompareExchangeError, Compute, Drain, EntryRef, HashMap, HashMapBuilder, HashMapRef, HashedKey,
+    IntoIter, Iter, IterMut, Keys, OccupiedError, Operation, ResizeMode, Values, ValuesMut,
 };
+pub use observer::MapObserver;
+#[cfg(feature = "rayon")]
+pub use rayon_impls::{ParIter, ParKeys, ParSetIter, ParValues};
 pub use seize::{Guard, LocalGuard, OwnedGuard};
 pub use set::{HashSet, HashSetBuilder, HashSetRef};
+pub use snapshot::{Snapshot, SnapshotIter};
+pub use stats::{MapStats, ResizeStats};
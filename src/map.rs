@@ -1,11 +1,17 @@
+use crate::changes::ChangeStream;
+use crate::observer::MapObserver;
 use crate::raw::utils::MapGuard;
 use crate::raw::{self, InsertResult};
+use crate::snapshot::Snapshot;
+use crate::stats::MapStats;
 use crate::Equivalent;
 use seize::{Collector, Guard, LocalGuard, OwnedGuard};
 
 use std::borrow::Borrow;
 use std::collections::hash_map::RandomState;
+use std::convert::Infallible;
 use std::fmt;
+use std::future::{self, Future};
 use std::hash::{BuildHasher, Hash};
 use std::marker::PhantomData;
 use std::sync::Arc;
@@ -19,7 +25,7 @@ pub struct HashMap<K, V, S = RandomState, C = Collector>
 where
     C: Borrow<Collector>,
 {
-    raw: raw::HashMap<K, V, S, C>,
+    pub(crate) raw: raw::HashMap<K, V, S, C>,
 }
 
 // Safety: `HashMap` acts as a single-threaded collection on a single thread.
@@ -69,6 +75,10 @@ where
     capacity: usize,
     collector: C,
     resize_mode: ResizeMode,
+    versioned: bool,
+    cache_hashes: bool,
+    observer: Option<Arc<dyn MapObserver>>,
+    metrics: Option<Arc<str>>,
     _kv: PhantomData<(K, V)>,
 }
 
@@ -88,6 +98,10 @@ impl<K, V> HashMapBuilder<K, V> {
             capacity: self.capacity,
             collector: self.collector,
             resize_mode: self.resize_mode,
+            versioned: self.versioned,
+            cache_hashes: self.cache_hashes,
+            observer: self.observer,
+            metrics: self.metrics,
             _kv: PhantomData,
         }
     }
@@ -106,6 +120,10 @@ impl<K, V, S> HashMapBuilder<K, V, S> {
             hasher: self.hasher,
             capacity: self.capacity,
             resize_mode: self.resize_mode,
+            versioned: self.versioned,
+            cache_hashes: self.cache_hashes,
+            observer: self.observer,
+            metrics: self.metrics,
             _kv: PhantomData,
         }
     }
@@ -131,6 +149,10 @@ where
             hasher: self.hasher,
             capacity: self.capacity,
             resize_mode: self.resize_mode,
+            versioned: self.versioned,
+            cache_hashes: self.cache_hashes,
+            observer: self.observer,
+            metrics: self.metrics,
             _kv: PhantomData,
         }
     }
@@ -151,6 +173,10 @@ where
             hasher: self.hasher,
             collector: self.collector,
             resize_mode: self.resize_mode,
+            versioned: self.versioned,
+            cache_hashes: self.cache_hashes,
+            observer: self.observer,
+            metrics: self.metrics,
             _kv: PhantomData,
         }
     }
@@ -162,15 +188,127 @@ where
             hasher: self.hasher,
             capacity: self.capacity,
             collector: self.collector,
+            versioned: self.versioned,
+            cache_hashes: self.cache_hashes,
+            observer: self.observer,
+            metrics: self.metrics,
+            _kv: PhantomData,
+        }
+    }
+
+    /// Enable per-entry version stamps.
+    ///
+    /// When enabled, every entry written to the map is stamped with a version taken from
+    /// a map-wide clock. Versions strictly increase with every write, so an entry with the
+    /// same key and version is guaranteed to be unchanged. Versions can be read with
+    /// [`HashMap::get_versioned`]. Versioned maps also maintain a
+    /// [modification count](HashMap::modification_count).
+    ///
+    /// Versioning requires two extra words of memory per entry, which are shared with
+    /// [hash caching](HashMapBuilder::cache_hashes), and is disabled by default. To support
+    /// [snapshots](HashMap::snapshot), every write to a versioned map also increments a
+    /// per-thread counter, and writes made while a snapshot is alive acquire a lock for the
+    /// key being written.
+    pub fn versioned(self, versioned: bool) -> Self {
+        HashMapBuilder {
+            versioned,
+            hasher: self.hasher,
+            capacity: self.capacity,
+            collector: self.collector,
+            resize_mode: self.resize_mode,
+            cache_hashes: self.cache_hashes,
+            observer: self.observer,
+            metrics: self.metrics,
+            _kv: PhantomData,
+        }
+    }
+
+    /// Cache the hash of every key in its entry.
+    ///
+    /// When enabled, the hash of a key is computed once when it is inserted and reused when
+    /// the entry is copied during a resize, instead of rehashing the key. The cached hash is
+    /// also compared before keys, avoiding expensive comparisons with keys that have a
+    /// different hash. This can significantly reduce the latency of resizes for maps with
+    /// keys that are expensive to hash, such as large strings.
+    ///
+    /// Hash caching requires two extra words of memory per entry, which are shared with
+    /// [versioning](HashMapBuilder::versioned), and is disabled by default.
+    pub fn cache_hashes(self, cache_hashes: bool) -> Self {
+        HashMapBuilder {
+            cache_hashes,
+            hasher: self.hasher,
+            capacity: self.capacity,
+            collector: self.collector,
+            resize_mode: self.resize_mode,
+            versioned: self.versioned,
+            observer: self.observer,
+            metrics: self.metrics,
+            _kv: PhantomData,
+        }
+    }
+
+    /// Set an observer to be notified of internal events, such as resizes.
+    ///
+    /// This can be used to diagnose latency spikes caused by resizing or blocking. See
+    /// [`MapObserver`] for details. By default, no observer is set.
+    pub fn observer(self, observer: impl MapObserver + 'static) -> Self {
+        HashMapBuilder {
+            observer: Some(Arc::new(observer)),
+            hasher: self.hasher,
+            capacity: self.capacity,
+            collector: self.collector,
+            resize_mode: self.resize_mode,
+            versioned: self.versioned,
+            cache_hashes: self.cache_hashes,
+            metrics: self.metrics,
+            _kv: PhantomData,
+        }
+    }
+
+    /// Export metrics for the map through the [`metrics`](https://docs.rs/metrics) crate,
+    /// labeled with the given `name`.
+    ///
+    /// The following metrics are recorded with a `map` label set to `name`:
+    ///
+    /// - `papaya_capacity`: A gauge of the number of slots in the root table.
+    /// - `papaya_len`: A gauge of the number of entries in the map.
+    /// - `papaya_resizes_in_progress`: A gauge of the number of resizes in progress.
+    /// - `papaya_promotions`: A counter of the number of completed resizes.
+    ///
+    /// Metrics are recorded on slow paths such as resizes, so they add no overhead to
+    /// reads or writes. As a result, the length of the map is only sampled when a resize
+    /// starts or completes, and occasionally on removals. Use [`HashMap::record_metrics`]
+    /// to record the current capacity and length on demand, for example before metrics
+    /// are scraped.
+    ///
+    /// Names should be unique among live maps, as maps with the same name will overwrite
+    /// each other's gauges. By default, metrics are not exported.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(self, name: impl Into<Arc<str>>) -> Self {
+        HashMapBuilder {
+            metrics: Some(name.into()),
+            hasher: self.hasher,
+            capacity: self.capacity,
+            collector: self.collector,
+            resize_mode: self.resize_mode,
+            versioned: self.versioned,
+            cache_hashes: self.cache_hashes,
+            observer: self.observer,
             _kv: PhantomData,
         }
     }
 
     /// Construct a [`HashMap`] from the builder, using the configured options.
     pub fn build(self) -> HashMap<K, V, S, C> {
-        HashMap {
-            raw: raw::HashMap::new(self.capacity, self.hasher, self.collector, self.resize_mode),
-        }
+        let raw = raw::HashMap::new(self.capacity, self.hasher, self.collector, self.resize_mode)
+            .versioned(self.versioned)
+            .cache_hashes(self.cache_hashes)
+            .observer(self.observer);
+
+        #[cfg(feature = "metrics")]
+        let raw = raw.metrics(self.metrics);
+
+        HashMap { raw }
     }
 }
 
@@ -180,6 +318,10 @@ impl<K, V, S> fmt::Debug for HashMapBuilder<K, V, S> {
             .field("capacity", &self.capacity)
             .field("collector", &self.collector)
             .field("resize_mode", &self.resize_mode)
+            .field("versioned", &self.versioned)
+            .field("cache_hashes", &self.cache_hashes)
+            .field("observer", &self.observer.is_some())
+            .field("metrics", &self.metrics)
             .finish()
     }
 }
@@ -268,6 +410,10 @@ impl<K, V> HashMap<K, V> {
             hasher: RandomState::default(),
             collector: Collector::default(),
             resize_mode: ResizeMode::default(),
+            versioned: false,
+            cache_hashes: false,
+            observer: None,
+            metrics: None,
             _kv: PhantomData,
         }
     }
@@ -454,6 +600,134 @@ where
         self.len() == 0
     }
 
+    /// Returns the number of modifications made to the map.
+    ///
+    /// Modifications are only counted if the map is [versioned](HashMapBuilder::versioned),
+    /// otherwise the count is always zero. This avoids the cost of maintaining the count for
+    /// maps that do not use it.
+    ///
+    /// The count is incremented by every successful insertion, update, and removal, and is
+    /// never decremented. Readers can poll the modification count to avoid re-scanning a map
+    /// that has not changed. If the count is unchanged between two calls, no entries were
+    /// modified in between. Note that mutable access to a value, such as through
+    /// [`get_mut`](HashMap::get_mut), is counted as a modification.
+    ///
+    /// Similar to [`len`](HashMap::len), the count is maintained across shards and may
+    /// not reflect modifications that are concurrently in progress.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::builder().versioned(true).build();
+    /// assert_eq!(map.modification_count(), 0);
+    ///
+    /// map.pin().insert(1, "a");
+    /// map.pin().insert(1, "b");
+    /// map.pin().remove(&1);
+    /// assert_eq!(map.modification_count(), 3);
+    /// ```
+    #[inline]
+    pub fn modification_count(&self) -> u64 {
+        self.raw.modification_count()
+    }
+
+    /// Returns statistics about the internal state of the map.
+    ///
+    /// The statistics include the occupancy of the underlying table, a histogram of probe
+    /// lengths, and the progress of any in-progress resize, which can be useful for tuning
+    /// the map's capacity and hasher. See [`MapStats`] for details.
+    ///
+    /// Collecting statistics requires walking the entire table, and so takes time linear in
+    /// the capacity of the map. The statistics are not a consistent view if the map is
+    /// modified concurrently.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// for i in 0..100 {
+    ///     map.pin().insert(i, i);
+    /// }
+    ///
+    /// let stats = map.stats(&map.guard());
+    /// assert_eq!(stats.len, 100);
+    /// assert_eq!(stats.live, 100);
+    /// assert!(stats.capacity >= 100);
+    /// assert_eq!(stats.probe_lengths.iter().sum::<usize>(), 100);
+    /// ```
+    #[inline]
+    pub fn stats(&self, guard: &impl Guard) -> MapStats {
+        self.raw.stats(self.raw.verify(guard))
+    }
+
+    /// Records the current capacity and length of the map to the exported metrics.
+    ///
+    /// Metrics are otherwise only recorded on slow paths such as resizes. This method has
+    /// no effect if metrics were not enabled with [`HashMapBuilder::metrics`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::builder().metrics("users").build();
+    /// map.pin().insert(1, "a");
+    ///
+    /// // Record the length before metrics are scraped.
+    /// map.record_metrics(&map.guard());
+    /// ```
+    #[cfg(feature = "metrics")]
+    #[inline]
+    pub fn record_metrics(&self, guard: &impl Guard) {
+        self.raw.record_metrics(self.raw.verify(guard))
+    }
+
+    /// Subscribes to changes made to the map.
+    ///
+    /// The returned [`ChangeStream`] yields a [`Change`](crate::Change) for every entry that is inserted,
+    /// updated, or removed after this call, including through [`retain`](HashMap::retain),
+    /// [`clear`](HashMap::clear), [`Extend`], and the methods that take `&mut self`. Changes to a
+    /// given key are yielded in the order they took effect in the map, while changes to different
+    /// keys may be interleaved arbitrarily. Mutations that are concurrent with the call to
+    /// `subscribe` may or may not be observed.
+    ///
+    /// Mutable access to a value, such as through [`get_mut`](HashMap::get_mut) or
+    /// [`iter_mut`](HashMap::iter_mut), is not observable and is not reported.
+    ///
+    /// Keys and values are cloned into every event. Maps with no active subscriptions do not
+    /// pay any cost for change tracking. Otherwise, writes acquire a lock that orders the write
+    /// with respect to other writes to the same key, and reads are unaffected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::{Change, HashMap};
+    ///
+    /// let map = HashMap::new();
+    /// let changes = map.subscribe();
+    ///
+    /// map.pin().insert(1, "a");
+    /// map.pin().insert(1, "b");
+    /// map.pin().remove(&1);
+    ///
+    /// assert_eq!(changes.try_recv(), Some(Change::Inserted { key: 1, value: "a" }));
+    /// assert_eq!(changes.try_recv(), Some(Change::Updated { key: 1, old: "a", new: "b" }));
+    /// assert_eq!(changes.try_recv(), Some(Change::Removed { key: 1, value: "b" }));
+    /// assert_eq!(changes.try_recv(), None);
+    /// ```
+    #[inline]
+    pub fn subscribe(&self) -> ChangeStream<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.raw.subscribe()
+    }
+
     /// Returns `true` if the map contains a value for the specified key.
     ///
     /// The key may be any borrowed form of the map's key type, but
@@ -540,85 +814,192 @@ where
         self.raw.get(key, self.raw.verify(guard))
     }
 
-    /// Inserts a key-value pair into the map.
-    ///
-    /// If the map did not have this key present, [`None`] is returned.
-    ///
-    /// If the map did have this key present, the value is updated, and the old
-    /// value is returned. The key is not updated, though; this matters for
-    /// types that can be `==` without being identical. See the [standard library
-    /// documentation] for details.
+    /// Hashes a key with the map's hasher, returning a [`HashedKey`] that can be passed to
+    /// the `*_hashed` family of methods.
     ///
-    /// [standard library documentation]: https://doc.rust-lang.org/std/collections/index.html#insert-and-complex-keys
+    /// This avoids hashing the same key more than once when performing multiple operations
+    /// on it, such as a read followed by a [`compute_hashed`](HashMap::compute_hashed). Reads
+    /// accept a borrowed key, while writes take ownership of the key. A borrowed key can be
+    /// obtained from an owned one with [`HashedKey::as_ref`].
     ///
     /// # Examples
     ///
     /// ```
-    /// use papaya::HashMap;
+    /// use papaya::{HashMap, Operation};
     ///
     /// let map = HashMap::new();
-    /// assert_eq!(map.pin().insert(37, "a"), None);
-    /// assert_eq!(map.pin().is_empty(), false);
+    /// let map = map.pin();
     ///
-    /// map.pin().insert(37, "b");
-    /// assert_eq!(map.pin().insert(37, "c"), Some(&"b"));
-    /// assert_eq!(map.pin().get(&37), Some(&"c"));
+    /// let key = map.hash_key(1);
+    /// assert_eq!(map.insert_hashed(key, "a"), None);
+    /// assert_eq!(map.get_hashed(key.as_ref()), Some(&"a"));
+    /// map.compute_hashed(key, |_| Operation::Insert::<_, ()>("b"));
+    /// assert_eq!(map.remove_hashed(key.as_ref()), Some(&"b"));
     /// ```
     #[inline]
-    pub fn insert<'g>(&self, key: K, value: V, guard: &'g impl Guard) -> Option<&'g V> {
-        match self.raw.insert(key, value, true, self.raw.verify(guard)) {
+    pub fn hash_key<Q>(&self, key: Q) -> HashedKey<Q>
+    where
+        Q: Hash,
+    {
+        let hash = self.raw.hasher.hash_one(&key);
+        HashedKey::new(key, hash)
+    }
+
+    /// Returns `true` if the map contains a value for the specified hashed key.
+    ///
+    /// See [`HashedKey`] for details on precomputed hashes.
+    #[inline]
+    pub fn contains_key_hashed<Q>(&self, key: HashedKey<&Q>, guard: &impl Guard) -> bool
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        self.get_key_value_hashed(key, guard).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the hashed key.
+    ///
+    /// See [`HashedKey`] for details on precomputed hashes.
+    #[inline]
+    pub fn get_hashed<'g, Q>(&self, key: HashedKey<&Q>, guard: &'g impl Guard) -> Option<&'g V>
+    where
+        K: 'g,
+        Q: Equivalent<K> + ?Sized,
+    {
+        match self.get_key_value_hashed(key, guard) {
+            Some((_, v)) => Some(v),
+            None => None,
+        }
+    }
+
+    /// Returns the key-value pair corresponding to the hashed key.
+    ///
+    /// See [`HashedKey`] for details on precomputed hashes.
+    #[inline]
+    pub fn get_key_value_hashed<'g, Q>(
+        &self,
+        key: HashedKey<&Q>,
+        guard: &'g impl Guard,
+    ) -> Option<(&'g K, &'g V)>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        self.raw
+            .get_entry_hashed(key.key, key.hash, self.raw.verify(guard))
+            .map(|entry| (&entry.key, &entry.value))
+    }
+
+    /// Inserts a hashed key and value into the map.
+    ///
+    /// See [`HashedKey`] for details on precomputed hashes, and [`insert`](HashMap::insert)
+    /// for details on insertion.
+    #[inline]
+    pub fn insert_hashed<'g>(
+        &self,
+        key: HashedKey<K>,
+        value: V,
+        guard: &'g impl Guard,
+    ) -> Option<&'g V> {
+        match self
+            .raw
+            .insert_with_hash(key.key, value, key.hash, true, self.raw.verify(guard))
+        {
             InsertResult::Inserted(_) => None,
             InsertResult::Replaced(value) => Some(value),
             InsertResult::Error { .. } => unreachable!(),
         }
     }
 
-    /// Tries to insert a key-value pair into the map, and returns
-    /// a reference to the value that was inserted.
+    /// Updates the entry of a hashed key with a compare-and-swap (CAS) function.
     ///
-    /// If the map already had this key present, nothing is updated, and
-    /// an error containing the existing value is returned.
+    /// See [`HashedKey`] for details on precomputed hashes, and [`compute`](HashMap::compute)
+    /// for details on the update function.
+    #[inline]
+    pub fn compute_hashed<'g, F, T>(
+        &self,
+        key: HashedKey<K>,
+        compute: F,
+        guard: &'g impl Guard,
+    ) -> Compute<'g, K, V, T>
+    where
+        F: FnMut(Option<(&'g K, &'g V)>) -> Operation<V, T>,
+    {
+        self.raw
+            .compute_hashed(key.key, key.hash, compute, self.raw.verify(guard))
+    }
+
+    /// Removes a hashed key from the map, returning the value at the key if the key
+    /// was previously in the map.
+    ///
+    /// See [`HashedKey`] for details on precomputed hashes.
+    #[inline]
+    pub fn remove_hashed<'g, Q>(&self, key: HashedKey<&Q>, guard: &'g impl Guard) -> Option<&'g V>
+    where
+        K: 'g,
+        Q: Equivalent<K> + ?Sized,
+    {
+        match self
+            .raw
+            .remove_if_hashed(key.key, key.hash, |_, _| true, self.raw.verify(guard))
+        {
+            Ok(Some((_, value))) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the key-value pair corresponding to the supplied key, along with the
+    /// version of the entry.
+    ///
+    /// Versions are only tracked if the map was created with
+    /// [`HashMapBuilder::versioned`]. Every write to a versioned map stamps the entry with
+    /// a new version that is greater than any previous version, so comparing versions can be
+    /// used to determine whether an entry has changed. If versioning is disabled, the version
+    /// is always `0`.
+    ///
+    /// The supplied key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
     ///
     /// # Examples
     ///
     /// ```
     /// use papaya::HashMap;
     ///
-    /// let map = HashMap::new();
+    /// let map = HashMap::builder().versioned(true).build();
     /// let map = map.pin();
     ///
-    /// assert_eq!(map.try_insert(37, "a").unwrap(), &"a");
+    /// map.insert(1, "a");
+    /// let (_, _, v1) = map.get_versioned(&1).unwrap();
     ///
-    /// let err = map.try_insert(37, "b").unwrap_err();
-    /// assert_eq!(err.current, &"a");
-    /// assert_eq!(err.not_inserted, "b");
+    /// map.insert(1, "a");
+    /// let (_, _, v2) = map.get_versioned(&1).unwrap();
+    /// assert!(v2 > v1);
     /// ```
     #[inline]
-    pub fn try_insert<'g>(
+    pub fn get_versioned<'g, Q>(
         &self,
-        key: K,
-        value: V,
+        key: &Q,
         guard: &'g impl Guard,
-    ) -> Result<&'g V, OccupiedError<'g, V>> {
-        // Safety: Checked the guard above.
-        match self.raw.insert(key, value, false, self.raw.verify(guard)) {
-            InsertResult::Inserted(value) => Ok(value),
-            InsertResult::Error {
-                current,
-                not_inserted,
-            } => Err(OccupiedError {
-                current,
-                not_inserted,
-            }),
-            InsertResult::Replaced(_) => unreachable!(),
-        }
+    ) -> Option<(&'g K, &'g V, u64)>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let entry = self.raw.get_entry(key, self.raw.verify(guard))?;
+
+        // Safety: The entry was read from this map.
+        let version = unsafe { self.raw.version(entry) };
+        Some((&entry.key, &entry.value, version))
     }
 
-    /// Tries to insert a key and value computed from a closure into the map,
-    /// and returns a reference to the value that was inserted.
+    /// Returns references to the values corresponding to each of the keys.
     ///
-    /// If the map already had this key present, nothing is updated, and
-    /// the existing value is returned.
+    /// This is equivalent to calling [`get`](HashMap::get) for every key, but all keys are
+    /// hashed and their table slots prefetched before any lookups are resolved. This allows
+    /// the cache misses of independent lookups to overlap, instead of paying for each one
+    /// in sequence.
+    ///
+    /// The keys may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
     ///
     /// # Examples
     ///
@@ -626,31 +1007,29 @@ where
     /// use papaya::HashMap;
     ///
     /// let map = HashMap::new();
-    /// let map = map.pin();
-    ///
-    /// assert_eq!(map.try_insert_with(37, || "a").unwrap(), &"a");
-    ///
-    /// let current = map.try_insert_with(37, || "b").unwrap_err();
-    /// assert_eq!(current, &"a");
+    /// map.pin().insert(1, "a");
+    /// map.pin().insert(2, "b");
+    /// assert_eq!(map.pin().get_many([&1, &2, &3]), [Some(&"a"), Some(&"b"), None]);
     /// ```
     #[inline]
-    pub fn try_insert_with<'g, F>(
+    pub fn get_many<'g, Q, const N: usize>(
         &self,
-        key: K,
-        f: F,
+        keys: [&Q; N],
         guard: &'g impl Guard,
-    ) -> Result<&'g V, &'g V>
+    ) -> [Option<&'g V>; N]
     where
-        F: FnOnce() -> V,
         K: 'g,
+        Q: Equivalent<K> + Hash + ?Sized,
     {
-        self.raw.try_insert_with(key, f, self.raw.verify(guard))
+        self.raw
+            .get_many(keys, self.raw.verify(guard))
+            .map(|entry| entry.map(|(_, value)| value))
     }
 
-    /// Returns a reference to the value corresponding to the key, or inserts a default value.
+    /// Returns references to the values corresponding to each key in the iterator.
     ///
-    /// If the given key is present, the corresponding value is returned. If it is not present,
-    /// the provided `value` is inserted, and a reference to the newly inserted value is returned.
+    /// Keys are resolved in small batches, with every key in a batch hashed and prefetched
+    /// before being resolved. See [`get_many`](HashMap::get_many) for details.
     ///
     /// # Examples
     ///
@@ -658,14 +1037,269 @@ where
     /// use papaya::HashMap;
     ///
     /// let map = HashMap::new();
-    /// assert_eq!(map.pin().get_or_insert("a", 3), &3);
-    /// assert_eq!(map.pin().get_or_insert("a", 6), &3);
+    /// map.pin().insert(1, "a");
+    /// map.pin().insert(2, "b");
+    ///
+    /// let keys = [1, 2, 3];
+    /// assert_eq!(map.pin().get_batch(&keys), [Some(&"a"), Some(&"b"), None]);
     /// ```
     #[inline]
-    pub fn get_or_insert<'g>(&self, key: K, value: V, guard: &'g impl Guard) -> &'g V {
-        // Note that we use `try_insert` instead of `compute` or `get_or_insert_with` here, as it
-        // allows us to avoid the closure indirection.
-        match self.try_insert(key, value, guard) {
+    pub fn get_batch<'g, 'q, Q, I>(&self, keys: I, guard: &'g impl Guard) -> Vec<Option<&'g V>>
+    where
+        K: 'g,
+        Q: Equivalent<K> + Hash + ?Sized + 'q,
+        I: IntoIterator<Item = &'q Q>,
+    {
+        let entries = self.raw.get_batch(keys, self.raw.verify(guard));
+        entries
+            .into_iter()
+            .map(|entry| entry.map(|(_, value)| value))
+            .collect()
+    }
+
+    /// Waits for a value to be present for the specified key.
+    ///
+    /// The returned future resolves to the value once the key is present in the map. If the key
+    /// is not present when the future is polled, the task is woken when the key is next inserted
+    /// through methods such as [`insert`](HashMap::insert), [`update`](HashMap::update), or
+    /// [`compute`](HashMap::compute).
+    ///
+    /// Note that the guard is held until the future completes, which prevents the reclamation
+    /// of any objects retired by the map in the meantime. Waiting on a [`HashMapRef`] created
+    /// with [`HashMap::pin_owned`] results in a future that is [`Send`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    /// use std::thread;
+    ///
+    /// # let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// let map = HashMap::new();
+    ///
+    /// thread::scope(|s| {
+    ///     s.spawn(|| {
+    ///         map.pin().insert("result", 42);
+    ///     });
+    ///
+    ///     let map = map.pin_owned();
+    ///     let value = runtime.block_on(map.wait_for(&"result"));
+    ///     assert_eq!(value, &42);
+    /// });
+    /// ```
+    #[inline]
+    pub fn wait_for<'g, Q, G>(
+        &'g self,
+        key: &'g Q,
+        guard: &'g G,
+    ) -> impl Future<Output = &'g V> + 'g
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+        G: Guard,
+    {
+        self.wait_until(key, |_| true, guard)
+    }
+
+    /// Waits for the value of the specified key to satisfy a predicate.
+    ///
+    /// The returned future resolves to the value once the key is present in the map and its
+    /// value satisfies the predicate. Otherwise, the task is woken and the predicate is checked
+    /// again whenever the key is next inserted or updated. See [`wait_for`](HashMap::wait_for)
+    /// for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    /// use std::thread;
+    ///
+    /// # let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// let map = HashMap::new();
+    /// map.pin().insert("progress", 0);
+    ///
+    /// thread::scope(|s| {
+    ///     s.spawn(|| {
+    ///         for _ in 0..100 {
+    ///             map.pin().update("progress", |p| p + 1);
+    ///         }
+    ///     });
+    ///
+    ///     let map = map.pin_owned();
+    ///     let value = runtime.block_on(map.wait_until(&"progress", |&p| p >= 50));
+    ///     assert!(*value >= 50);
+    /// });
+    /// ```
+    #[inline]
+    pub fn wait_until<'g, Q, F, G>(
+        &'g self,
+        key: &'g Q,
+        mut predicate: F,
+        guard: &'g G,
+    ) -> impl Future<Output = &'g V> + 'g
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+        F: FnMut(&V) -> bool + 'g,
+        G: Guard,
+    {
+        let guard = self.raw.verify(guard);
+        let mut waiter = self.raw.waiter(key);
+
+        future::poll_fn(move |cx| {
+            self.raw
+                .poll_until(key, &mut predicate, &mut waiter, cx, guard)
+        })
+    }
+
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, [`None`] is returned.
+    ///
+    /// If the map did have this key present, the value is updated, and the old
+    /// value is returned. The key is not updated, though; this matters for
+    /// types that can be `==` without being identical. See the [standard library
+    /// documentation] for details.
+    ///
+    /// [standard library documentation]: https://doc.rust-lang.org/std/collections/index.html#insert-and-complex-keys
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// assert_eq!(map.pin().insert(37, "a"), None);
+    /// assert_eq!(map.pin().is_empty(), false);
+    ///
+    /// map.pin().insert(37, "b");
+    /// assert_eq!(map.pin().insert(37, "c"), Some(&"b"));
+    /// assert_eq!(map.pin().get(&37), Some(&"c"));
+    /// ```
+    #[inline]
+    pub fn insert<'g>(&self, key: K, value: V, guard: &'g impl Guard) -> Option<&'g V> {
+        match self.raw.insert(key, value, true, self.raw.verify(guard)) {
+            InsertResult::Inserted(_) => None,
+            InsertResult::Replaced(value) => Some(value),
+            InsertResult::Error { .. } => unreachable!(),
+        }
+    }
+
+    /// Inserts every key-value pair in the iterator into the map.
+    ///
+    /// Existing values are replaced, as with [`insert`](HashMap::insert). Capacity for the
+    /// entries is reserved up-front, and keys are inserted in small batches, with every key
+    /// in a batch hashed and prefetched before being inserted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert_batch([(1, "a"), (2, "b")]);
+    /// assert_eq!(map.pin().get(&1), Some(&"a"));
+    /// assert_eq!(map.pin().get(&2), Some(&"b"));
+    /// ```
+    #[inline]
+    pub fn insert_batch<I>(&self, entries: I, guard: &impl Guard)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.raw.insert_batch(entries, self.raw.verify(guard))
+    }
+
+    /// Tries to insert a key-value pair into the map, and returns
+    /// a reference to the value that was inserted.
+    ///
+    /// If the map already had this key present, nothing is updated, and
+    /// an error containing the existing value is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let map = map.pin();
+    ///
+    /// assert_eq!(map.try_insert(37, "a").unwrap(), &"a");
+    ///
+    /// let err = map.try_insert(37, "b").unwrap_err();
+    /// assert_eq!(err.current, &"a");
+    /// assert_eq!(err.not_inserted, "b");
+    /// ```
+    #[inline]
+    pub fn try_insert<'g>(
+        &self,
+        key: K,
+        value: V,
+        guard: &'g impl Guard,
+    ) -> Result<&'g V, OccupiedError<'g, V>> {
+        // Safety: Checked the guard above.
+        match self.raw.insert(key, value, false, self.raw.verify(guard)) {
+            InsertResult::Inserted(value) => Ok(value),
+            InsertResult::Error {
+                current,
+                not_inserted,
+            } => Err(OccupiedError {
+                current,
+                not_inserted,
+            }),
+            InsertResult::Replaced(_) => unreachable!(),
+        }
+    }
+
+    /// Tries to insert a key and value computed from a closure into the map,
+    /// and returns a reference to the value that was inserted.
+    ///
+    /// If the map already had this key present, nothing is updated, and
+    /// the existing value is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// let map = map.pin();
+    ///
+    /// assert_eq!(map.try_insert_with(37, || "a").unwrap(), &"a");
+    ///
+    /// let current = map.try_insert_with(37, || "b").unwrap_err();
+    /// assert_eq!(current, &"a");
+    /// ```
+    #[inline]
+    pub fn try_insert_with<'g, F>(
+        &self,
+        key: K,
+        f: F,
+        guard: &'g impl Guard,
+    ) -> Result<&'g V, &'g V>
+    where
+        F: FnOnce() -> V,
+        K: 'g,
+    {
+        self.raw.try_insert_with(key, f, self.raw.verify(guard))
+    }
+
+    /// Returns a reference to the value corresponding to the key, or inserts a default value.
+    ///
+    /// If the given key is present, the corresponding value is returned. If it is not present,
+    /// the provided `value` is inserted, and a reference to the newly inserted value is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// assert_eq!(map.pin().get_or_insert("a", 3), &3);
+    /// assert_eq!(map.pin().get_or_insert("a", 6), &3);
+    /// ```
+    #[inline]
+    pub fn get_or_insert<'g>(&self, key: K, value: V, guard: &'g impl Guard) -> &'g V {
+        // Note that we use `try_insert` instead of `compute` or `get_or_insert_with` here, as it
+        // allows us to avoid the closure indirection.
+        match self.try_insert(key, value, guard) {
             Ok(inserted) => inserted,
             Err(OccupiedError { current, .. }) => current,
         }
@@ -697,6 +1331,147 @@ where
         self.raw.get_or_insert_with(key, f, self.raw.verify(guard))
     }
 
+    /// Returns a reference to the value corresponding to the key, or initializes it with the
+    /// value computed from a closure.
+    ///
+    /// Unlike [`get_or_insert_with`](HashMap::get_or_insert_with), the closure is called at most
+    /// once even if multiple threads call this method concurrently, similar to
+    /// [`OnceLock::get_or_init`](std::sync::OnceLock::get_or_init). The first thread to find the
+    /// key missing runs the initializer, while other threads block until the value is
+    /// inserted. If the initializer panics, the panic is propagated to the caller and another
+    /// thread waiting on the key runs its own initializer.
+    ///
+    /// Note that calling this method from within the initializer for the same key results
+    /// in a deadlock. Keys that are inserted through other methods while the initializer is
+    /// running take precedence over the initialized value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// assert_eq!(map.pin().get_or_init("a", || 3), &3);
+    /// assert_eq!(map.pin().get_or_init("a", || unreachable!()), &3);
+    /// ```
+    #[inline]
+    pub fn get_or_init<'g, F>(&self, key: K, f: F, guard: &'g impl Guard) -> &'g V
+    where
+        F: FnOnce() -> V,
+        K: 'g,
+    {
+        let guard = self.raw.verify(guard);
+
+        match self.raw.get_or_claim(key, guard) {
+            Ok(value) => value,
+            // Note that the claim is released if the initializer panics.
+            Err(claim) => claim.insert(f(), guard),
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key, or inserts a value loaded
+    /// asynchronously.
+    ///
+    /// Unlike [`get_or_insert_with`](HashMap::get_or_insert_with), the value is only loaded
+    /// once even if multiple tasks call this method concurrently. The first task to find the
+    /// key missing claims it and awaits the future returned by `f`, while other tasks wait for
+    /// the value to be inserted. If the loading task is cancelled, another task waiting on the
+    /// key takes over the load.
+    ///
+    /// Note that the guard is held until the future completes, which prevents the reclamation
+    /// of any objects retired by the map in the meantime. Calling this method on a
+    /// [`HashMapRef`] created with [`HashMap::pin_owned`] results in a future that is [`Send`],
+    /// as long as the future returned by `f` is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// # let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// # runtime.block_on(async {
+    /// let map = HashMap::new();
+    /// let map = map.pin_owned();
+    ///
+    /// let value = map.get_or_insert_async("a", || async { 3 }).await;
+    /// assert_eq!(value, &3);
+    ///
+    /// let value = map.get_or_insert_async("a", || async { 6 }).await;
+    /// assert_eq!(value, &3);
+    /// # });
+    /// ```
+    pub async fn get_or_insert_async<'g, F, Fut, G>(&'g self, key: K, f: F, guard: &'g G) -> &'g V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+        G: Guard,
+    {
+        let result = self
+            .get_or_try_insert_async(key, || async { Ok::<_, Infallible>(f().await) }, guard)
+            .await;
+
+        match result {
+            Ok(value) => value,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key, or inserts a value loaded
+    /// asynchronously by a fallible future.
+    ///
+    /// If the future returned by `f` fails, the error is returned and nothing is inserted.
+    /// Another task waiting on the key then takes over the load. See
+    /// [`get_or_insert_async`](HashMap::get_or_insert_async) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// # let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    /// # runtime.block_on(async {
+    /// let map = HashMap::new();
+    /// let map = map.pin_owned();
+    ///
+    /// let value = map.get_or_try_insert_async("a", || async { Err("failed") }).await;
+    /// assert_eq!(value, Err("failed"));
+    /// assert_eq!(map.get("a"), None);
+    ///
+    /// let value = map.get_or_try_insert_async("a", || async { Ok::<_, ()>(3) }).await;
+    /// assert_eq!(value, Ok(&3));
+    /// # });
+    /// ```
+    pub async fn get_or_try_insert_async<'g, F, Fut, E, G>(
+        &'g self,
+        key: K,
+        f: F,
+        guard: &'g G,
+    ) -> Result<&'g V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+        G: Guard,
+    {
+        let raw_guard = self.raw.verify(guard);
+
+        // Wait for the key to be inserted, or claim the right to load it.
+        let claim = {
+            let mut waiter = self.raw.waiter(&key);
+            let mut key = Some(key);
+            let poll =
+                future::poll_fn(|cx| self.raw.poll_load(&mut key, &mut waiter, cx, raw_guard));
+
+            match poll.await {
+                Ok(value) => return Ok(value),
+                Err(claim) => claim,
+            }
+        };
+
+        // Note that the claim is released if the future fails or we are cancelled.
+        let value = f().await?;
+        Ok(claim.insert(value, raw_guard))
+    }
+
     /// Updates an existing entry atomically.
     ///
     /// If the value for the specified `key` is present, the new value is computed and stored the
@@ -844,6 +1619,8 @@ where
     /// assert_eq!(map.compute('A', compute), Compute::Updated {
     ///     old: (&'A', &1),
     ///     new: (&'A', &2),
+    ///     old_version: 0,
+    ///     new_version: 0,
     /// });
     /// assert_eq!(map.compute('A', compute), Compute::Removed(&'A', &2));
     /// ```
@@ -955,31 +1732,282 @@ where
             .remove_if(key, should_remove, self.raw.verify(guard))
     }
 
-    /// Tries to reserve capacity for `additional` more elements to be inserted
-    /// in the `HashMap`.
-    ///
-    /// After calling this method, the table should be able to hold at least `capacity` elements
-    /// before resizing. However, the capacity is an estimate, and the table may prematurely resize
-    /// due to poor hash distribution. The collection may also reserve more space to avoid frequent
-    /// reallocations.
+    /// Replaces the value of an existing key if it is equal to `expected`, returning a
+    /// reference to the new value.
     ///
-    /// # Panics
+    /// This is a specialized form of [`compute`](HashMap::compute) for the common case of
+    /// conditionally updating a value. Note that the value is never inserted if the key is not
+    /// present in the map.
     ///
-    /// Panics if the new allocation size overflows `usize`.
+    /// If the key is not present or its value is not equal to `expected`, an error is returned
+    /// containing the current value and the value that was not inserted.
     ///
     /// # Examples
     ///
     /// ```
-    /// use papaya::HashMap;
+    /// use papaya::{CompareExchangeError, HashMap};
     ///
-    /// let map: HashMap<&str, i32> = HashMap::new();
-    /// map.pin().reserve(10);
-    /// ```
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a");
+    ///
+    /// assert_eq!(map.pin().compare_exchange(1, &"a", "b"), Ok(&"b"));
+    /// assert_eq!(
+    ///     map.pin().compare_exchange(1, &"a", "c"),
+    ///     Err(CompareExchangeError { current: Some(&"b"), new: "c" })
+    /// );
+    /// assert_eq!(
+    ///     map.pin().compare_exchange(2, &"a", "c"),
+    ///     Err(CompareExchangeError { current: None, new: "c" })
+    /// );
+    /// ```
+    #[inline]
+    pub fn compare_exchange<'g>(
+        &self,
+        key: K,
+        expected: &V,
+        new: V,
+        guard: &'g impl Guard,
+    ) -> Result<&'g V, CompareExchangeError<'g, V>>
+    where
+        V: PartialEq,
+    {
+        match self
+            .raw
+            .compare_exchange(key, expected, new, self.raw.verify(guard))
+        {
+            Ok(value) => Ok(value),
+            Err((current, new)) => Err(CompareExchangeError { current, new }),
+        }
+    }
+
+    /// Removes a key from the map if its value is equal to `expected`, returning a reference
+    /// to the removed value.
+    ///
+    /// If the key is not present or its value is not equal to `expected`, an error is returned
+    /// containing the current value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a");
+    ///
+    /// assert_eq!(map.pin().compare_exchange_remove(&1, &"b"), Err(Some(&"a")));
+    /// assert_eq!(map.pin().compare_exchange_remove(&1, &"a"), Ok(&"a"));
+    /// assert_eq!(map.pin().compare_exchange_remove(&1, &"a"), Err(None));
+    /// ```
+    #[inline]
+    pub fn compare_exchange_remove<'g, Q>(
+        &self,
+        key: &Q,
+        expected: &V,
+        guard: &'g impl Guard,
+    ) -> Result<&'g V, Option<&'g V>>
+    where
+        K: 'g,
+        V: PartialEq,
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let should_remove = |_: &K, value: &V| *value == *expected;
+
+        match self
+            .raw
+            .remove_if(key, should_remove, self.raw.verify(guard))
+        {
+            Ok(Some((_, value))) => Ok(value),
+            Ok(None) => Err(None),
+            Err((_, current)) => Err(Some(current)),
+        }
+    }
+
+    /// Returns a reference to the entry corresponding to the key.
+    ///
+    /// The returned [`EntryRef`] identifies the exact entry that was read, and can later be
+    /// passed to [`replace_if_current`](HashMap::replace_if_current) or
+    /// [`remove_if_current`](HashMap::remove_if_current) to modify the entry only if it has
+    /// not been changed since.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a");
+    ///
+    /// let guard = map.guard();
+    /// let entry = map.get_entry(&1, &guard).unwrap();
+    /// assert_eq!(entry.key(), &1);
+    /// assert_eq!(entry.value(), &"a");
+    /// assert!(map.get_entry(&2, &guard).is_none());
+    /// ```
+    #[inline]
+    pub fn get_entry<'g, Q>(&self, key: &Q, guard: &'g impl Guard) -> Option<EntryRef<'g, K, V>>
+    where
+        K: 'g,
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.raw
+            .get_entry(key, self.raw.verify(guard))
+            .map(|entry| EntryRef { entry })
+    }
+
+    /// Replaces the value of an entry if it has not been modified since it was read,
+    /// returning a reference to the new entry.
+    ///
+    /// Unlike [`compare_exchange`](HashMap::compare_exchange), the entry is compared by
+    /// identity rather than by value. The operation fails if the entry was replaced or removed
+    /// since it was returned by [`get_entry`](HashMap::get_entry), even if the key was
+    /// later reinserted with an equal value. Entries are not reclaimed while the guard they
+    /// were read with is held, so an [`EntryRef`] can never be confused with a newer entry.
+    ///
+    /// If the entry is no longer current, an error containing the value that was not
+    /// inserted is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a");
+    ///
+    /// let guard = map.guard();
+    /// let entry = map.get_entry(&1, &guard).unwrap();
+    /// let new = map.replace_if_current(entry, "b", &guard).unwrap();
+    /// assert_eq!(new.value(), &"b");
+    ///
+    /// // The original entry was replaced.
+    /// assert_eq!(map.replace_if_current(entry, "c", &guard).unwrap_err(), "c");
+    /// assert_eq!(map.get(&1, &guard), Some(&"b"));
+    /// ```
+    #[inline]
+    pub fn replace_if_current<'g>(
+        &self,
+        entry: EntryRef<'g, K, V>,
+        value: V,
+        guard: &'g impl Guard,
+    ) -> Result<EntryRef<'g, K, V>, V>
+    where
+        K: Clone,
+    {
+        self.raw
+            .replace_if_current(entry.entry, value, self.raw.verify(guard))
+            .map(|entry| EntryRef { entry })
+    }
+
+    /// Removes an entry if it has not been modified since it was read.
+    ///
+    /// Returns `true` if the entry was removed. See
+    /// [`replace_if_current`](HashMap::replace_if_current) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// map.pin().insert(1, "a");
+    ///
+    /// let guard = map.guard();
+    /// let entry = map.get_entry(&1, &guard).unwrap();
+    ///
+    /// // The key was reinserted with the same value, but the entry is no longer current.
+    /// map.insert(1, "a", &guard);
+    /// assert!(!map.remove_if_current(entry, &guard));
+    ///
+    /// let entry = map.get_entry(&1, &guard).unwrap();
+    /// assert!(map.remove_if_current(entry, &guard));
+    /// assert_eq!(map.get(&1, &guard), None);
+    /// ```
+    #[inline]
+    pub fn remove_if_current<'g>(&self, entry: EntryRef<'g, K, V>, guard: &'g impl Guard) -> bool {
+        self.raw
+            .remove_if_current(entry.entry, self.raw.verify(guard))
+    }
+
+    /// Tries to reserve capacity for `additional` more elements to be inserted
+    /// in the `HashMap`.
+    ///
+    /// After calling this method, the table should be able to hold at least `capacity` elements
+    /// before resizing. However, the capacity is an estimate, and the table may prematurely resize
+    /// due to poor hash distribution. The collection may also reserve more space to avoid frequent
+    /// reallocations.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new allocation size overflows `usize`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map: HashMap<&str, i32> = HashMap::new();
+    /// map.pin().reserve(10);
+    /// ```
     #[inline]
     pub fn reserve(&self, additional: usize, guard: &impl Guard) {
         self.raw.reserve(additional, self.raw.verify(guard))
     }
 
+    /// Shrinks the capacity of the map as much as possible.
+    ///
+    /// The map will shrink to fit the number of elements it currently holds, while
+    /// maintaining its internal load factor. Note that the map is never shrunk below
+    /// the initial capacity it was created with.
+    ///
+    /// The map also shrinks automatically when enough elements are removed. This method
+    /// can be used to reclaim memory eagerly after a large number of removals.
+    ///
+    /// Note that this method will block until the resize is completed, regardless of
+    /// the configured [`ResizeMode`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map: HashMap<i32, i32> = (0..1000).map(|x| (x, x)).collect();
+    /// map.pin().retain(|&k, _| k < 10);
+    /// map.pin().shrink_to_fit();
+    /// assert_eq!(map.len(), 10);
+    /// ```
+    #[inline]
+    pub fn shrink_to_fit(&self, guard: &impl Guard) {
+        self.raw.shrink_to(0, self.raw.verify(guard))
+    }
+
+    /// Shrinks the capacity of the map with a lower limit.
+    ///
+    /// The capacity will remain at least as large as both the number of elements
+    /// in the map and the supplied value, as well as the initial capacity of the map.
+    /// If the current capacity is less than the lower limit, this is a no-op.
+    ///
+    /// Note that this method will block until the resize is completed, regardless of
+    /// the configured [`ResizeMode`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map: HashMap<i32, i32> = (0..1000).map(|x| (x, x)).collect();
+    /// map.pin().retain(|&k, _| k < 10);
+    /// map.pin().shrink_to(100);
+    /// assert_eq!(map.len(), 10);
+    /// ```
+    #[inline]
+    pub fn shrink_to(&self, capacity: usize, guard: &impl Guard) {
+        self.raw.shrink_to(capacity, self.raw.verify(guard))
+    }
+
     /// Clears the map, removing all key-value pairs.
     ///
     /// Note that this method will block until any in-progress resizes are
@@ -1043,86 +2071,404 @@ where
     /// ```
     /// use papaya::HashMap;
     ///
-    /// let map = HashMap::from([
+    /// let map = HashMap::from([
+    ///     ("a", 1),
+    ///     ("b", 2),
+    ///     ("c", 3),
+    /// ]);
+    ///
+    /// for (key, val) in map.pin().iter() {
+    ///     println!("key: {key} val: {val}");
+    /// }
+    #[inline]
+    pub fn iter<'g, G>(&self, guard: &'g G) -> Iter<'g, K, V, G>
+    where
+        G: Guard,
+    {
+        Iter {
+            raw: self.raw.iter(self.raw.verify(guard)),
+        }
+    }
+
+    /// An iterator visiting the key-value pairs in a single partition of the map.
+    ///
+    /// The underlying table is split into `of` contiguous ranges of slots, and the returned
+    /// iterator visits the entries in the range at the given `index`. This allows iteration to
+    /// be distributed manually across threads. Together, the partitions yield every entry
+    /// exactly once if the map is not concurrently modified.
+    ///
+    /// Note that the partitions are determined by the table at the time this method is called.
+    /// If the table is resized between creating iterators for different partitions, entries
+    /// may be missed or yielded more than once. See the [consistency](crate#consistency)
+    /// section for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than `of`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map: HashMap<i32, i32> = (0..1000).map(|x| (x, x)).collect();
+    /// let map = map.pin();
+    ///
+    /// let sum: i32 = (0..4)
+    ///     .map(|i| map.iter_partition(i, 4).map(|(_, v)| v).sum::<i32>())
+    ///     .sum();
+    /// assert_eq!(sum, (0..1000).sum());
+    /// ```
+    #[inline]
+    pub fn iter_partition<'g, G>(&self, index: usize, of: usize, guard: &'g G) -> Iter<'g, K, V, G>
+    where
+        G: Guard,
+    {
+        Iter {
+            raw: self.raw.iter_partition(index, of, self.raw.verify(guard)),
+        }
+    }
+
+    /// Returns a batch of entries from an incremental scan of the map, along with the cursor to
+    /// continue the scan from.
+    ///
+    /// A scan is started with a cursor of `0`, and is complete when the returned cursor is `0`.
+    /// Each call returns at least `count` entries, unless the scan is complete, but may return
+    /// more.
+    ///
+    /// Unlike iteration, the cursor does not borrow the guard, and remains valid across resizes
+    /// of the map. This allows long scans to be performed in batches, refreshing or dropping the
+    /// guard in between so that memory reclamation can make progress.
+    ///
+    /// Every entry that is present in the map for the duration of the scan is returned at least
+    /// once. Entries that are inserted or removed during the scan may or may not be returned,
+    /// and entries may be returned more than once if the map shrinks during the scan.
+    ///
+    /// Note that each call will block until any in-progress resizes are completed before
+    /// proceeding. See the [consistency](crate#consistency) section for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::new();
+    /// for i in 0..1000 {
+    ///     map.pin().insert(i, i);
+    /// }
+    ///
+    /// let mut sum = 0;
+    /// let mut cursor = 0;
+    /// loop {
+    ///     let guard = map.guard();
+    ///     let (next, entries) = map.scan(cursor, 100, &guard);
+    ///     sum += entries.iter().map(|(_, v)| *v).sum::<i32>();
+    ///
+    ///     cursor = next;
+    ///     if cursor == 0 {
+    ///         break;
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(sum, (0..1000).sum());
+    /// ```
+    #[inline]
+    pub fn scan<'g>(
+        &self,
+        cursor: u64,
+        count: usize,
+        guard: &'g impl Guard,
+    ) -> (u64, Vec<(&'g K, &'g V)>) {
+        self.raw.scan(cursor, count, self.raw.verify(guard))
+    }
+
+    /// Returns a point-in-time snapshot of the map.
+    ///
+    /// Unlike iteration, which only provides a weak snapshot of the map, the returned
+    /// [`Snapshot`] reflects the state of the map at a single point in time. Writes that complete
+    /// before this call are visible to the snapshot, while writes made after it returns are not.
+    /// This makes snapshots suitable for consistent checkpoints and exports while the map is
+    /// being concurrently modified.
+    ///
+    /// Writers are not blocked while a snapshot is alive. Instead, the first write to a key after
+    /// a snapshot is taken preserves the previous state of the key for the snapshot, and any
+    /// replaced entries are kept alive until the guard is dropped. Taking a snapshot waits for
+    /// any in-progress writes to complete.
+    ///
+    /// Snapshots rely on entry versions to distinguish entries that were written after the
+    /// snapshot was taken. Writers to a versioned map pay a small cost to support snapshots,
+    /// which increases while a snapshot is alive.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map was not created with [`HashMapBuilder::versioned`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::builder().versioned(true).build();
+    /// let map = map.pin();
+    /// map.insert(1, "a");
+    /// map.insert(2, "b");
+    ///
+    /// let snapshot = map.snapshot();
+    /// map.insert(1, "c");
+    /// map.remove(&2);
+    /// map.insert(3, "d");
+    ///
+    /// assert_eq!(snapshot.get(&1), Some(&"a"));
+    /// assert_eq!(snapshot.get(&2), Some(&"b"));
+    /// assert_eq!(snapshot.get(&3), None);
+    /// assert_eq!(snapshot.len(), 2);
+    /// ```
+    #[inline]
+    pub fn snapshot<'g, G>(&'g self, guard: &'g G) -> Snapshot<'g, K, V, S, C, G>
+    where
+        G: Guard,
+    {
+        Snapshot::new(&self.raw, self.raw.verify(guard))
+    }
+
+    /// An iterator visiting all keys in arbitrary order.
+    /// The iterator element type is `&K`.
+    ///
+    /// Note that this method will block until any in-progress resizes are
+    /// completed before proceeding. See the [consistency](crate#consistency)
+    /// section for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::from([
+    ///     ("a", 1),
+    ///     ("b", 2),
+    ///     ("c", 3),
+    /// ]);
+    ///
+    /// for key in map.pin().keys() {
+    ///     println!("{key}");
+    /// }
+    /// ```
+    #[inline]
+    pub fn keys<'g, G>(&self, guard: &'g G) -> Keys<'g, K, V, G>
+    where
+        G: Guard,
+    {
+        Keys {
+            iter: self.iter(guard),
+        }
+    }
+
+    /// An iterator visiting all values in arbitrary order.
+    /// The iterator element type is `&V`.
+    ///
+    /// Note that this method will block until any in-progress resizes are
+    /// completed before proceeding. See the [consistency](crate#consistency)
+    /// section for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::from([
+    ///     ("a", 1),
+    ///     ("b", 2),
+    ///     ("c", 3),
+    /// ]);
+    ///
+    /// for value in map.pin().values() {
+    ///     println!("{value}");
+    /// }
+    /// ```
+    #[inline]
+    pub fn values<'g, G>(&self, guard: &'g G) -> Values<'g, K, V, G>
+    where
+        G: Guard,
+    {
+        Values {
+            iter: self.iter(guard),
+        }
+    }
+}
+
+/// Exclusive access operations.
+///
+/// These operations take `&mut self`, and so do not require a guard. Exclusive access to a map
+/// that owns its collector statically guarantees that no other thread holds references into the
+/// map, so entries can be accessed and reclaimed directly.
+///
+/// Note that these operations are not available for maps using a
+/// [shared collector](HashMapBuilder::shared_collector), as guards for a shared collector
+/// may outlive a borrow of the map.
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let mut map = HashMap::new();
+    /// map.insert_mut(1, "a");
+    ///
+    /// if let Some(x) = map.get_mut(&1) {
+    ///     *x = "b";
+    /// }
+    /// assert_eq!(map.pin().get(&1), Some(&"b"));
+    /// ```
+    #[inline]
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        // Safety: We own the collector, so any guards would borrow the map.
+        unsafe { self.raw.get_mut(key) }
+    }
+
+    /// Inserts a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, [`None`] is returned.
+    ///
+    /// If the map did have this key present, the value is updated in-place and the
+    /// old value is returned. The key is not updated, though; this matters for types
+    /// that can be `==` without being identical.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let mut map = HashMap::new();
+    /// assert_eq!(map.insert_mut(37, "a"), None);
+    /// assert_eq!(map.insert_mut(37, "b"), Some("a"));
+    /// assert_eq!(map.pin().get(&37), Some(&"b"));
+    /// ```
+    #[inline]
+    pub fn insert_mut(&mut self, key: K, value: V) -> Option<V> {
+        // Safety: We own the collector, so any guards would borrow the map.
+        unsafe { self.raw.insert_mut(key, value) }
+    }
+
+    /// Removes a key from the map, returning the owned key and value if the
+    /// key was previously in the map.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let mut map = HashMap::new();
+    /// map.insert_mut(1, String::from("a"));
+    /// assert_eq!(map.remove_owned(&1), Some((1, String::from("a"))));
+    /// assert_eq!(map.remove_owned(&1), None);
+    /// ```
+    #[inline]
+    pub fn remove_owned<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        // Safety: We own the collector, so any guards would borrow the map.
+        unsafe { self.raw.remove_mut(key) }
+    }
+
+    /// An iterator visiting all key-value pairs in arbitrary order,
+    /// with mutable references to the values.
+    /// The iterator element type is `(&K, &mut V)`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let mut map = HashMap::from([
     ///     ("a", 1),
     ///     ("b", 2),
     ///     ("c", 3),
     /// ]);
     ///
-    /// for (key, val) in map.pin().iter() {
-    ///     println!("key: {key} val: {val}");
+    /// for (_, val) in map.iter_mut() {
+    ///     *val *= 2;
     /// }
+    ///
+    /// assert_eq!(map.pin().get("b"), Some(&4));
+    /// ```
     #[inline]
-    pub fn iter<'g, G>(&self, guard: &'g G) -> Iter<'g, K, V, G>
-    where
-        G: Guard,
-    {
-        Iter {
-            raw: self.raw.iter(self.raw.verify(guard)),
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            // Safety: We own the collector, so any guards would borrow the map.
+            raw: unsafe { self.raw.iter_mut() },
         }
     }
 
-    /// An iterator visiting all keys in arbitrary order.
-    /// The iterator element type is `&K`.
-    ///
-    /// Note that this method will block until any in-progress resizes are
-    /// completed before proceeding. See the [consistency](crate#consistency)
-    /// section for details.
+    /// An iterator visiting all values mutably in arbitrary order.
+    /// The iterator element type is `&mut V`.
     ///
     /// # Examples
     ///
     /// ```
     /// use papaya::HashMap;
     ///
-    /// let map = HashMap::from([
+    /// let mut map = HashMap::from([
     ///     ("a", 1),
     ///     ("b", 2),
     ///     ("c", 3),
     /// ]);
     ///
-    /// for key in map.pin().keys() {
-    ///     println!("{key}");
+    /// for val in map.values_mut() {
+    ///     *val += 10;
     /// }
+    ///
+    /// assert_eq!(map.pin().get("a"), Some(&11));
     /// ```
     #[inline]
-    pub fn keys<'g, G>(&self, guard: &'g G) -> Keys<'g, K, V, G>
-    where
-        G: Guard,
-    {
-        Keys {
-            iter: self.iter(guard),
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut {
+            iter: self.iter_mut(),
         }
     }
 
-    /// An iterator visiting all values in arbitrary order.
-    /// The iterator element type is `&V`.
+    /// Clears the map, returning all key-value pairs as an iterator.
     ///
-    /// Note that this method will block until any in-progress resizes are
-    /// completed before proceeding. See the [consistency](crate#consistency)
-    /// section for details.
+    /// If the returned iterator is dropped before being fully consumed, it
+    /// drops the remaining key-value pairs.
     ///
     /// # Examples
     ///
     /// ```
     /// use papaya::HashMap;
     ///
-    /// let map = HashMap::from([
-    ///     ("a", 1),
-    ///     ("b", 2),
-    ///     ("c", 3),
-    /// ]);
+    /// let mut map = HashMap::new();
+    /// map.insert_mut(1, "a");
+    /// map.insert_mut(2, "b");
     ///
-    /// for value in map.pin().values() {
-    ///     println!("{value}");
-    /// }
+    /// let mut drained = map.drain().collect::<Vec<_>>();
+    /// drained.sort();
+    ///
+    /// assert_eq!(drained, [(1, "a"), (2, "b")]);
+    /// assert!(map.is_empty());
     /// ```
     #[inline]
-    pub fn values<'g, G>(&self, guard: &'g G) -> Values<'g, K, V, G>
-    where
-        G: Guard,
-    {
-        Values {
-            iter: self.iter(guard),
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        Drain {
+            // Safety: We own the collector, so any guards would borrow the map.
+            raw: unsafe { self.raw.drain() },
         }
     }
 }
@@ -1157,6 +2503,16 @@ pub enum Compute<'g, K, V, T> {
 
         /// The entry that was inserted.
         new: (&'g K, &'g V),
+
+        /// The version of the entry that was replaced.
+        ///
+        /// This is `0` if the map is not [versioned](HashMapBuilder::versioned).
+        old_version: u64,
+
+        /// The version of the entry that was inserted.
+        ///
+        /// This is `0` if the map is not [versioned](HashMapBuilder::versioned).
+        new_version: u64,
     },
 
     /// The given entry was removed.
@@ -1177,6 +2533,136 @@ pub struct OccupiedError<'a, V: 'a> {
     pub not_inserted: V,
 }
 
+/// An error returned by [`compare_exchange`](HashMap::compare_exchange) when the current
+/// value does not match the expected value.
+///
+/// Contains the current value, and the value that was not inserted.
+#[derive(Debug, PartialEq, Eq)]
+pub struct CompareExchangeError<'a, V: 'a> {
+    /// The value in the map when the operation failed, or `None` if the key was not present.
+    pub current: Option<&'a V>,
+    /// The value which was not inserted, because the current value did not match.
+    pub new: V,
+}
+
+/// A reference to an entry in a [`HashMap`].
+///
+/// Returned by [`get_entry`](HashMap::get_entry), and used to identify the exact entry that
+/// was read in [`replace_if_current`](HashMap::replace_if_current) and
+/// [`remove_if_current`](HashMap::remove_if_current).
+pub struct EntryRef<'g, K, V> {
+    entry: &'g raw::Entry<K, V>,
+}
+
+impl<'g, K, V> EntryRef<'g, K, V> {
+    /// Returns a reference to the entry's key.
+    #[inline]
+    pub fn key(&self) -> &'g K {
+        &self.entry.key
+    }
+
+    /// Returns a reference to the entry's value.
+    #[inline]
+    pub fn value(&self) -> &'g V {
+        &self.entry.value
+    }
+}
+
+/// A key paired with its precomputed hash.
+///
+/// Returned by [`HashMap::hash_key`], or constructed with [`HashedKey::new`] from a hash that
+/// was computed upstream, such as when routing keys between shards. Hashed keys are accepted
+/// by the `*_hashed` family of methods on [`HashMap`], avoiding the cost of hashing the key
+/// again for every operation. Reads accept a hashed reference to a key, such as
+/// `HashedKey<&Q>`, while writes take a hashed owned key, `HashedKey<K>`.
+///
+/// The hash must be the hash of the key computed by the map's [`BuildHasher`]. Passing a
+/// different hash is a logic error. The behavior resulting from such a logic error is not
+/// specified, but will be encapsulated to the map and will not result in undefined behavior.
+/// This may include entries not being found, the same key being inserted more than once, or
+/// degraded performance.
+pub struct HashedKey<Q> {
+    key: Q,
+    hash: u64,
+}
+
+impl<Q> HashedKey<Q> {
+    /// Creates a hashed key from a key and its precomputed hash.
+    ///
+    /// See the [type-level documentation](HashedKey) for the requirements on `hash`.
+    #[inline]
+    pub fn new(key: Q, hash: u64) -> HashedKey<Q> {
+        HashedKey { key, hash }
+    }
+
+    /// Returns a reference to the key.
+    #[inline]
+    pub fn key(&self) -> &Q {
+        &self.key
+    }
+
+    /// Returns the precomputed hash of the key.
+    #[inline]
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns a hashed reference to the key, without rehashing it.
+    #[inline]
+    pub fn as_ref(&self) -> HashedKey<&Q> {
+        HashedKey::new(&self.key, self.hash)
+    }
+
+    /// Returns the key, discarding its hash.
+    #[inline]
+    pub fn into_key(self) -> Q {
+        self.key
+    }
+}
+
+impl<Q: Clone> Clone for HashedKey<Q> {
+    #[inline]
+    fn clone(&self) -> Self {
+        HashedKey::new(self.key.clone(), self.hash)
+    }
+}
+
+impl<Q: Copy> Copy for HashedKey<Q> {}
+
+impl<Q> fmt::Debug for HashedKey<Q>
+where
+    Q: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashedKey")
+            .field("key", &self.key)
+            .field("hash", &self.hash)
+            .finish()
+    }
+}
+
+impl<K, V> Clone for EntryRef<'_, K, V> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K, V> Copy for EntryRef<'_, K, V> {}
+
+impl<K, V> fmt::Debug for EntryRef<'_, K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EntryRef")
+            .field("key", self.key())
+            .field("value", self.value())
+            .finish()
+    }
+}
+
 impl<K, V, S, C> PartialEq for HashMap<K, V, S, C>
 where
     K: Hash + Eq,
@@ -1218,6 +2704,40 @@ where
     }
 }
 
+impl<K, V, S, C> IntoIterator for HashMap<K, V, S, C>
+where
+    C: Borrow<Collector>,
+{
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    /// Creates a consuming iterator, that is, one that moves each key-value
+    /// pair out of the map in arbitrary order. The map cannot be used after
+    /// calling this.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map = HashMap::from([
+    ///     ("a", 1),
+    ///     ("b", 2),
+    ///     ("c", 3),
+    /// ]);
+    ///
+    /// let mut vec: Vec<(&str, i32)> = map.into_iter().collect();
+    /// vec.sort_unstable();
+    /// assert_eq!(vec, [("a", 1), ("b", 2), ("c", 3)]);
+    /// ```
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            raw: self.raw.into_entries(),
+        }
+    }
+}
+
 impl<K, V, S, C> Extend<(K, V)> for &HashMap<K, V, S, C>
 where
     K: Hash + Eq,
@@ -1315,11 +2835,16 @@ where
     S: BuildHasher + Clone,
 {
     fn clone(&self) -> HashMap<K, V, S> {
-        let other = HashMap::builder()
+        let mut builder = HashMap::builder()
             .capacity(self.len())
             .hasher(self.raw.hasher.clone())
             .collector(Collector::default())
-            .build();
+            .versioned(self.raw.is_versioned())
+            .cache_hashes(self.raw.is_caching_hashes());
+
+        // Share the observer with the new map.
+        builder.observer = self.raw.get_observer().cloned();
+        let other = builder.build();
 
         {
             let (guard1, guard2) = (&self.guard(), &other.guard());
@@ -1339,11 +2864,16 @@ where
     S: BuildHasher + Clone,
 {
     fn clone(&self) -> HashMap<K, V, S, Arc<Collector>> {
-        let other = HashMap::builder()
+        let mut builder = HashMap::builder()
             .capacity(self.len())
             .hasher(self.raw.hasher.clone())
             .shared_collector(Arc::new(Collector::default()))
-            .build();
+            .versioned(self.raw.is_versioned())
+            .cache_hashes(self.raw.is_caching_hashes());
+
+        // Share the observer with the new map.
+        builder.observer = self.raw.get_observer().cloned();
+        let other = builder.build();
 
         {
             let (guard1, guard2) = (&self.guard(), &other.guard());
@@ -1352,85 +2882,282 @@ where
             }
         }
 
-        other
+        other
+    }
+}
+
+/// A pinned reference to a [`HashMap`].
+///
+/// This type is created with [`HashMap::pin`] and can be used to easily access a [`HashMap`]
+/// without explicitly managing a guard. See the [crate-level documentation](crate#usage) for details.
+pub struct HashMapRef<'map, K, V, S, C, G>
+where
+    C: Borrow<Collector>,
+{
+    pub(crate) guard: MapGuard<G>,
+    pub(crate) map: &'map HashMap<K, V, S, C>,
+}
+
+impl<'map, K, V, S, C, G> HashMapRef<'map, K, V, S, C, G>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+    G: Guard,
+{
+    /// Returns a reference to the inner [`HashMap`].
+    #[inline]
+    pub fn map(&self) -> &'map HashMap<K, V, S, C> {
+        self.map
+    }
+
+    /// Returns the number of entries in the map.
+    ///
+    /// See [`HashMap::len`] for details.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.map.raw.len()
+    }
+
+    /// Returns `true` if the map is empty. Otherwise returns `false`.
+    ///
+    /// See [`HashMap::is_empty`] for details.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of modifications made to the map.
+    ///
+    /// See [`HashMap::modification_count`] for details.
+    #[inline]
+    pub fn modification_count(&self) -> u64 {
+        self.map.raw.modification_count()
+    }
+
+    /// Returns statistics about the internal state of the map.
+    ///
+    /// See [`HashMap::stats`] for details.
+    #[inline]
+    pub fn stats(&self) -> MapStats {
+        self.map.raw.stats(&self.guard)
+    }
+
+    /// Records the current capacity and length of the map to the exported metrics.
+    ///
+    /// See [`HashMap::record_metrics`] for details.
+    #[cfg(feature = "metrics")]
+    #[inline]
+    pub fn record_metrics(&self) {
+        self.map.raw.record_metrics(&self.guard)
+    }
+
+    /// Subscribes to changes made to the map.
+    ///
+    /// See [`HashMap::subscribe`] for details.
+    #[inline]
+    pub fn subscribe(&self) -> ChangeStream<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.map.raw.subscribe()
+    }
+
+    /// Returns `true` if the map contains a value for the specified key.
+    ///
+    /// See [`HashMap::contains_key`] for details.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// See [`HashMap::get`] for details.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        match self.map.raw.get(key, &self.guard) {
+            Some((_, v)) => Some(v),
+            None => None,
+        }
+    }
+
+    /// Returns the key-value pair corresponding to the supplied key.
+    ///
+    /// See [`HashMap::get_key_value`] for details.
+    #[inline]
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.map.raw.get(key, &self.guard)
+    }
+
+    /// Hashes a key with the map's hasher.
+    ///
+    /// See [`HashMap::hash_key`] for details.
+    #[inline]
+    pub fn hash_key<Q>(&self, key: Q) -> HashedKey<Q>
+    where
+        Q: Hash,
+    {
+        self.map.hash_key(key)
+    }
+
+    /// Returns `true` if the map contains a value for the specified hashed key.
+    ///
+    /// See [`HashMap::contains_key_hashed`] for details.
+    #[inline]
+    pub fn contains_key_hashed<Q>(&self, key: HashedKey<&Q>) -> bool
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        self.get_key_value_hashed(key).is_some()
+    }
+
+    /// Returns a reference to the value corresponding to the hashed key.
+    ///
+    /// See [`HashMap::get_hashed`] for details.
+    #[inline]
+    pub fn get_hashed<Q>(&self, key: HashedKey<&Q>) -> Option<&V>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        match self.get_key_value_hashed(key) {
+            Some((_, v)) => Some(v),
+            None => None,
+        }
+    }
+
+    /// Returns the key-value pair corresponding to the hashed key.
+    ///
+    /// See [`HashMap::get_key_value_hashed`] for details.
+    #[inline]
+    pub fn get_key_value_hashed<Q>(&self, key: HashedKey<&Q>) -> Option<(&K, &V)>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        self.map
+            .raw
+            .get_entry_hashed(key.key, key.hash, &self.guard)
+            .map(|entry| (&entry.key, &entry.value))
     }
-}
 
-/// A pinned reference to a [`HashMap`].
-///
-/// This type is created with [`HashMap::pin`] and can be used to easily access a [`HashMap`]
-/// without explicitly managing a guard. See the [crate-level documentation](crate#usage) for details.
-pub struct HashMapRef<'map, K, V, S, C, G>
-where
-    C: Borrow<Collector>,
-{
-    guard: MapGuard<G>,
-    map: &'map HashMap<K, V, S, C>,
-}
+    /// Inserts a hashed key and value into the map.
+    ///
+    /// See [`HashMap::insert_hashed`] for details.
+    #[inline]
+    pub fn insert_hashed(&self, key: HashedKey<K>, value: V) -> Option<&V> {
+        match self
+            .map
+            .raw
+            .insert_with_hash(key.key, value, key.hash, true, &self.guard)
+        {
+            InsertResult::Inserted(_) => None,
+            InsertResult::Replaced(value) => Some(value),
+            InsertResult::Error { .. } => unreachable!(),
+        }
+    }
 
-impl<'map, K, V, S, C, G> HashMapRef<'map, K, V, S, C, G>
-where
-    K: Hash + Eq,
-    S: BuildHasher,
-    C: Borrow<Collector>,
-    G: Guard,
-{
-    /// Returns a reference to the inner [`HashMap`].
+    /// Updates the entry of a hashed key with a compare-and-swap (CAS) function.
+    ///
+    /// See [`HashMap::compute_hashed`] for details.
     #[inline]
-    pub fn map(&self) -> &'map HashMap<K, V, S, C> {
+    pub fn compute_hashed<'g, F, T>(&'g self, key: HashedKey<K>, compute: F) -> Compute<'g, K, V, T>
+    where
+        F: FnMut(Option<(&'g K, &'g V)>) -> Operation<V, T>,
+    {
         self.map
+            .raw
+            .compute_hashed(key.key, key.hash, compute, &self.guard)
     }
 
-    /// Returns the number of entries in the map.
+    /// Removes a hashed key from the map, returning the value at the key if the key
+    /// was previously in the map.
     ///
-    /// See [`HashMap::len`] for details.
+    /// See [`HashMap::remove_hashed`] for details.
     #[inline]
-    pub fn len(&self) -> usize {
-        self.map.raw.len()
+    pub fn remove_hashed<Q>(&self, key: HashedKey<&Q>) -> Option<&V>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        match self
+            .map
+            .raw
+            .remove_if_hashed(key.key, key.hash, |_, _| true, &self.guard)
+        {
+            Ok(Some((_, value))) => Some(value),
+            _ => None,
+        }
     }
 
-    /// Returns `true` if the map is empty. Otherwise returns `false`.
+    /// Returns the key-value pair corresponding to the supplied key, along with the
+    /// version of the entry.
     ///
-    /// See [`HashMap::is_empty`] for details.
+    /// See [`HashMap::get_versioned`] for details.
     #[inline]
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+    pub fn get_versioned<Q>(&self, key: &Q) -> Option<(&K, &V, u64)>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.map.get_versioned(key, &self.guard)
     }
 
-    /// Returns `true` if the map contains a value for the specified key.
+    /// Returns references to the values corresponding to each of the keys.
     ///
-    /// See [`HashMap::contains_key`] for details.
+    /// See [`HashMap::get_many`] for details.
     #[inline]
-    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    pub fn get_many<Q, const N: usize>(&self, keys: [&Q; N]) -> [Option<&V>; N]
     where
         Q: Equivalent<K> + Hash + ?Sized,
     {
-        self.get(key).is_some()
+        self.map.get_many(keys, &self.guard)
     }
 
-    /// Returns a reference to the value corresponding to the key.
+    /// Returns references to the values corresponding to each key in the iterator.
     ///
-    /// See [`HashMap::get`] for details.
+    /// See [`HashMap::get_batch`] for details.
     #[inline]
-    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    pub fn get_batch<'q, Q, I>(&self, keys: I) -> Vec<Option<&V>>
+    where
+        Q: Equivalent<K> + Hash + ?Sized + 'q,
+        I: IntoIterator<Item = &'q Q>,
+    {
+        self.map.get_batch(keys, &self.guard)
+    }
+
+    /// Waits for a value to be present for the specified key.
+    ///
+    /// See [`HashMap::wait_for`] for details.
+    #[inline]
+    pub fn wait_for<'a, Q>(&'a self, key: &'a Q) -> impl Future<Output = &'a V> + 'a
     where
         Q: Equivalent<K> + Hash + ?Sized,
     {
-        match self.map.raw.get(key, &self.guard) {
-            Some((_, v)) => Some(v),
-            None => None,
-        }
+        self.map.wait_for(key, &self.guard)
     }
 
-    /// Returns the key-value pair corresponding to the supplied key.
+    /// Waits for the value of the specified key to satisfy a predicate.
     ///
-    /// See [`HashMap::get_key_value`] for details.
+    /// See [`HashMap::wait_until`] for details.
     #[inline]
-    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    pub fn wait_until<'a, Q, F>(
+        &'a self,
+        key: &'a Q,
+        predicate: F,
+    ) -> impl Future<Output = &'a V> + 'a
     where
         Q: Equivalent<K> + Hash + ?Sized,
+        F: FnMut(&V) -> bool + 'a,
     {
-        self.map.raw.get(key, &self.guard)
+        self.map.wait_until(key, predicate, &self.guard)
     }
 
     /// Inserts a key-value pair into the map.
@@ -1445,6 +3172,17 @@ where
         }
     }
 
+    /// Inserts every key-value pair in the iterator into the map.
+    ///
+    /// See [`HashMap::insert_batch`] for details.
+    #[inline]
+    pub fn insert_batch<I>(&self, entries: I)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.map.insert_batch(entries, &self.guard)
+    }
+
     /// Tries to insert a key-value pair into the map, and returns
     /// a reference to the value that was inserted.
     ///
@@ -1501,6 +3239,44 @@ where
         self.map.raw.get_or_insert_with(key, f, &self.guard)
     }
 
+    /// Returns a reference to the value corresponding to the key, or initializes it with the
+    /// value computed from a closure.
+    ///
+    /// See [`HashMap::get_or_init`] for details.
+    #[inline]
+    pub fn get_or_init<F>(&self, key: K, f: F) -> &V
+    where
+        F: FnOnce() -> V,
+    {
+        self.map.get_or_init(key, f, &self.guard)
+    }
+
+    /// Returns a reference to the value corresponding to the key, or inserts a value loaded
+    /// asynchronously.
+    ///
+    /// See [`HashMap::get_or_insert_async`] for details.
+    #[inline]
+    pub async fn get_or_insert_async<F, Fut>(&self, key: K, f: F) -> &V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V>,
+    {
+        self.map.get_or_insert_async(key, f, &self.guard).await
+    }
+
+    /// Returns a reference to the value corresponding to the key, or inserts a value loaded
+    /// asynchronously by a fallible future.
+    ///
+    /// See [`HashMap::get_or_try_insert_async`] for details.
+    #[inline]
+    pub async fn get_or_try_insert_async<F, Fut, E>(&self, key: K, f: F) -> Result<&V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        self.map.get_or_try_insert_async(key, f, &self.guard).await
+    }
+
     /// Updates an existing entry atomically.
     ///
     /// See [`HashMap::update`] for details.
@@ -1587,6 +3363,71 @@ where
         self.map.raw.remove_if(key, should_remove, &self.guard)
     }
 
+    /// Replaces the value of an existing key if it is equal to `expected`, returning a
+    /// reference to the new value.
+    ///
+    /// See [`HashMap::compare_exchange`] for details.
+    #[inline]
+    pub fn compare_exchange(
+        &self,
+        key: K,
+        expected: &V,
+        new: V,
+    ) -> Result<&V, CompareExchangeError<'_, V>>
+    where
+        V: PartialEq,
+    {
+        self.map.compare_exchange(key, expected, new, &self.guard)
+    }
+
+    /// Removes a key from the map if its value is equal to `expected`, returning a reference
+    /// to the removed value.
+    ///
+    /// See [`HashMap::compare_exchange_remove`] for details.
+    #[inline]
+    pub fn compare_exchange_remove<Q>(&self, key: &Q, expected: &V) -> Result<&V, Option<&V>>
+    where
+        V: PartialEq,
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.map.compare_exchange_remove(key, expected, &self.guard)
+    }
+
+    /// Returns a reference to the entry corresponding to the key.
+    ///
+    /// See [`HashMap::get_entry`] for details.
+    #[inline]
+    pub fn get_entry<Q>(&self, key: &Q) -> Option<EntryRef<'_, K, V>>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.map.get_entry(key, &self.guard)
+    }
+
+    /// Replaces the value of an entry if it has not been modified since it was read,
+    /// returning a reference to the new entry.
+    ///
+    /// See [`HashMap::replace_if_current`] for details.
+    #[inline]
+    pub fn replace_if_current<'g>(
+        &'g self,
+        entry: EntryRef<'g, K, V>,
+        value: V,
+    ) -> Result<EntryRef<'g, K, V>, V>
+    where
+        K: Clone,
+    {
+        self.map.replace_if_current(entry, value, &self.guard)
+    }
+
+    /// Removes an entry if it has not been modified since it was read.
+    ///
+    /// See [`HashMap::remove_if_current`] for details.
+    #[inline]
+    pub fn remove_if_current(&self, entry: EntryRef<'_, K, V>) -> bool {
+        self.map.remove_if_current(entry, &self.guard)
+    }
+
     /// Clears the map, removing all key-value pairs.
     ///
     /// See [`HashMap::clear`] for details.
@@ -1615,6 +3456,22 @@ where
         self.map.raw.reserve(additional, &self.guard)
     }
 
+    /// Shrinks the capacity of the map as much as possible.
+    ///
+    /// See [`HashMap::shrink_to_fit`] for details.
+    #[inline]
+    pub fn shrink_to_fit(&self) {
+        self.map.raw.shrink_to(0, &self.guard)
+    }
+
+    /// Shrinks the capacity of the map with a lower limit.
+    ///
+    /// See [`HashMap::shrink_to`] for details.
+    #[inline]
+    pub fn shrink_to(&self, capacity: usize) {
+        self.map.raw.shrink_to(capacity, &self.guard)
+    }
+
     /// An iterator visiting all key-value pairs in arbitrary order.
     /// The iterator element type is `(&K, &V)`.
     ///
@@ -1626,6 +3483,78 @@ where
         }
     }
 
+    /// An iterator visiting the key-value pairs in a single partition of the map.
+    ///
+    /// See [`HashMap::iter_partition`] for details.
+    #[inline]
+    pub fn iter_partition(&self, index: usize, of: usize) -> Iter<'_, K, V, G> {
+        Iter {
+            raw: self.map.raw.iter_partition(index, of, &self.guard),
+        }
+    }
+
+    /// Calls a closure on every entry in the map, refreshing the guard after every `every`
+    /// entries.
+    ///
+    /// Holding a guard for the duration of a long iteration prevents memory reclamation from
+    /// making progress. This method instead [refreshes](Guard::refresh) the guard periodically,
+    /// and so entries are passed to the closure rather than returned by reference.
+    ///
+    /// The position of the iteration is tracked with a [`scan`](HashMap::scan) cursor, and the
+    /// table is re-derived from the root after every refresh, so the map may be resized during
+    /// iteration. Every entry that is present in the map for the duration of the iteration is
+    /// visited at least once, see [`HashMap::scan`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map: HashMap<i32, i32> = (0..1000).map(|i| (i, i)).collect();
+    ///
+    /// let mut sum = 0;
+    /// map.pin_owned().iter_refreshing(100, |_, value| sum += value);
+    /// assert_eq!(sum, (0..1000).sum());
+    /// ```
+    pub fn iter_refreshing<F>(&mut self, every: usize, mut f: F)
+    where
+        F: FnMut(&K, &V),
+    {
+        let mut cursor = 0;
+
+        loop {
+            let (next, entries) = self.map.raw.scan(cursor, every, &self.guard);
+            for (key, value) in entries {
+                f(key, value);
+            }
+
+            // Visited every entry.
+            if next == 0 {
+                return;
+            }
+
+            cursor = next;
+            self.guard.refresh();
+        }
+    }
+
+    /// Returns a batch of entries from an incremental scan of the map, along with the cursor to
+    /// continue the scan from.
+    ///
+    /// See [`HashMap::scan`] for details.
+    #[inline]
+    pub fn scan(&self, cursor: u64, count: usize) -> (u64, Vec<(&K, &V)>) {
+        self.map.raw.scan(cursor, count, &self.guard)
+    }
+
+    /// Returns a point-in-time snapshot of the map.
+    ///
+    /// See [`HashMap::snapshot`] for details.
+    #[inline]
+    pub fn snapshot(&self) -> Snapshot<'_, K, V, S, C, G> {
+        Snapshot::new(&self.map.raw, &self.guard)
+    }
+
     /// An iterator visiting all keys in arbitrary order.
     /// The iterator element type is `&K`.
     ///
@@ -1677,7 +3606,7 @@ where
 ///
 /// This struct is created by the [`iter`](HashMap::iter) method on [`HashMap`]. See its documentation for details.
 pub struct Iter<'g, K, V, G> {
-    raw: raw::Iter<'g, K, V, MapGuard<G>>,
+    pub(crate) raw: raw::Iter<'g, K, V, MapGuard<G>>,
 }
 
 impl<'g, K: 'g, V: 'g, G> Iterator for Iter<'g, K, V, G>
@@ -1768,3 +3697,114 @@ where
         f.debug_tuple("Values").field(&self.iter).finish()
     }
 }
+
+/// A mutable iterator over a map's entries.
+///
+/// This struct is created by the [`iter_mut`](HashMap::iter_mut) method on [`HashMap`]. See its documentation for details.
+pub struct IterMut<'a, K, V> {
+    raw: raw::IterMut<'a, K, V>,
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw.next()
+    }
+}
+
+impl<K, V> fmt::Debug for IterMut<'_, K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.raw.remaining()).finish()
+    }
+}
+
+/// A mutable iterator over a map's values.
+///
+/// This struct is created by the [`values_mut`](HashMap::values_mut) method on [`HashMap`]. See its documentation for details.
+pub struct ValuesMut<'a, K, V> {
+    iter: IterMut<'a, K, V>,
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let (_, value) = self.iter.next()?;
+        Some(value)
+    }
+}
+
+impl<K, V> fmt::Debug for ValuesMut<'_, K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ValuesMut").field(&self.iter).finish()
+    }
+}
+
+/// A draining iterator over a map's entries.
+///
+/// This struct is created by the [`drain`](HashMap::drain) method on [`HashMap`]. See its documentation for details.
+pub struct Drain<'a, K, V> {
+    raw: raw::Drain<'a, K, V>,
+}
+
+impl<K, V> Iterator for Drain<'_, K, V> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw.next()
+    }
+}
+
+impl<K, V> fmt::Debug for Drain<'_, K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.raw.remaining()).finish()
+    }
+}
+
+/// An owning iterator over a map's entries.
+///
+/// This struct is created by the [`into_iter`](IntoIterator::into_iter) method on [`HashMap`]
+/// (provided by the [`IntoIterator`] trait). See its documentation for details.
+pub struct IntoIter<K, V> {
+    raw: raw::IntoIter<K, V>,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
+impl<K, V> fmt::Debug for IntoIter<K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.raw.remaining()).finish()
+    }
+}
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+/// Hooks for observing internal events of a [`HashMap`](crate::HashMap).
+///
+/// An observer can be set with [`HashMapBuilder::observer`](crate::HashMapBuilder::observer)
+/// to diagnose latency spikes caused by resizing, for example by recording the reported
+/// durations in a histogram. Every method has an empty default implementation, so only the
+/// events of interest need to be implemented.
+///
+/// Observers are called synchronously by the thread that triggered the event, often while
+/// other threads are waiting for it to make progress, and so should return quickly. If no
+/// observer is set, events are not timed and cost a single branch on otherwise slow paths.
+///
+/// # Examples
+///
+/// ```
+/// use papaya::{HashMap, MapObserver};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// #[derive(Default)]
+/// struct Resizes(AtomicUsize);
+///
+/// impl MapObserver for Resizes {
+///     fn promoted(&self, _capacity: usize, _elapsed: Duration) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let resizes = Arc::new(Resizes::default());
+/// let map = HashMap::builder().observer(resizes.clone()).build();
+///
+/// for i in 0..1000 {
+///     map.pin().insert(i, i);
+/// }
+///
+/// assert!(resizes.0.load(Ordering::Relaxed) > 0);
+/// ```
+pub trait MapObserver: Send + Sync {
+    /// Called when a new table is allocated to resize a table with `capacity` slots into
+    /// `next_capacity` slots.
+    fn resize_started(&self, capacity: usize, next_capacity: usize) {
+        let _ = (capacity, next_capacity);
+    }
+
+    /// Called when a resize into a table with `capacity` slots is aborted because the table
+    /// was too small to hold every entry.
+    ///
+    /// The resize is retried in a larger table, which is reported by
+    /// [`resize_started`](MapObserver::resize_started).
+    fn resize_aborted(&self, capacity: usize) {
+        let _ = capacity;
+    }
+
+    /// Called when a resize completes and a table with `capacity` slots is promoted to the
+    /// root table, along with the time elapsed since the table was allocated.
+    fn promoted(&self, capacity: usize, elapsed: Duration) {
+        let _ = (capacity, elapsed);
+    }
+
+    /// Called when a thread finishes copying a chunk of `copied` slots to help along a
+    /// resize, along with the time it took to copy the chunk.
+    fn copied(&self, copied: usize, elapsed: Duration) {
+        let _ = (copied, elapsed);
+    }
+
+    /// Called when a thread that was blocked waiting on another thread is unparked, along
+    /// with the time it was parked for.
+    ///
+    /// Threads are parked while waiting for a resize to complete, or in
+    /// [`HashMap::get_or_init`](crate::HashMap::get_or_init) while waiting for another
+    /// thread to initialize the value.
+    fn parked(&self, elapsed: Duration) {
+        let _ = elapsed;
+    }
+}
+
+impl<T> MapObserver for std::sync::Arc<T>
+where
+    T: MapObserver + ?Sized,
+{
+    fn resize_started(&self, capacity: usize, next_capacity: usize) {
+        (**self).resize_started(capacity, next_capacity)
+    }
+
+    fn resize_aborted(&self, capacity: usize) {
+        (**self).resize_aborted(capacity)
+    }
+
+    fn promoted(&self, capacity: usize, elapsed: Duration) {
+        (**self).promoted(capacity, elapsed)
+    }
+
+    fn copied(&self, copied: usize, elapsed: Duration) {
+        (**self).copied(copied, elapsed)
+    }
+
+    fn parked(&self, elapsed: Duration) {
+        (**self).parked(elapsed)
+    }
+}
@@ -24,6 +24,9 @@ struct TableLayout<T> {
     /// State for the table resize.
     state: State<T>,
 
+    /// Whether the table has an array of access bits.
+    access: bool,
+
     /// An array of metadata for each entry.
     meta: [AtomicU8; 0],
 
@@ -55,8 +58,10 @@ impl<T> Clone for Table<T> {
 }
 
 impl<T> Table<T> {
-    // Allocate a table with the provided length and collector.
-    pub fn alloc(len: usize) -> Table<T> {
+    // Allocate a table with the provided length.
+    //
+    // If `access` is `true`, the table is allocated with an access bit for each entry.
+    pub fn alloc(len: usize, access: bool) -> Table<T> {
         assert!(len.is_power_of_two());
 
         // Pad the meta table to fulfill the alignment requirement of an entry.
@@ -64,7 +69,7 @@ impl<T> Table<T> {
         let mask = len - 1;
         let limit = probe::limit(len);
 
-        let layout = Table::<T>::layout(len);
+        let layout = Table::<T>::layout(len, access);
 
         // Allocate the table, zeroing the entries.
         //
@@ -80,6 +85,7 @@ impl<T> Table<T> {
             ptr.cast::<TableLayout<T>>().write(TableLayout {
                 mask,
                 limit,
+                access,
                 meta: [],
                 entries: [],
                 state: State::default(),
@@ -157,6 +163,66 @@ impl<T> Table<T> {
         }
     }
 
+    // Returns the access bit for the entry at the given index.
+    //
+    // # Safety
+    //
+    // The index must be in-bounds for the length of the table, and the table must have
+    // been allocated with access bits.
+    #[inline]
+    pub unsafe fn access(&self, i: usize) -> &AtomicU8 {
+        debug_assert!(i < self.len());
+        debug_assert!(self.has_access());
+
+        // Safety: The caller guarantees the index is in-bounds, and that the access array
+        // was allocated.
+        unsafe {
+            let meta = self.raw.add(mem::size_of::<TableLayout<T>>());
+            let access = meta
+                .add(self.len())
+                .add(mem::size_of::<AtomicPtr<T>>() * self.len());
+            &*access.cast::<AtomicU8>().add(i)
+        }
+    }
+
+    // Marks the entry at the given index as recently accessed.
+    //
+    // # Safety
+    //
+    // The index must be in-bounds for the length of the table, and the table must have
+    // been allocated with access bits.
+    #[inline]
+    pub unsafe fn touch(&self, i: usize) {
+        // Safety: Guaranteed by caller.
+        let access = unsafe { self.access(i) };
+
+        // Avoid writing to the shared cache line if the bit is already set.
+        if access.load(Ordering::Relaxed) == 0 {
+            access.store(1, Ordering::Relaxed);
+        }
+    }
+
+    // Returns `true` if the table was allocated with access bits.
+    #[inline]
+    pub fn has_access(&self) -> bool {
+        // Safety: The raw table pointer is always valid for reads and writes.
+        unsafe { (*self.raw.cast::<TableLayout<T>>()).access }
+    }
+
+    // Prefetches the metadata and entry at the given index.
+    //
+    // # Safety
+    //
+    // The index must be in-bounds for the length of the table.
+    #[inline]
+    pub unsafe fn prefetch(&self, i: usize) {
+        // Safety: The caller guarantees the index is in-bounds.
+        let (meta, entry) = unsafe { (self.meta(i), self.entry(i)) };
+
+        prefetch(meta as *const AtomicU8);
+        prefetch(entry as *const AtomicPtr<T>);
+    }
+
     /// Returns the length of the table.
     #[inline]
     pub fn len(&self) -> usize {
@@ -198,7 +264,7 @@ impl<T> Table<T> {
     // The table may not be accessed in any way after this method is
     // called.
     pub unsafe fn dealloc(table: Table<T>) {
-        let layout = Self::layout(table.len());
+        let layout = Self::layout(table.len(), table.has_access());
 
         // Safety: The raw table pointer is valid and allocated with `alloc::alloc_zeroed`.
         // Additionally, the caller guarantees that the allocation will not be accessed after
@@ -210,19 +276,34 @@ impl<T> Table<T> {
     }
 
     // Returns the non-zero layout for a table allocation.
-    fn layout(len: usize) -> Layout {
+    fn layout(len: usize, access: bool) -> Layout {
         let size = mem::size_of::<TableLayout<T>>()
             + (mem::size_of::<u8>() * len) // Metadata table.
-            + (mem::size_of::<AtomicPtr<T>>() * len); // Entry pointers.
-                                                      //
+            + (mem::size_of::<AtomicPtr<T>>() * len) // Entry pointers.
+            + if access { mem::size_of::<u8>() * len } else { 0 }; // Access bits.
+
         Layout::from_size_align(size, mem::align_of::<TableLayout<T>>()).unwrap()
     }
 }
 
+// Hints to the CPU that the cache line containing the given pointer will be read soon.
+#[inline(always)]
+fn prefetch<T>(ptr: *const T) {
+    // Safety: Prefetching is only a hint, and never faults on invalid addresses.
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr.cast::<i8>());
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = ptr;
+}
+
 #[test]
 fn layout() {
     unsafe {
-        let table: Table<u8> = Table::alloc(4);
+        let table: Table<u8> = Table::alloc(4, false);
         let table: Table<u8> = Table::from_raw(table.raw);
 
         // The capacity is padded for pointer alignment.
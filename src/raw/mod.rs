@@ -4,20 +4,33 @@ mod probe;
 pub(crate) mod utils;
 
 use std::borrow::Borrow;
+use std::collections::HashMap as StdHashMap;
 use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
 use std::mem::MaybeUninit;
-use std::sync::atomic::{AtomicPtr, AtomicU8, AtomicUsize, Ordering};
-use std::sync::Mutex;
+use std::sync::atomic::{self, AtomicPtr, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::task::{Context, Poll};
+use std::time::Instant;
 use std::{hint, panic, ptr};
 
 use self::alloc::{RawTable, Table};
 use self::probe::Probe;
-use self::utils::{untagged, AtomicPtrFetchOps, Counter, Parker, StrictProvenance, Tagged};
+#[allow(unused_imports)] // `atomic_ptr_strict_provenance` has stabilized on nightly.
+use self::utils::AtomicPtrFetchOps;
+use self::utils::{untagged, Atomic, Counter, Parker, StrictProvenance, Tagged};
+use crate::changes::{ChangeStream, RawChange, Subscribers};
 use crate::map::{Compute, Operation, ResizeMode};
+use crate::observer::MapObserver;
+use crate::snapshot::{Records, Snapshots, Write};
+use crate::stats::{MapStats, ResizeStats};
 use crate::Equivalent;
 
 use seize::{Collector, LocalGuard, OwnedGuard};
-use utils::{MapGuard, Stack, VerifiedGuard};
+use utils::{MapGuard, Stack, VerifiedGuard, Waiter, Waiters};
+
+/// The number of keys resolved together by batch operations.
+const BATCH: usize = 16;
 
 /// A lock-free hash-table.
 pub struct HashMap<K, V, S, C = Collector>
@@ -36,15 +49,184 @@ where
     /// An atomic counter of the number of keys in the table.
     count: Counter,
 
+    /// An atomic counter of the number of modifications made to the table, maintained only if
+    /// versioning is enabled.
+    modifications: Counter,
+
+    /// The number of times a table has been promoted to the root.
+    promotions: AtomicU64,
+
+    /// An observer notified of resize and parking events.
+    observer: Option<Arc<dyn MapObserver>>,
+
+    /// The name that exported metrics are labeled with, if metrics are enabled.
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<str>>,
+
+    /// Whether entries are allocated with a version stamp.
+    versioned: bool,
+
+    /// Whether entries are allocated with the hash of their key, which is used instead of
+    /// rehashing the key.
+    cache_hashes: bool,
+
+    /// The clock used to stamp entry versions, if versioning is enabled.
+    clock: AtomicU64,
+
+    /// Whether tables are allocated with access bits, which are set by reads.
+    track_access: bool,
+
+    /// Streams subscribed to changes made to the table, initialized by the first subscription.
+    changes: OnceLock<Subscribers<K, V>>,
+
+    /// Snapshots taken of the table, initialized if versioning is enabled.
+    snapshots: OnceLock<Snapshots<K, V>>,
+
+    /// Asynchronous tasks waiting for keys to be written.
+    waiters: Waiters,
+
+    /// Keys whose values are being loaded.
+    loading: Mutex<Loads<K>>,
+
+    /// A thread parker for blocking on loads.
+    parker: Parker,
+
     /// The initial capacity provided to `HashMap::new`.
     ///
-    /// The table is guaranteed to never shrink below this capacity.
+    /// The table is guaranteed to never shrink below this capacity, see `HashMap::min_len`.
     initial_capacity: usize,
 
     /// Hasher for keys.
     pub hasher: S,
 }
 
+/// Keys whose values are being loaded, grouped by hash.
+type Loads<K> = StdHashMap<u64, Vec<Loading<K>>>;
+
+/// The result of a load, either the current value or a claim to load it.
+type Load<'g, 'a, K, V, S, C> = Result<&'g V, LoadClaim<'a, K, V, S, C>>;
+
+/// A key whose value is being loaded.
+struct Loading<K> {
+    /// The key being loaded.
+    key: K,
+
+    /// The state of the load, which threads blocked on the load park on.
+    ///
+    /// Note that the state is shared with any blocked threads, ensuring that its address is
+    /// not reused while they are parked.
+    state: Arc<AtomicU8>,
+}
+
+impl Loading<()> {
+    /// The load is in progress.
+    const PENDING: u8 = 0;
+
+    /// The load was completed or abandoned.
+    const RELEASED: u8 = 1;
+}
+
+/// A claim to load the value of a key, acquired through `HashMap::poll_load` or
+/// `HashMap::get_or_claim`.
+///
+/// The claim is released when dropped, waking any tasks waiting on the key.
+pub struct LoadClaim<'a, K, V, S, C>
+where
+    C: Borrow<Collector>,
+{
+    map: &'a HashMap<K, V, S, C>,
+    state: Arc<AtomicU8>,
+    hash: u64,
+}
+
+impl<K, V, S, C> LoadClaim<'_, K, V, S, C>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+{
+    /// Inserts the loaded value and releases the claim, returning the current value if the key
+    /// was inserted by another method while it was being loaded.
+    #[inline]
+    pub fn insert<'g>(self, value: V, guard: &'g impl VerifiedGuard) -> &'g V
+    where
+        K: 'g,
+        V: 'g,
+    {
+        let result = {
+            let mut loading = self
+                .map
+                .loading
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let key = take_load(&mut loading, self.hash, &self.state).unwrap();
+
+            // Note that the value is inserted while holding the lock, ensuring that anyone who
+            // fails to find our claim observes the value.
+            self.map
+                .insert_with_hash(key, value, self.hash, false, guard)
+        };
+
+        match result {
+            InsertResult::Inserted(value) => value,
+            InsertResult::Error { current, .. } => current,
+            InsertResult::Replaced(_) => unreachable!(),
+        }
+    }
+}
+
+impl<K, V, S, C> Drop for LoadClaim<'_, K, V, S, C>
+where
+    C: Borrow<Collector>,
+{
+    fn drop(&mut self) {
+        // Remove our claim if the load was abandoned.
+        let key = {
+            let mut loading = self
+                .map
+                .loading
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            take_load(&mut loading, self.hash, &self.state)
+        };
+        drop(key);
+
+        // Unpark any threads blocked on our load.
+        //
+        // Note that `SeqCst` is necessary to be visible to unparked threads.
+        self.state.store(Loading::RELEASED, Ordering::SeqCst);
+        self.map.parker.unpark(&*self.state);
+
+        // Ensure the release of our claim is visible to any tasks woken below.
+        atomic::fence(Ordering::SeqCst);
+        self.map.waiters.wake(meta::h1(self.hash));
+    }
+}
+
+/// Returns the state of the load of the given key, if it is being loaded.
+#[inline]
+fn find_load<'a, K: Eq>(loading: &'a Loads<K>, hash: u64, key: &K) -> Option<&'a Arc<AtomicU8>> {
+    let loads = loading.get(&hash)?;
+    let load = loads.iter().find(|load| load.key == *key)?;
+    Some(&load.state)
+}
+
+/// Removes the load with the given state, returning its key if it was not already removed.
+#[inline]
+fn take_load<K>(loading: &mut Loads<K>, hash: u64, state: &Arc<AtomicU8>) -> Option<K> {
+    let loads = loading.get_mut(&hash)?;
+    let i = loads
+        .iter()
+        .position(|load| Arc::ptr_eq(&load.state, state))?;
+    let load = loads.swap_remove(i);
+
+    if loads.is_empty() {
+        loading.remove(&hash);
+    }
+
+    Some(load.key)
+}
+
 /// Resize state for the hash-table.
 pub struct State<T> {
     /// The next table used for resizing.
@@ -68,6 +250,9 @@ pub struct State<T> {
 
     /// Entries whose retirement has been deferred by later tables.
     pub deferred: Stack<*mut T>,
+
+    /// The time at which the table was allocated for a resize, if the map is observed.
+    pub started: OnceLock<Instant>,
 }
 
 impl<T> Default for State<T> {
@@ -80,6 +265,7 @@ impl<T> Default for State<T> {
             status: AtomicU8::new(State::PENDING),
             parker: Parker::default(),
             deferred: Stack::new(),
+            started: OnceLock::new(),
         }
     }
 }
@@ -112,8 +298,8 @@ pub enum RawInsertResult<'g, K, V> {
     /// Inserted the given value.
     Inserted(&'g V),
 
-    /// Replaced the given value.
-    Replaced(&'g V),
+    /// Replaced the given entry, which is valid for reads for the lifetime of the guard.
+    Replaced(*mut Entry<K, V>),
 
     /// Error returned by `try_insert`.
     Error {
@@ -168,6 +354,100 @@ impl<K, V> Entry<K, V> {
     /// Note that tombstone entries may still be marked as `COPYING`, so this state
     /// cannot be used for direct equality.
     const TOMBSTONE: *mut Entry<K, V> = Entry::COPIED as _;
+
+    /// Allocates the entry, reserving space for a version stamp and the hash of the key
+    /// if `extended` is set.
+    #[inline]
+    fn into_raw(self, hash: usize, extended: bool) -> *mut Entry<K, V> {
+        if extended {
+            let entry = ExtendedEntry {
+                entry: self,
+                version: 0,
+                hash,
+            };
+
+            Box::into_raw(Box::new(entry)).cast()
+        } else {
+            Box::into_raw(Box::new(self))
+        }
+    }
+
+    /// Deallocates an entry, returning its key and value.
+    ///
+    /// # Safety
+    ///
+    /// The entry must have been allocated by `Entry::into_raw` with the same `extended` flag,
+    /// and must not be accessed after this call.
+    #[inline]
+    unsafe fn from_raw(entry: *mut Entry<K, V>, extended: bool) -> Entry<K, V> {
+        if extended {
+            // Safety: Guaranteed by caller.
+            unsafe { Box::from_raw(entry.cast::<ExtendedEntry<K, V>>()).entry }
+        } else {
+            // Safety: Guaranteed by caller.
+            unsafe { *Box::from_raw(entry) }
+        }
+    }
+
+    /// Returns the version of the entry, or `0` if versioning is disabled.
+    ///
+    /// # Safety
+    ///
+    /// The entry must be valid for reads, and must have been allocated by `Entry::into_raw`
+    /// with the `extended` flag set if `versioned` is set.
+    #[inline]
+    unsafe fn version(entry: *const Entry<K, V>, versioned: bool) -> u64 {
+        if !versioned {
+            return 0;
+        }
+
+        // Safety: Guaranteed by caller.
+        unsafe { (*entry.cast::<ExtendedEntry<K, V>>()).version }
+    }
+
+    /// Returns the cached hash of the entry's key.
+    ///
+    /// # Safety
+    ///
+    /// The entry must be valid for reads, and must have been allocated by `Entry::into_raw`
+    /// with the `extended` flag set.
+    #[inline]
+    unsafe fn hash(entry: *const Entry<K, V>) -> usize {
+        // Safety: Guaranteed by caller.
+        unsafe { (*entry.cast::<ExtendedEntry<K, V>>()).hash }
+    }
+
+    /// Returns the function used to reclaim entries allocated with the given `extended` flag.
+    #[inline]
+    fn reclaim(extended: bool) -> unsafe fn(*mut Entry<K, V>, &Collector) {
+        if extended {
+            |entry, _| unsafe { drop(Box::from_raw(entry.cast::<ExtendedEntry<K, V>>())) }
+        } else {
+            seize::reclaim::boxed::<Entry<K, V>>
+        }
+    }
+}
+
+/// An entry allocated with a version stamp and the hash of its key.
+///
+/// Entries are allocated in this form if the map is versioned or caches hashes. The entry is
+/// stored at the start of the allocation, so a pointer to an extended entry is also a valid
+/// pointer to the inner `Entry`.
+#[repr(C)]
+struct ExtendedEntry<K, V> {
+    /// The entry.
+    entry: Entry<K, V>,
+
+    /// The version of the entry, stamped before it is written to the table.
+    ///
+    /// The version is only modified after the entry becomes reachable through exclusive access
+    /// to the table.
+    version: u64,
+
+    /// The primary hash of the entry's key, see `meta::h1`.
+    ///
+    /// This is only read if the map caches hashes.
+    hash: usize,
 }
 
 /// The status of an entry.
@@ -235,11 +515,25 @@ where
                 initial_capacity: 1,
                 table: AtomicPtr::new(ptr::null_mut()),
                 count: Counter::default(),
+                modifications: Counter::default(),
+                promotions: AtomicU64::new(0),
+                observer: None,
+                #[cfg(feature = "metrics")]
+                metrics: None,
+                versioned: false,
+                cache_hashes: false,
+                clock: AtomicU64::new(0),
+                track_access: false,
+                changes: OnceLock::new(),
+                snapshots: OnceLock::new(),
+                waiters: Waiters::default(),
+                loading: Mutex::default(),
+                parker: Parker::default(),
             };
         }
 
         // Initialize the table and mark it as the root.
-        let mut table = Table::alloc(probe::entries_for(capacity));
+        let mut table = Table::alloc(probe::entries_for(capacity), false);
         *table.state_mut().status.get_mut() = State::PROMOTED;
 
         HashMap {
@@ -249,8 +543,178 @@ where
             initial_capacity: capacity,
             table: AtomicPtr::new(table.raw),
             count: Counter::default(),
+            modifications: Counter::default(),
+            promotions: AtomicU64::new(0),
+            observer: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            versioned: false,
+            cache_hashes: false,
+            clock: AtomicU64::new(0),
+            track_access: false,
+            changes: OnceLock::new(),
+            snapshots: OnceLock::new(),
+            waiters: Waiters::default(),
+            loading: Mutex::default(),
+            parker: Parker::default(),
+        }
+    }
+
+    /// Enables or disables entry versioning.
+    ///
+    /// This must be configured before any entries are inserted into the table.
+    #[inline]
+    pub fn versioned(mut self, versioned: bool) -> HashMap<K, V, S, C> {
+        debug_assert_eq!(self.count.sum(), 0);
+        self.versioned = versioned;
+
+        // Snapshots rely on entry versions.
+        self.snapshots = OnceLock::new();
+        if versioned {
+            let _ = self.snapshots.set(Snapshots::new());
+        }
+
+        self
+    }
+
+    /// Returns `true` if entries are allocated with a version stamp.
+    #[inline]
+    pub fn is_versioned(&self) -> bool {
+        self.versioned
+    }
+
+    /// Enables or disables hash caching.
+    ///
+    /// When enabled, the hash of every key is stored in its entry and used for resizing and
+    /// to skip comparisons with keys that have a different hash. This must be configured
+    /// before any entries are inserted into the table.
+    #[inline]
+    pub fn cache_hashes(mut self, cache_hashes: bool) -> HashMap<K, V, S, C> {
+        debug_assert_eq!(self.count.sum(), 0);
+        self.cache_hashes = cache_hashes;
+        self
+    }
+
+    /// Returns `true` if entries are allocated with the hash of their key.
+    #[inline]
+    pub fn is_caching_hashes(&self) -> bool {
+        self.cache_hashes
+    }
+
+    /// Sets the observer notified of resize and parking events.
+    #[inline]
+    pub fn observer(mut self, observer: Option<Arc<dyn MapObserver>>) -> HashMap<K, V, S, C> {
+        self.observer = observer;
+        self
+    }
+
+    /// Returns the observer notified of resize and parking events, if there is one.
+    #[inline]
+    pub fn get_observer(&self) -> Option<&Arc<dyn MapObserver>> {
+        self.observer.as_ref()
+    }
+
+    /// Sets the name that exported metrics are labeled with, enabling metrics.
+    #[cfg(feature = "metrics")]
+    #[inline]
+    pub fn metrics(mut self, name: Option<Arc<str>>) -> HashMap<K, V, S, C> {
+        self.metrics = name;
+        self
+    }
+
+    /// Returns the current time if the map is observed, used to time observed events.
+    #[inline]
+    fn observe_start(&self) -> Option<Instant> {
+        self.observer.as_ref().map(|_| Instant::now())
+    }
+
+    /// Parks the current thread until the atomic no longer satisfies the condition, notifying
+    /// the observer of the time spent parked.
+    #[inline]
+    fn park<T>(&self, parker: &Parker, atomic: &impl Atomic<T>, should_park: impl Fn(T) -> bool) {
+        utils::span!(DEBUG, "park");
+
+        let start = self.observe_start();
+        parker.park(atomic, should_park);
+
+        if let (Some(observer), Some(start)) = (&self.observer, start) {
+            observer.parked(start.elapsed());
+        }
+    }
+
+    /// Returns `true` if entries are allocated as an `ExtendedEntry`.
+    #[inline]
+    fn extended(&self) -> bool {
+        self.versioned || self.cache_hashes
+    }
+
+    /// Enables or disables access tracking.
+    ///
+    /// When enabled, successful reads set the access bit of the slot they found the entry in,
+    /// which is used for eviction. This must be configured before any entries are inserted
+    /// into the table.
+    #[inline]
+    pub fn track_access(mut self, track_access: bool) -> HashMap<K, V, S, C> {
+        debug_assert_eq!(self.count.sum(), 0);
+
+        let table = *self.table.get_mut();
+        if !table.is_null() {
+            // Reallocate the initial table with the correct layout.
+            //
+            // Safety: The table is non-null and has not been shared.
+            let table = unsafe { Table::<Entry<K, V>>::from_raw(table) };
+            let mut new = Table::alloc(table.len(), track_access);
+            *new.state_mut().status.get_mut() = State::PROMOTED;
+
+            // Safety: The table is empty and is not accessible after it is replaced.
+            unsafe { Table::dealloc(table) };
+            *self.table.get_mut() = new.raw;
+        }
+
+        self.track_access = track_access;
+        self
+    }
+
+    /// Subscribes to changes made to the table.
+    #[inline]
+    pub fn subscribe(&self) -> ChangeStream<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.changes.get_or_init(Subscribers::new).subscribe()
+    }
+
+    /// Returns the subscribers to changes made to the table, if there are any.
+    #[inline]
+    fn subscribers(&self) -> Option<&Subscribers<K, V>> {
+        self.changes
+            .get()
+            .filter(|subscribers| subscribers.is_active())
+    }
+
+    /// Registers a snapshot of the table, returning its records and the version it was
+    /// taken at.
+    ///
+    /// # Panics
+    ///
+    /// Panics if versioning is not enabled.
+    #[inline]
+    pub fn snapshot(&self) -> (Arc<Records<K, V>>, u64) {
+        self.snapshots
+            .get()
+            .expect("snapshots require a versioned map, see `HashMapBuilder::versioned`")
+            .register(&self.clock)
+    }
+
+    /// Deregisters a snapshot of the table.
+    #[inline]
+    pub fn release_snapshot(&self, records: &Arc<Records<K, V>>) {
+        if let Some(snapshots) = self.snapshots.get() {
+            snapshots.release(records);
         }
     }
+
     /// Returns a guard for this collector
     pub fn guard(&self) -> MapGuard<LocalGuard<'_>> {
         // Safety: Created the guard from our collector.
@@ -306,6 +770,31 @@ where
     fn is_incremental(&self) -> bool {
         matches!(self.resize, ResizeMode::Incremental(_))
     }
+
+    /// Returns the minimum length of a table, derived from the initial capacity.
+    ///
+    /// Tables are never shrunk below this length.
+    #[inline]
+    fn min_len(&self) -> usize {
+        probe::entries_for(self.initial_capacity)
+    }
+
+    /// Returns an iterator that moves all entries out of the table.
+    #[inline]
+    pub fn into_entries(mut self) -> IntoIter<K, V> {
+        let remaining = self.count.sum();
+
+        // Take ownership of the table, leaving behind an empty map to be dropped.
+        let raw = std::mem::replace(self.table.get_mut(), ptr::null_mut());
+
+        IntoIter {
+            i: 0,
+            remaining,
+            // Safety: The root table is either null or a valid table allocation.
+            table: unsafe { Table::from_raw(raw) },
+            extended: self.extended(),
+        }
+    }
 }
 
 impl<K, V, S, C> HashMap<K, V, S, C>
@@ -319,61 +808,419 @@ where
     pub fn get<'g, Q>(&self, key: &Q, guard: &'g impl VerifiedGuard) -> Option<(&'g K, &'g V)>
     where
         Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.get_entry(key, guard)
+            .map(|entry| (&entry.key, &entry.value))
+    }
+
+    /// Returns a reference to the entry allocation corresponding to the key.
+    ///
+    /// The entry is guaranteed to remain valid for reads as long as the guard is held.
+    #[inline]
+    pub fn get_entry<'g, Q>(
+        &self,
+        key: &Q,
+        guard: &'g impl VerifiedGuard,
+    ) -> Option<&'g Entry<K, V>>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.get_entry_hashed(key, self.hasher.hash_one(key), guard)
+    }
+
+    /// Returns a reference to the entry allocation corresponding to the key, given the hash
+    /// of the key.
+    ///
+    /// If the hash does not match the hash of the key, the entry may not be found.
+    #[inline]
+    pub fn get_entry_hashed<'g, Q>(
+        &self,
+        key: &Q,
+        hash: u64,
+        guard: &'g impl VerifiedGuard,
+    ) -> Option<&'g Entry<K, V>>
+    where
+        Q: Equivalent<K> + ?Sized,
     {
         // Load the root table.
-        let mut table = self.root(guard);
+        let table = self.root(guard);
 
         // The table has not been initialized yet.
         if table.raw.is_null() {
             return None;
         }
 
-        let (h1, h2) = self.hash(key);
-
-        loop {
-            // Initialize the probe state.
-            let mut probe = Probe::start(h1, table.mask);
-
-            // Probe until we reach the limit.
-            'probe: while probe.len <= table.limit {
-                // Load the entry metadata first for cheap searches.
-                //
-                // Safety: `probe.i` is always in-bounds for the table length.
-                let meta = unsafe { table.meta(probe.i) }.load(Ordering::Acquire);
+        self.get_in::<_, false>(key, meta::h1(hash), meta::h2(hash), table, guard)
+    }
 
-                if meta == h2 {
-                    // Load the full entry.
-                    //
-                    // Safety: `probe.i` is always in-bounds for the table length.
-                    let entry = guard
-                        .protect(unsafe { table.entry(probe.i) }, Ordering::Acquire)
-                        .unpack();
+    /// Returns a reference to the entry corresponding to the key, for a task that is waiting
+    /// on the key.
+    ///
+    /// Unlike `get_entry`, this is guaranteed to observe any insert that does not observe the
+    /// registration of the waiter, see `Waiters::wake` for details.
+    #[inline]
+    fn get_entry_waiting<'g, Q>(
+        &self,
+        key: &Q,
+        guard: &'g impl VerifiedGuard,
+    ) -> Option<&'g Entry<K, V>>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        // Load the root table.
+        let table = self.root(guard);
 
-                    // The entry was deleted, keep probing.
-                    if entry.ptr.is_null() {
-                        probe.next(table.mask);
-                        continue 'probe;
-                    }
+        // The table has not been initialized yet.
+        if table.raw.is_null() {
+            return None;
+        }
 
-                    // Safety: We performed a protected load of the pointer using a verified guard with
-                    // `Acquire` and ensured that it is non-null, meaning it is valid for reads as long
-                    // as we hold the guard.
-                    let entry_ref = unsafe { &(*entry.ptr) };
+        let (h1, h2) = self.hash(key);
+        self.get_in::<_, true>(key, h1, h2, table, guard)
+    }
 
-                    // Check for a full match.
-                    if key.equivalent(&entry_ref.key) {
-                        // The entry was copied to the new table.
-                        //
-                        // In blocking resize mode we do not need to perform self check as all writes block
-                        // until any resizes are complete, making the root table the source of truth for readers.
-                        if entry.tag() & Entry::COPIED != 0 {
-                            break 'probe;
-                        }
+    /// Returns a waiter for the given key, used with [`HashMap::poll_until`].
+    #[inline]
+    pub fn waiter<Q>(&self, key: &Q) -> Waiter<'_>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.waiters.waiter(self.hasher.hash_one(key))
+    }
 
-                        // Found the correct entry, return the key and value.
-                        return Some((&entry_ref.key, &entry_ref.value));
-                    }
-                }
+    /// Polls for a value for the given key that satisfies the predicate.
+    ///
+    /// If the value is not ready, the task is woken when the key is next inserted or updated.
+    #[inline]
+    pub fn poll_until<'g, Q, F>(
+        &self,
+        key: &Q,
+        predicate: F,
+        waiter: &mut Waiter<'_>,
+        cx: &mut Context<'_>,
+        guard: &'g impl VerifiedGuard,
+    ) -> Poll<&'g V>
+    where
+        K: 'g,
+        V: 'g,
+        Q: Equivalent<K> + Hash + ?Sized,
+        F: FnOnce(&V) -> bool,
+    {
+        // Register our task before checking the wait condition, ensuring that we either observe
+        // any concurrent writes or are woken by them.
+        waiter.register(cx.waker());
+
+        match self.get_entry_waiting(key, guard) {
+            Some(entry) if predicate(&entry.value) => {
+                waiter.cancel();
+                Poll::Ready(&entry.value)
+            }
+            _ => Poll::Pending,
+        }
+    }
+
+    /// Polls for the value of the given key, or claims the right to load it.
+    ///
+    /// If the key is not present and is already being loaded, the task is woken when the key
+    /// is next inserted or updated, or the load is abandoned. The key is taken if the claim is
+    /// acquired.
+    #[inline]
+    pub fn poll_load<'g>(
+        &self,
+        key: &mut Option<K>,
+        waiter: &mut Waiter<'_>,
+        cx: &mut Context<'_>,
+        guard: &'g impl VerifiedGuard,
+    ) -> Poll<Load<'g, '_, K, V, S, C>>
+    where
+        K: 'g,
+        V: 'g,
+    {
+        let Some(current) = key else {
+            panic!("`poll_load` called after acquiring a claim");
+        };
+
+        // Register our task before checking the wait condition, ensuring that we either observe
+        // any concurrent writes or are woken by them.
+        waiter.register(cx.waker());
+
+        if let Some(entry) = self.get_entry_waiting(current, guard) {
+            waiter.cancel();
+            return Poll::Ready(Ok(&entry.value));
+        }
+
+        let hash = waiter.hash();
+        let mut loading = self.loading.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Check again while holding the lock, as the value is inserted before a claim is released.
+        if let Some((_, value)) = self.get(current, guard) {
+            waiter.cancel();
+            return Poll::Ready(Ok(value));
+        }
+
+        // The key is being loaded by another task.
+        if find_load(&loading, hash, current).is_some() {
+            return Poll::Pending;
+        }
+
+        waiter.cancel();
+        let key = key.take().unwrap();
+        Poll::Ready(Err(self.load_claim(&mut loading, key, hash)))
+    }
+
+    /// Returns the value of the given key, or claims the right to load it.
+    ///
+    /// If the key is not present and is already being loaded, this method blocks until the
+    /// load is completed or abandoned.
+    #[inline]
+    pub fn get_or_claim<'g>(
+        &self,
+        key: K,
+        guard: &'g impl VerifiedGuard,
+    ) -> Load<'g, '_, K, V, S, C>
+    where
+        K: 'g,
+        V: 'g,
+    {
+        let hash = self.hasher.hash_one(&key);
+
+        loop {
+            if let Some((_, value)) = self.get(&key, guard) {
+                return Ok(value);
+            }
+
+            let state = {
+                let mut loading = self.loading.lock().unwrap_or_else(PoisonError::into_inner);
+
+                // Check again while holding the lock, as the value is inserted before a claim
+                // is released.
+                if let Some((_, value)) = self.get(&key, guard) {
+                    return Ok(value);
+                }
+
+                match find_load(&loading, hash, &key) {
+                    Some(state) => state.clone(),
+                    None => return Err(self.load_claim(&mut loading, key, hash)),
+                }
+            };
+
+            // The key is being loaded by another thread, wait for it to complete.
+            self.park(&self.parker, &*state, |state| state == Loading::PENDING);
+        }
+    }
+
+    /// Claims the right to load the given key, which must not already be loading.
+    #[inline]
+    fn load_claim(&self, loading: &mut Loads<K>, key: K, hash: u64) -> LoadClaim<'_, K, V, S, C> {
+        let state = Arc::new(AtomicU8::new(Loading::PENDING));
+
+        loading.entry(hash).or_default().push(Loading {
+            key,
+            state: state.clone(),
+        });
+
+        LoadClaim {
+            map: self,
+            state,
+            hash,
+        }
+    }
+
+    /// Returns references to the entries corresponding to the keys.
+    ///
+    /// All keys are hashed and their probe sequences prefetched before any lookups are
+    /// resolved, hiding the latency of cache misses.
+    #[inline]
+    pub fn get_many<'g, Q, const N: usize>(
+        &self,
+        keys: [&Q; N],
+        guard: &'g impl VerifiedGuard,
+    ) -> [Option<(&'g K, &'g V)>; N]
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        // Load the root table.
+        let table = self.root(guard);
+
+        // The table has not been initialized yet.
+        if table.raw.is_null() {
+            return [None; N];
+        }
+
+        let hashes = keys.map(|key| self.hash_prefetch(key, table));
+
+        let mut hashes = hashes.into_iter();
+        keys.map(|key| {
+            let (h1, h2) = hashes.next().unwrap();
+            self.get_in::<_, false>(key, h1, h2, table, guard)
+                .map(|entry| (&entry.key, &entry.value))
+        })
+    }
+
+    /// Returns references to the entries corresponding to each key in the iterator.
+    ///
+    /// Keys are resolved in batches, see [`HashMap::get_many`] for details.
+    #[inline]
+    pub fn get_batch<'g, 'q, Q, I>(
+        &self,
+        keys: I,
+        guard: &'g impl VerifiedGuard,
+    ) -> Vec<Option<(&'g K, &'g V)>>
+    where
+        Q: Equivalent<K> + Hash + ?Sized + 'q,
+        I: IntoIterator<Item = &'q Q>,
+    {
+        let mut keys = keys.into_iter();
+        let mut results = Vec::with_capacity(keys.size_hint().0);
+
+        // Load the root table.
+        let table = self.root(guard);
+
+        // The table has not been initialized yet.
+        if table.raw.is_null() {
+            results.extend(keys.map(|_| None));
+            return results;
+        }
+
+        let mut batch = Vec::with_capacity(BATCH);
+        loop {
+            batch.extend(
+                keys.by_ref()
+                    .take(BATCH)
+                    .map(|key| (key, self.hash_prefetch(key, table))),
+            );
+
+            if batch.is_empty() {
+                return results;
+            }
+
+            results.extend(
+                batch
+                    .drain(..)
+                    .map(|(key, (h1, h2))| self.get_in::<_, false>(key, h1, h2, table, guard))
+                    .map(|entry| entry.map(|entry| (&entry.key, &entry.value))),
+            );
+        }
+    }
+
+    /// Returns a reference to the entry corresponding to the key, starting the search
+    /// from the given table.
+    ///
+    /// If `WAITING` is true, the search does not rely on the metadata of newly inserted
+    /// entries, see `get_entry_waiting`.
+    #[inline]
+    fn get_in<'g, Q, const WAITING: bool>(
+        &self,
+        key: &Q,
+        h1: usize,
+        h2: u8,
+        table: Table<Entry<K, V>>,
+        guard: &'g impl VerifiedGuard,
+    ) -> Option<&'g Entry<K, V>>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        // Select the search for the configuration of the map once, so that maps without
+        // cached hashes or access tracking do not pay for them while probing.
+        match (self.cache_hashes, self.track_access) {
+            (false, false) => {
+                self.get_in_with::<_, WAITING, false, false>(key, h1, h2, table, guard)
+            }
+            (true, false) => self.get_in_with::<_, WAITING, true, false>(key, h1, h2, table, guard),
+            (false, true) => self.get_in_with::<_, WAITING, false, true>(key, h1, h2, table, guard),
+            (true, true) => self.get_in_with::<_, WAITING, true, true>(key, h1, h2, table, guard),
+        }
+    }
+
+    /// Returns a reference to the entry corresponding to the key, starting the search
+    /// from the given table.
+    ///
+    /// `CACHE_HASHES` and `TRACK_ACCESS` must match the configuration of the map.
+    #[inline]
+    fn get_in_with<'g, Q, const WAITING: bool, const CACHE_HASHES: bool, const TRACK_ACCESS: bool>(
+        &self,
+        key: &Q,
+        h1: usize,
+        h2: u8,
+        mut table: Table<Entry<K, V>>,
+        guard: &'g impl VerifiedGuard,
+    ) -> Option<&'g Entry<K, V>>
+    where
+        Q: Equivalent<K> + ?Sized,
+    {
+        debug_assert_eq!(CACHE_HASHES, self.cache_hashes);
+        debug_assert_eq!(TRACK_ACCESS, self.track_access);
+
+        loop {
+            // Initialize the probe state.
+            let mut probe = Probe::start(h1, table.mask);
+
+            // Probe until we reach the limit.
+            'probe: while probe.len <= table.limit {
+                // Load the entry metadata first for cheap searches.
+                //
+                // Safety: `probe.i` is always in-bounds for the table length.
+                let mut meta = unsafe { table.meta(probe.i) }.load(Ordering::Acquire);
+
+                // The metadata of a new entry is written after the entry is inserted, and may
+                // not be visible to waiting tasks yet. Check the entry itself instead.
+                //
+                // Safety: `probe.i` is always in-bounds for the table length.
+                if WAITING
+                    && meta == meta::EMPTY
+                    && !unsafe { table.entry(probe.i) }
+                        .load(Ordering::Acquire)
+                        .is_null()
+                {
+                    meta = h2;
+                }
+
+                if meta == h2 {
+                    // Load the full entry.
+                    //
+                    // Safety: `probe.i` is always in-bounds for the table length.
+                    let entry = guard
+                        .protect(unsafe { table.entry(probe.i) }, Ordering::Acquire)
+                        .unpack();
+
+                    // The entry was deleted, keep probing.
+                    if entry.ptr.is_null() {
+                        probe.next(table.mask);
+                        continue 'probe;
+                    }
+
+                    // Safety: We performed a protected load of the pointer using a verified guard with
+                    // `Acquire` and ensured that it is non-null, meaning it is valid for reads as long
+                    // as we hold the guard.
+                    let entry_ref = unsafe { &(*entry.ptr) };
+
+                    // Check for a full match, skipping the key comparison if the cached hash
+                    // does not match.
+                    //
+                    // Safety: The entry is valid for reads, and entries are always allocated as
+                    // extended entries if hashes are cached.
+                    if (!CACHE_HASHES || unsafe { Entry::hash(entry.ptr) } == h1)
+                        && key.equivalent(&entry_ref.key)
+                    {
+                        // The entry was copied to the new table.
+                        //
+                        // In blocking resize mode we do not need to perform self check as all writes block
+                        // until any resizes are complete, making the root table the source of truth for readers.
+                        if entry.tag() & Entry::COPIED != 0 {
+                            break 'probe;
+                        }
+
+                        // Record the access for eviction.
+                        if TRACK_ACCESS {
+                            // Safety: `probe.i` is always in-bounds for the table length, and
+                            // all tables are allocated with access bits if tracking is enabled.
+                            unsafe { table.touch(probe.i) };
+                        }
+
+                        // Found the correct entry.
+                        return Some(entry_ref);
+                    }
+                }
 
                 // The key is not in the table.
                 //
@@ -407,13 +1254,114 @@ where
         value: V,
         replace: bool,
         guard: &'g impl VerifiedGuard,
+    ) -> InsertResult<'g, V> {
+        let hash = self.hash(&key);
+        self.insert_hashed(key, value, hash, replace, guard)
+    }
+
+    /// Inserts a key-value pair into the table, replacing any existing value.
+    ///
+    /// Returns the key and value of the replaced entry, if any.
+    #[inline]
+    pub fn insert_entry<'g>(
+        &self,
+        key: K,
+        value: V,
+        guard: &'g impl VerifiedGuard,
+    ) -> Option<(&'g K, &'g V)> {
+        let hash = self.hash(&key);
+        match self.insert_inner(key, value, hash, true, guard) {
+            RawInsertResult::Replaced(entry) => {
+                // Safety: The replaced entry is valid for reads as long as we hold the guard.
+                let entry = unsafe { &(*entry) };
+                Some((&entry.key, &entry.value))
+            }
+            RawInsertResult::Inserted(_) => {
+                // Increment the table length.
+                self.count.get(guard).fetch_add(1, Ordering::Relaxed);
+
+                None
+            }
+            RawInsertResult::Error { .. } => unreachable!(),
+        }
+    }
+
+    /// Inserts a key-value pair into the table, given the hash of the key.
+    ///
+    /// If the hash does not match the hash of the key, the key may be inserted more than once.
+    #[inline]
+    pub fn insert_with_hash<'g>(
+        &self,
+        key: K,
+        value: V,
+        hash: u64,
+        replace: bool,
+        guard: &'g impl VerifiedGuard,
+    ) -> InsertResult<'g, V> {
+        let hash = (meta::h1(hash), meta::h2(hash));
+        self.insert_hashed(key, value, hash, replace, guard)
+    }
+
+    /// Inserts every key-value pair in the iterator into the table.
+    ///
+    /// Entries are inserted in batches, with all keys in a batch hashed and their probe
+    /// sequences prefetched before any inserts are performed.
+    #[inline]
+    pub fn insert_batch<I>(&self, entries: I, guard: &impl VerifiedGuard)
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut entries = entries.into_iter();
+
+        // Reserve space for the entries up-front to avoid resizing mid-batch.
+        let (additional, _) = entries.size_hint();
+        if additional > 0 {
+            self.reserve(additional, guard);
+        }
+
+        let mut batch = Vec::with_capacity(BATCH);
+        loop {
+            // Load the root table.
+            let table = self.root(guard);
+
+            batch.extend(entries.by_ref().take(BATCH).map(|(key, value)| {
+                let hash = if table.raw.is_null() {
+                    self.hash(&key)
+                } else {
+                    self.hash_prefetch(&key, table)
+                };
+
+                (key, value, hash)
+            }));
+
+            if batch.is_empty() {
+                return;
+            }
+
+            for (key, value, hash) in batch.drain(..) {
+                self.insert_hashed(key, value, hash, true, guard);
+            }
+        }
+    }
+
+    /// Inserts a key-value pair into the table, given the hash of the key.
+    #[inline]
+    fn insert_hashed<'g>(
+        &self,
+        key: K,
+        value: V,
+        hash: (usize, u8),
+        replace: bool,
+        guard: &'g impl VerifiedGuard,
     ) -> InsertResult<'g, V> {
         // Perform the insert.
-        let raw_result = self.insert_inner(key, value, replace, guard);
+        let raw_result = self.insert_inner(key, value, hash, replace, guard);
 
         let result = match raw_result {
             // Updated an entry.
-            RawInsertResult::Replaced(value) => InsertResult::Replaced(value),
+            //
+            // Safety: The replaced entry is valid for reads as long as we hold the guard.
+            RawInsertResult::Replaced(entry) => InsertResult::Replaced(unsafe { &(*entry).value }),
 
             // Inserted a new entry.
             RawInsertResult::Inserted(value) => {
@@ -428,8 +1376,8 @@ where
                 current,
                 not_inserted,
             } => {
-                // Safety: We allocated this box above and it was not inserted into the table.
-                let not_inserted = unsafe { Box::from_raw(not_inserted) };
+                // Safety: We allocated this entry above and it was not inserted into the table.
+                let not_inserted = unsafe { Entry::from_raw(not_inserted, self.extended()) };
 
                 InsertResult::Error {
                     current,
@@ -447,11 +1395,12 @@ where
         &self,
         key: K,
         value: V,
+        (h1, h2): (usize, u8),
         should_replace: bool,
         guard: &'g impl VerifiedGuard,
     ) -> RawInsertResult<'g, K, V> {
         // Allocate the entry to be inserted.
-        let new_entry = untagged(Box::into_raw(Box::new(Entry { key, value })));
+        let new_entry = untagged(Entry { key, value }.into_raw(h1, self.extended()));
 
         // Safety: We just allocated the entry above.
         let new_ref = unsafe { &(*new_entry.ptr) };
@@ -464,8 +1413,6 @@ where
             table = self.init(None);
         }
 
-        let (h1, h2) = self.hash(&new_ref.key);
-
         let mut help_copy = true;
         loop {
             // Initialize the probe state.
@@ -488,7 +1435,7 @@ where
                     //
                     // Safety: `probe.i` is always in-bounds for the table length. Additionally,
                     // `new_entry` was allocated above and never shared.
-                    match unsafe { self.insert_at(probe.i, h2, new_entry.raw, table, guard) } {
+                    match unsafe { self.insert_at(probe.i, h1, h2, new_entry.raw, table, guard) } {
                         // Successfully inserted.
                         InsertStatus::Inserted => return RawInsertResult::Inserted(&new_ref.value),
 
@@ -535,7 +1482,9 @@ where
                 let entry_ref = unsafe { &(*entry.ptr) };
 
                 // Check for a full match.
-                if entry_ref.key != new_ref.key {
+                //
+                // Safety: The entry is valid for reads and was allocated by this map.
+                if !unsafe { self.hash_matches(entry.ptr, h1) } || entry_ref.key != new_ref.key {
                     probe.next(table.mask);
                     continue 'probe;
                 }
@@ -558,13 +1507,12 @@ where
                 // Safety:
                 // - `probe.i` is always in-bounds for the table length
                 // - `entry` is a valid non-null entry that was inserted into the map.
-                match unsafe { self.insert_slow(probe.i, entry, new_entry.raw, table, guard) } {
+                match unsafe { self.insert_slow(probe.i, h1, entry, new_entry.raw, table, guard) } {
                     // Successfully performed the update.
                     UpdateStatus::Replaced(entry) => {
-                        // Safety: `entry` is a valid non-null entry that we found in the map
+                        // Note that `entry` is a valid non-null entry that we found in the map
                         // before replacing it.
-                        let value = unsafe { &(*entry.ptr).value };
-                        return RawInsertResult::Replaced(value);
+                        return RawInsertResult::Replaced(entry.ptr);
                     }
 
                     // The entry is being copied.
@@ -597,6 +1545,7 @@ where
     unsafe fn insert_slow(
         &self,
         i: usize,
+        h1: usize,
         mut entry: Tagged<Entry<K, V>>,
         new_entry: *mut Entry<K, V>,
         table: Table<Entry<K, V>>,
@@ -606,7 +1555,7 @@ where
             // Try to update the value.
             //
             // Safety: Guaranteed by caller.
-            match unsafe { self.update_at(i, entry, new_entry, table, guard) } {
+            match unsafe { self.update_at(i, h1, entry, new_entry, table, guard) } {
                 // Someone else beat us to the update, retry.
                 //
                 // Note that the pointer we find here is a non-null entry that was inserted
@@ -684,12 +1633,31 @@ where
     pub fn remove_if<'g, Q, F>(
         &self,
         key: &Q,
-        mut should_remove: F,
+        should_remove: F,
         guard: &'g impl VerifiedGuard,
     ) -> Result<Option<(&'g K, &'g V)>, (&'g K, &'g V)>
     where
         Q: Equivalent<K> + Hash + ?Sized,
         F: FnMut(&K, &V) -> bool,
+    {
+        self.remove_if_hashed(key, self.hasher.hash_one(key), should_remove, guard)
+    }
+
+    /// Removes a key from the map if the provided closure returns `true`, given the hash
+    /// of the key.
+    ///
+    /// If the hash does not match the hash of the key, the entry may not be found.
+    #[inline]
+    pub fn remove_if_hashed<'g, Q, F>(
+        &self,
+        key: &Q,
+        hash: u64,
+        mut should_remove: F,
+        guard: &'g impl VerifiedGuard,
+    ) -> Result<Option<(&'g K, &'g V)>, (&'g K, &'g V)>
+    where
+        Q: Equivalent<K> + ?Sized,
+        F: FnMut(&K, &V) -> bool,
     {
         // Load the root table.
         let mut table = self.root(guard);
@@ -699,7 +1667,7 @@ where
             return Ok(None);
         }
 
-        let (h1, h2) = self.hash(key);
+        let (h1, h2) = (meta::h1(hash), meta::h2(hash));
 
         let mut help_copy = true;
         loop {
@@ -747,7 +1715,9 @@ where
                 // Safety: We performed a protected load of the pointer using a verified guard with
                 // `Acquire` and ensured that it is non-null, meaning it is valid for reads as long
                 // as we hold the guard.
-                if !key.equivalent(unsafe { &(*entry.ptr).key }) {
+                if !unsafe { self.hash_matches(entry.ptr, h1) }
+                    || !key.equivalent(unsafe { &(*entry.ptr).key })
+                {
                     probe.next(table.mask);
                     continue 'probe;
                 }
@@ -770,8 +1740,9 @@ where
                     // Safety:
                     // - `probe.i` is always in-bounds for the table length
                     // - `entry` is a valid non-null entry that we found in the map.
-                    let status =
-                        unsafe { self.update_at(probe.i, entry, Entry::TOMBSTONE, table, guard) };
+                    let status = unsafe {
+                        self.update_at(probe.i, h1, entry, Entry::TOMBSTONE, table, guard)
+                    };
 
                     match status {
                         // Successfully removed the entry.
@@ -792,6 +1763,9 @@ where
                             // Decrement the table length.
                             self.count.get(guard).fetch_sub(1, Ordering::Relaxed);
 
+                            // Check if the table should be shrunk.
+                            self.check_shrink(probe.i, table, guard);
+
                             // Note that `entry_ref` here is the entry that we just replaced.
                             return Ok(Some((&entry_ref.key, &entry_ref.value)));
                         }
@@ -821,23 +1795,225 @@ where
         }
     }
 
-    /// Prepare to retry an operation on an existing key in the next table.
+    /// Replaces the value of an entry if the map still contains the exact entry allocation,
+    /// returning the newly inserted entry.
     ///
-    /// Returns `None` if the recursive search has been exhausted.
-    #[cold]
-    fn prepare_retry(
+    /// On failure, the value that was not inserted is returned.
+    #[inline]
+    pub fn replace_if_current<'g>(
         &self,
-        copying: Option<usize>,
-        help_copy: &mut bool,
-        table: Table<Entry<K, V>>,
-        guard: &impl VerifiedGuard,
-    ) -> Option<Table<Entry<K, V>>> {
-        let next_table = match self.resize {
-            ResizeMode::Blocking => match copying {
-                // The entry we want to perform the operation on is being copied.
-                //
-                // In blocking mode we must complete the resize before proceeding.
-                Some(_) => self.help_copy(true, &table, guard),
+        current: &'g Entry<K, V>,
+        value: V,
+        guard: &'g impl VerifiedGuard,
+    ) -> Result<&'g Entry<K, V>, V>
+    where
+        K: Clone,
+    {
+        // Note that the key must be rehashed rather than reading the cached hash of `current`, as
+        // the entry may have been allocated by a different map.
+        let hash = self.hash(&current.key);
+
+        let new_entry = Entry {
+            key: current.key.clone(),
+            value,
+        }
+        .into_raw(hash.0, self.extended());
+
+        // Safety: `new_entry` was just allocated above and never shared.
+        if unsafe { self.update_if_current(current, hash, new_entry, guard) } {
+            // Safety: `new_entry` was inserted into the map, and we hold the guard.
+            return Ok(unsafe { &*new_entry });
+        }
+
+        // Safety: The entry was allocated above but not inserted into the map.
+        let new_entry = unsafe { Entry::from_raw(new_entry, self.extended()) };
+        Err(new_entry.value)
+    }
+
+    /// Removes an entry if the map still contains the exact entry allocation.
+    ///
+    /// Returns `true` if the entry was removed.
+    #[inline]
+    pub fn remove_if_current(&self, current: &Entry<K, V>, guard: &impl VerifiedGuard) -> bool {
+        let hash = self.hash(&current.key);
+
+        // Safety: Tombstones are valid sentinel pointers.
+        unsafe { self.update_if_current(current, hash, Entry::TOMBSTONE, guard) }
+    }
+
+    /// Replaces the entry if the map still contains the exact entry allocation, given the hash
+    /// of its key.
+    ///
+    /// Entry allocations are not reclaimed while the guard is held, so pointer identity is
+    /// not subject to ABA as long as `current` was loaded with the same guard.
+    ///
+    /// # Safety
+    ///
+    /// `new_entry` must be a valid sentinel or owned pointer to insert into the map.
+    #[inline]
+    unsafe fn update_if_current(
+        &self,
+        current: &Entry<K, V>,
+        (h1, h2): (usize, u8),
+        new_entry: *mut Entry<K, V>,
+        guard: &impl VerifiedGuard,
+    ) -> bool {
+        // Load the root table.
+        let mut table = self.root(guard);
+
+        // The table has not been initialized yet.
+        if table.raw.is_null() {
+            return false;
+        }
+
+        let current_ptr = current as *const Entry<K, V> as *mut Entry<K, V>;
+
+        let mut help_copy = true;
+        loop {
+            // Initialize the probe state.
+            let mut probe = Probe::start(h1, table.mask);
+
+            // Probe until we reach the limit.
+            let copying = 'probe: loop {
+                if probe.len > table.limit {
+                    break None;
+                }
+
+                // Load the entry metadata first for cheap searches.
+                //
+                // Safety: `probe.i` is always in-bounds for the table length.
+                let meta = unsafe { table.meta(probe.i).load(Ordering::Acquire) };
+
+                // The key is not in the table.
+                // It also cannot be in the next table because we have not went over the probe limit.
+                if meta == meta::EMPTY {
+                    return false;
+                }
+
+                // Check for a potential match.
+                if meta != h2 {
+                    probe.next(table.mask);
+                    continue 'probe;
+                }
+
+                // Load the full entry.
+                //
+                // Safety: `probe.i` is always in-bounds for the table length.
+                let mut entry = guard
+                    .protect(unsafe { table.entry(probe.i) }, Ordering::Acquire)
+                    .unpack();
+
+                // The entry was deleted, keep probing.
+                if entry.ptr.is_null() {
+                    probe.next(table.mask);
+                    continue 'probe;
+                }
+
+                if entry.ptr != current_ptr {
+                    // Safety: We performed a protected load of the pointer using a verified guard
+                    // with `Acquire` and ensured that it is non-null, meaning it is valid for reads
+                    // as long as we hold the guard.
+                    if !unsafe { self.hash_matches(entry.ptr, h1) }
+                        || unsafe { (*entry.ptr).key != current.key }
+                    {
+                        probe.next(table.mask);
+                        continue 'probe;
+                    }
+
+                    // The entry for this key may have been copied to the new table before it was
+                    // replaced, in which case the current entry is only accessible there.
+                    if entry.tag() & Entry::COPYING != 0 {
+                        break 'probe Some(probe.i);
+                    }
+
+                    // The entry was replaced.
+                    return false;
+                }
+
+                // The entry is being copied to the new table, we have to complete the copy before
+                // we can update it.
+                if entry.tag() & Entry::COPYING != 0 {
+                    break 'probe Some(probe.i);
+                }
+
+                loop {
+                    // Safety:
+                    // - `probe.i` is always in-bounds for the table length
+                    // - `entry` is a valid non-null entry that we found in the map.
+                    // - The caller guarantees that `new_entry` is valid to insert into the map.
+                    let status =
+                        unsafe { self.update_at(probe.i, h1, entry, new_entry, table, guard) };
+
+                    match status {
+                        // Successfully updated the entry.
+                        UpdateStatus::Replaced(_) => {
+                            if new_entry == Entry::TOMBSTONE {
+                                // Mark the entry as a tombstone.
+                                //
+                                // Safety: `probe.i` is always in-bounds for the table length.
+                                unsafe {
+                                    table
+                                        .meta(probe.i)
+                                        .store(meta::TOMBSTONE, Ordering::Release)
+                                };
+
+                                // Decrement the table length.
+                                self.count.get(guard).fetch_sub(1, Ordering::Relaxed);
+
+                                // Check if the table should be shrunk.
+                                self.check_shrink(probe.i, table, guard);
+                            }
+
+                            return true;
+                        }
+
+                        // The entry is being copied to the new table, we have to complete the copy
+                        // before we can update it.
+                        UpdateStatus::Found(EntryStatus::Copied(_)) => break 'probe Some(probe.i),
+
+                        // The entry was deleted.
+                        UpdateStatus::Found(EntryStatus::Null) => return false,
+
+                        // The update failed spuriously, retry.
+                        UpdateStatus::Found(EntryStatus::Value(found))
+                            if found.ptr == current_ptr =>
+                        {
+                            entry = found
+                        }
+
+                        // The entry was replaced.
+                        UpdateStatus::Found(EntryStatus::Value(_)) => return false,
+                    }
+                }
+            };
+
+            // Prepare to retry in the next table.
+            table = match self.prepare_retry(copying, &mut help_copy, table, guard) {
+                Some(table) => table,
+
+                // The search was exhausted.
+                None => return false,
+            }
+        }
+    }
+
+    /// Prepare to retry an operation on an existing key in the next table.
+    ///
+    /// Returns `None` if the recursive search has been exhausted.
+    #[cold]
+    fn prepare_retry(
+        &self,
+        copying: Option<usize>,
+        help_copy: &mut bool,
+        table: Table<Entry<K, V>>,
+        guard: &impl VerifiedGuard,
+    ) -> Option<Table<Entry<K, V>>> {
+        let next_table = match self.resize {
+            ResizeMode::Blocking => match copying {
+                // The entry we want to perform the operation on is being copied.
+                //
+                // In blocking mode we must complete the resize before proceeding.
+                Some(_) => self.help_copy(true, &table, guard),
 
                 // If we went over the probe limit, the key is not in the map.
                 None => return None,
@@ -881,10 +2057,14 @@ where
     ///
     /// The index must be in-bounds for the table. Additionally, `new_entry` must be a
     /// valid owned pointer to insert into the map.
+    ///
+    /// Note that `h1` and `meta` must be the hash of the key being inserted, which is used to
+    /// wake any tasks waiting on the key.
     #[inline]
     unsafe fn insert_at(
         &self,
         i: usize,
+        h1: usize,
         meta: u8,
         new_entry: *mut Entry<K, V>,
         table: Table<Entry<K, V>>,
@@ -894,6 +2074,27 @@ where
         let entry = unsafe { table.entry(i) };
         let meta_entry = unsafe { table.meta(i) };
 
+        // Safety: The caller guarantees that `new_entry` is valid for reads.
+        let new_key = unsafe { &(*new_entry).key };
+
+        // Preserve the absence of the key for any active snapshots.
+        //
+        // Note that the entry must be stamped after beginning the write.
+        let write = self.begin_write(|| h1, guard);
+        let preserved = match &write {
+            Some(write) if write.is_preserving() && entry.load(Ordering::Acquire).is_null() => {
+                // Safety: The key is only reachable by the snapshot if it is inserted below,
+                // otherwise we undo the preservation.
+                unsafe { write.preserve(new_key, ptr::null()) }
+            }
+            _ => false,
+        };
+
+        // Safety: The caller guarantees that `new_entry` is an owned pointer.
+        unsafe { self.stamp(new_entry) };
+
+        let changes = self.lock_changes(new_key);
+
         // Try to claim the empty entry.
         let found = match guard.compare_exchange(
             entry,
@@ -907,6 +2108,18 @@ where
                 // Update the metadata table.
                 meta_entry.store(meta, Ordering::Release);
 
+                // Wake any tasks waiting on the key.
+                self.waiters.wake(h1);
+
+                self.record_modification(guard);
+
+                if let Some((subscribers, _lock)) = changes {
+                    // Safety: The entry was inserted into the map, so it cannot be reclaimed
+                    // until the guard is dropped.
+                    let entry = unsafe { &*new_entry };
+                    subscribers.emit(RawChange::Inserted(&entry.key, &entry.value));
+                }
+
                 // Return the value we inserted.
                 return InsertStatus::Inserted;
             }
@@ -915,19 +2128,25 @@ where
             Err(found) => found.unpack(),
         };
 
+        // The key may be freed by the caller.
+        if preserved {
+            if let Some(write) = &write {
+                write.unpreserve(new_key, ptr::null());
+            }
+        }
+
         let (meta, status) = match EntryStatus::from(found) {
             EntryStatus::Value(_) | EntryStatus::Copied(_) => {
-                // Safety: We performed a protected load of the pointer using a verified guard
-                // with `Acquire` and ensured that it is non-null, meaning it is valid for reads
-                // as long as we hold the guard.
-                let key = unsafe { &(*found.ptr).key };
-
                 // An entry was inserted, we have to hash it to get the metadata.
                 //
                 // The logic is the same for copied entries here as we have to
                 // check if the key matches and continue the update in the new table.
-                let hash = self.hasher.hash_one(key);
-                (meta::h2(hash), EntryStatus::Value(found))
+                //
+                // Safety: We performed a protected load of the pointer using a verified guard
+                // with `Acquire` and ensured that it is non-null, meaning it is valid for reads
+                // as long as we hold the guard.
+                let (_, h2) = unsafe { self.entry_hash(found.ptr) };
+                (h2, EntryStatus::Value(found))
             }
 
             // The entry was deleted or null copied.
@@ -952,10 +2171,14 @@ where
     /// - The index must be in-bounds for the table.
     /// - `current` must be a valid non-null entry that was inserted into the map.
     /// - `new_entry` must be a valid sentinel or owned pointer to insert into the map.
+    ///
+    /// Note that `h1` must be the primary hash of the key being updated, which is used to
+    /// wake any tasks waiting on the key.
     #[inline]
     unsafe fn update_at(
         &self,
         i: usize,
+        h1: usize,
         current: Tagged<Entry<K, V>>,
         new_entry: *mut Entry<K, V>,
         table: Table<Entry<K, V>>,
@@ -964,6 +2187,30 @@ where
         // Safety: The caller guarantees that `i` is in-bounds.
         let entry = unsafe { table.entry(i) };
 
+        // Safety: The caller guarantees that `current` is a valid non-null entry.
+        let current_ref = unsafe { &*current.ptr };
+
+        // Preserve the current entry for any active snapshots.
+        let write = self.begin_write(|| h1, guard);
+        let preserved = match &write {
+            Some(write)
+                if write.is_preserving() && entry.load(Ordering::Acquire) == current.raw =>
+            {
+                // Safety: The entry is retired below after the snapshot was taken, so it
+                // remains valid for as long as the snapshot's guard is held. Otherwise, we
+                // undo the preservation.
+                unsafe { write.preserve(&current_ref.key, current.ptr) }
+            }
+            _ => false,
+        };
+
+        if new_entry != Entry::TOMBSTONE {
+            // Safety: The caller guarantees that `new_entry` is an owned pointer.
+            unsafe { self.stamp(new_entry) };
+        }
+
+        let changes = self.lock_changes(&current_ref.key);
+
         // Try to perform the update.
         let found = match guard.compare_exchange_weak(
             entry,
@@ -974,6 +2221,23 @@ where
         ) {
             // Successfully updated.
             Ok(_) => unsafe {
+                self.record_modification(guard);
+
+                // Wake any tasks waiting on the key.
+                if new_entry != Entry::TOMBSTONE {
+                    self.waiters.wake(h1);
+                }
+
+                if let Some((subscribers, _lock)) = changes {
+                    // Safety: Both entries are valid for reads until the guard is dropped.
+                    let old = &*current.ptr;
+                    subscribers.emit(if new_entry == Entry::TOMBSTONE {
+                        RawChange::Removed(&old.key, &old.value)
+                    } else {
+                        RawChange::Updated(&old.key, &old.value, &(*new_entry).value)
+                    });
+                }
+
                 // Safety: The caller guarantees that `current` is a valid non-null entry that was
                 // inserted into the map. Additionally, it is now unreachable from this table due
                 // to the CAS above.
@@ -986,6 +2250,13 @@ where
             Err(found) => found.unpack(),
         };
 
+        // The entry may not have been live when the snapshot was taken.
+        if preserved {
+            if let Some(write) = &write {
+                write.unpreserve(&current_ref.key, current.ptr);
+            }
+        }
+
         UpdateStatus::Found(EntryStatus::from(found))
     }
 
@@ -1017,6 +2288,45 @@ where
         }
     }
 
+    /// Shrink the table to hold at least `capacity` elements, or the current number of elements
+    /// if it is larger.
+    ///
+    /// The table is never shrunk below its initial capacity.
+    #[inline]
+    pub fn shrink_to(&self, capacity: usize, guard: &impl VerifiedGuard) {
+        let mut table = self.root(guard);
+
+        // The table has not yet been allocated, there is nothing to shrink.
+        if table.raw.is_null() {
+            return;
+        }
+
+        loop {
+            let capacity = probe::entries_for(self.count.sum().max(capacity)).max(self.min_len());
+
+            // The table is already small enough.
+            if table.len() <= capacity {
+                return;
+            }
+
+            // Complete any in-progress resize before shrinking the new table.
+            if table.next_table().is_some() {
+                table = self.help_copy(true, &table, guard);
+                continue;
+            }
+
+            // Race to allocate the new table.
+            self.get_or_alloc_next(Some(capacity), table);
+
+            // Force the copy to complete.
+            //
+            // Note that we only attempt to shrink the table once, even if the resulting table
+            // is larger than requested due to concurrent insertions.
+            self.help_copy(true, &table, guard);
+            return;
+        }
+    }
+
     /// Remove all entries from this table.
     #[inline]
     pub fn clear(&self, guard: &impl VerifiedGuard) {
@@ -1059,42 +2369,22 @@ where
 
                     // Try to delete the entry.
                     //
-                    // Safety: `i` is in bounds for the table length.
-                    let result = unsafe {
-                        table.entry(i).compare_exchange(
-                            entry.raw,
-                            Entry::TOMBSTONE,
-                            Ordering::Release,
-                            Ordering::Acquire,
-                        )
-                    };
-
-                    match result {
+                    // Safety: `i` is in bounds for the table length, and we performed a protected
+                    // load of the non-null entry.
+                    match unsafe { self.delete_at(i, entry, &table, guard) } {
                         // Successfully deleted the entry.
-                        Ok(_) => {
-                            // Update the metadata table.
-                            //
-                            // Safety: `i` is in bounds for the table length.
-                            unsafe { table.meta(i).store(meta::TOMBSTONE, Ordering::Release) };
-
-                            // Decrement the table length.
-                            self.count.get(guard).fetch_sub(1, Ordering::Relaxed);
-
-                            // Safety: The caller guarantees that `current` is a valid non-null entry that was
-                            // inserted into the map. Additionally, it is now unreachable from this table due
-                            // to the CAS above.
-                            unsafe { self.defer_retire(entry, &table, guard) };
-                            continue 'probe;
-                        }
+                        Ok(()) => continue 'probe,
 
                         // Lost to a concurrent update, retry.
-                        Err(found) => entry = found.unpack(),
+                        Err(found) => entry = found,
                     }
                 }
             }
 
             // We cleared every entry in this table.
             if !copying {
+                // The table is likely sparse now, shrink it if necessary.
+                self.try_shrink(table, guard);
                 break;
             }
 
@@ -1123,129 +2413,725 @@ where
             // Get a clean copy of the table to delete from.
             table = self.linearize(table, guard);
 
-            let mut copying = false;
-            'probe: for i in 0..table.len() {
-                // Load the entry metadata first to ensure consistency with calls to `get`
-                // for entries that are retained.
-                //
-                // Safety: `i` is in bounds for the table length.
-                let meta = unsafe { table.meta(i) }.load(Ordering::Acquire);
+            let copying = self.retain_range(&table, 0..table.len(), &mut f, guard);
 
-                // The entry is empty or deleted.
-                if matches!(meta, meta::EMPTY | meta::TOMBSTONE) {
-                    continue 'probe;
-                }
+            // We cleared every entry in this table.
+            if !copying {
+                // The table is likely sparse now, shrink it if necessary.
+                self.try_shrink(table, guard);
+                break;
+            }
 
-                // Load the entry to delete.
-                //
-                // Safety: `i` is in bounds for the table length.
-                let mut entry = guard
-                    .protect(unsafe { table.entry(i) }, Ordering::Acquire)
-                    .unpack();
+            // A resize prevented us from deleting all the entries in this table.
+            //
+            // Complete the resize and retry in the new table.
+            table = self.help_copy(true, &table, guard);
+        }
+    }
 
-                loop {
-                    // The entry is empty or already deleted.
-                    if entry.ptr.is_null() {
-                        continue 'probe;
-                    }
+    /// Retains only the elements specified by the predicate, splitting the scan of each table
+    /// across the rayon thread pool.
+    ///
+    /// Each worker thread pins its own guard, while the table is kept alive by the given guard.
+    #[cfg(feature = "rayon")]
+    pub fn par_retain<F>(&self, f: F, guard: &impl VerifiedGuard)
+    where
+        K: Send + Sync,
+        V: Send + Sync,
+        S: Sync,
+        C: Sync,
+        F: Fn(&K, &V) -> bool + Sync,
+    {
+        use rayon::prelude::*;
 
-                    // Found a non-empty entry being copied.
-                    if entry.tag() & Entry::COPYING != 0 {
-                        // Clear every entry in this table that we can, then deal with the copy.
-                        copying = true;
-                        continue 'probe;
-                    }
+        // The minimum number of entries scanned by a single worker.
+        const CHUNK: usize = 4096;
 
-                    // Safety: We performed a protected load of the pointer using a verified guard with
-                    // `Acquire` and ensured that it is non-null, meaning it is valid for reads as long
-                    // as we hold the guard.
-                    let entry_ref = unsafe { &*entry.ptr };
+        // A table shared across worker threads.
+        struct SharedTable<K, V>(Table<Entry<K, V>>);
 
-                    // Should we retain this entry?
-                    if f(&entry_ref.key, &entry_ref.value) {
-                        continue 'probe;
-                    }
+        // Safety: The table is protected by the caller's guard for the duration of the scan,
+        // and all accesses to the table are synchronized.
+        unsafe impl<K: Send + Sync, V: Send + Sync> Sync for SharedTable<K, V> {}
 
-                    // Try to delete the entry.
-                    //
-                    // Safety: `i` is in bounds for the table length.
-                    let result = unsafe {
-                        table.entry(i).compare_exchange(
-                            entry.raw,
-                            Entry::TOMBSTONE,
-                            Ordering::Release,
-                            Ordering::Acquire,
-                        )
-                    };
+        // Load the root table.
+        let mut table = self.root(guard);
 
-                    match result {
-                        // Successfully deleted the entry.
-                        Ok(_) => {
-                            // Update the metadata table.
-                            //
-                            // Safety: `i` is in bounds for the table length.
-                            unsafe { table.meta(i).store(meta::TOMBSTONE, Ordering::Release) };
+        // The table has not been initialized yet.
+        if table.raw.is_null() {
+            return;
+        }
 
-                            // Decrement the table length.
-                            self.count.get(guard).fetch_sub(1, Ordering::Relaxed);
+        loop {
+            // Get a clean copy of the table to delete from.
+            table = self.linearize(table, guard);
 
-                            // Safety: The caller guarantees that `current` is a valid non-null entry that was
-                            // inserted into the map. Additionally, it is now unreachable from this table due
-                            // to the CAS above.
-                            unsafe { self.defer_retire(entry, &table, guard) };
-                            continue 'probe;
-                        }
+            let shared = SharedTable(table);
+            let shared = &shared;
 
-                        // Lost to a concurrent update, retry.
-                        Err(found) => entry = found.unpack(),
+            let copying = (0..(table.len() + CHUNK - 1) / CHUNK)
+                .into_par_iter()
+                .map_init(
+                    || self.guard(),
+                    |guard, chunk| {
+                        let range = (chunk * CHUNK)..((chunk + 1) * CHUNK).min(shared.0.len());
+                        self.retain_range(&shared.0, range, &mut |k, v| f(k, v), guard)
+                    },
+                )
+                .reduce(|| false, |a, b| a || b);
+
+            // We cleared every entry in this table.
+            if !copying {
+                // The table is likely sparse now, shrink it if necessary.
+                self.try_shrink(table, guard);
+                break;
+            }
+
+            // A resize prevented us from deleting all the entries in this table.
+            //
+            // Complete the resize and retry in the new table.
+            table = self.help_copy(true, &table, guard);
+        }
+    }
+
+    /// Retains only the elements specified by the predicate in a chunk of the root table.
+    ///
+    /// The chunk starts at `cursor`, wrapped to the length of the table, and covers at most
+    /// `chunk` entries. Unlike `retain`, this method does not wait for in-progress resizes,
+    /// so entries that are being copied are skipped.
+    #[inline]
+    pub fn retain_chunk<F>(&self, cursor: usize, chunk: usize, mut f: F, guard: &impl VerifiedGuard)
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        // Load the root table.
+        let table = self.root(guard);
+
+        // The table has not been initialized yet.
+        if table.raw.is_null() {
+            return;
+        }
+
+        let start = cursor & table.mask;
+        let end = start.saturating_add(chunk).min(table.len());
+        self.retain_range(&table, start..end, &mut f, guard);
+    }
+
+    /// Evicts an entry from the root table using the CLOCK algorithm, returning the evicted
+    /// entry.
+    ///
+    /// The table is scanned starting from `hand`, which is advanced past every slot that is
+    /// visited. Slots with their access bit set are given a second chance, clearing the bit,
+    /// while the first unaccessed entry is removed. Returns `None` if no entry could be evicted
+    /// after two full passes over the table, which may happen if it is being resized.
+    ///
+    /// Access tracking must be enabled.
+    #[inline]
+    pub fn evict<'g>(
+        &self,
+        hand: &AtomicUsize,
+        guard: &'g impl VerifiedGuard,
+    ) -> Option<(&'g K, &'g V)> {
+        debug_assert!(self.track_access);
+
+        // Load the root table.
+        let table = self.root(guard);
+
+        // The table has not been initialized yet.
+        if table.raw.is_null() {
+            return None;
+        }
+
+        for _ in 0..table.len() * 2 {
+            let i = hand.fetch_add(1, Ordering::Relaxed) & table.mask;
+
+            // Safety: `i` is in bounds for the table length.
+            let meta = unsafe { table.meta(i) }.load(Ordering::Acquire);
+
+            // The entry is empty or deleted.
+            if matches!(meta, meta::EMPTY | meta::TOMBSTONE) {
+                continue;
+            }
+
+            // The entry was recently accessed, give it a second chance.
+            //
+            // Safety: `i` is in bounds for the table length, and the table was allocated with
+            // access bits.
+            if unsafe { table.access(i) }.swap(0, Ordering::Relaxed) != 0 {
+                continue;
+            }
+
+            // Load the entry to evict.
+            //
+            // Safety: `i` is in bounds for the table length.
+            let entry = guard
+                .protect(unsafe { table.entry(i) }, Ordering::Acquire)
+                .unpack();
+
+            // The entry was deleted, or is being copied to the new table.
+            if entry.ptr.is_null() || entry.tag() & Entry::COPYING != 0 {
+                continue;
+            }
+
+            // Try to delete the entry.
+            //
+            // Safety: `i` is in bounds for the table length, and we performed a protected
+            // load of the non-null entry.
+            if unsafe { self.delete_at(i, entry, &table, guard) }.is_err() {
+                // Lost to a concurrent update, move on to the next slot.
+                continue;
+            }
+
+            // Safety: We performed a protected load of the pointer using a verified guard with
+            // `Acquire` and ensured that it is non-null, meaning it is valid for reads as long
+            // as we hold the guard.
+            let entry_ref = unsafe { &*entry.ptr };
+
+            return Some((&entry_ref.key, &entry_ref.value));
+        }
+
+        None
+    }
+
+    /// Attempts to delete the entry at the given index, without following the probe sequence.
+    ///
+    /// Returns the entry that was found if the deletion lost to a concurrent update.
+    ///
+    /// # Safety
+    ///
+    /// The index must be in-bounds for the table, and `entry` must be a valid non-null entry that
+    /// was loaded from it with a protected load.
+    #[inline]
+    unsafe fn delete_at(
+        &self,
+        i: usize,
+        entry: Tagged<Entry<K, V>>,
+        table: &Table<Entry<K, V>>,
+        guard: &impl VerifiedGuard,
+    ) -> Result<(), Tagged<Entry<K, V>>> {
+        // Safety: The caller guarantees that `entry` is valid for reads.
+        let entry_ref = unsafe { &*entry.ptr };
+
+        // Preserve the entry for any active snapshots.
+        //
+        // Safety: The caller guarantees that `entry` is valid for reads.
+        let write = self.begin_write(|| unsafe { self.entry_hash(entry.ptr) }.0, guard);
+        let preserved = match &write {
+            // Safety: The caller guarantees that `i` is in-bounds.
+            Some(write)
+                if write.is_preserving()
+                    && unsafe { table.entry(i) }.load(Ordering::Acquire) == entry.raw =>
+            {
+                // Safety: The entry is retired below after the snapshot was taken, so it
+                // remains valid for as long as the snapshot's guard is held. Otherwise, we
+                // undo the preservation.
+                unsafe { write.preserve(&entry_ref.key, entry.ptr) }
+            }
+            _ => false,
+        };
+
+        let changes = self.lock_changes(&entry_ref.key);
+
+        // Safety: The caller guarantees that `i` is in-bounds.
+        let result = unsafe {
+            table.entry(i).compare_exchange(
+                entry.raw,
+                Entry::TOMBSTONE,
+                Ordering::Release,
+                Ordering::Acquire,
+            )
+        };
+
+        if let Err(found) = result {
+            // The entry may not have been live when the snapshot was taken.
+            if preserved {
+                if let Some(write) = &write {
+                    write.unpreserve(&entry_ref.key, entry.ptr);
+                }
+            }
+
+            return Err(found.unpack());
+        }
+
+        // Update the metadata table.
+        //
+        // Safety: The caller guarantees that `i` is in-bounds.
+        unsafe { table.meta(i).store(meta::TOMBSTONE, Ordering::Release) };
+
+        // Decrement the table length.
+        self.count.get(guard).fetch_sub(1, Ordering::Relaxed);
+        self.record_modification(guard);
+
+        if let Some((subscribers, _lock)) = changes {
+            subscribers.emit(RawChange::Removed(&entry_ref.key, &entry_ref.value));
+        }
+
+        // Safety: The caller guarantees that `entry` is a valid non-null entry that was inserted
+        // into the map. Additionally, it is now unreachable from this table due to the CAS above.
+        unsafe { self.defer_retire(entry, table, guard) };
+
+        Ok(())
+    }
+
+    /// Records a modification to the table, if versioning is enabled.
+    ///
+    /// Maps that are not versioned do not count modifications, avoiding a shared
+    /// read-modify-write on every write.
+    #[inline]
+    fn record_modification(&self, guard: &impl VerifiedGuard) {
+        if self.versioned {
+            self.modifications
+                .get(guard)
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Begins a write to the key with the given primary hash, if versioning is enabled.
+    ///
+    /// The write must be completed before the returned guard is dropped, and the key must be
+    /// preserved if there are any active snapshots, see `Write::preserve` for details.
+    #[inline]
+    fn begin_write(
+        &self,
+        h1: impl FnOnce() -> usize,
+        guard: &impl VerifiedGuard,
+    ) -> Option<Write<'_, K, V>> {
+        let snapshots = self.snapshots.get()?;
+        Some(snapshots.write(h1, guard))
+    }
+
+    /// Acquires the lock for changes to the given key, if there are any subscribers.
+    ///
+    /// The lock must be held across a mutation of the key and the emission of its change.
+    #[inline]
+    fn lock_changes(&self, key: &K) -> Option<(&Subscribers<K, V>, MutexGuard<'_, ()>)> {
+        let subscribers = self.subscribers()?;
+        let lock = subscribers.lock(self.hasher.hash_one(key));
+        Some((subscribers, lock))
+    }
+
+    /// Retains only the elements specified by the predicate in the given range of the table.
+    ///
+    /// Returns `true` if any entries in the range were being copied, and so could not be deleted.
+    #[inline]
+    fn retain_range<F>(
+        &self,
+        table: &Table<Entry<K, V>>,
+        range: std::ops::Range<usize>,
+        f: &mut F,
+        guard: &impl VerifiedGuard,
+    ) -> bool
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut copying = false;
+        'probe: for i in range {
+            // Load the entry metadata first to ensure consistency with calls to `get`
+            // for entries that are retained.
+            //
+            // Safety: `i` is in bounds for the table length.
+            let meta = unsafe { table.meta(i) }.load(Ordering::Acquire);
+
+            // The entry is empty or deleted.
+            if matches!(meta, meta::EMPTY | meta::TOMBSTONE) {
+                continue 'probe;
+            }
+
+            // Load the entry to delete.
+            //
+            // Safety: `i` is in bounds for the table length.
+            let mut entry = guard
+                .protect(unsafe { table.entry(i) }, Ordering::Acquire)
+                .unpack();
+
+            loop {
+                // The entry is empty or already deleted.
+                if entry.ptr.is_null() {
+                    continue 'probe;
+                }
+
+                // Found a non-empty entry being copied.
+                if entry.tag() & Entry::COPYING != 0 {
+                    // Clear every entry in this table that we can, then deal with the copy.
+                    copying = true;
+                    continue 'probe;
+                }
+
+                // Safety: We performed a protected load of the pointer using a verified guard with
+                // `Acquire` and ensured that it is non-null, meaning it is valid for reads as long
+                // as we hold the guard.
+                let entry_ref = unsafe { &*entry.ptr };
+
+                // Should we retain this entry?
+                if f(&entry_ref.key, &entry_ref.value) {
+                    continue 'probe;
+                }
+
+                // Try to delete the entry.
+                //
+                // Safety: `i` is in bounds for the table length, and we performed a protected
+                // load of the non-null entry.
+                match unsafe { self.delete_at(i, entry, table, guard) } {
+                    // Successfully deleted the entry.
+                    Ok(()) => continue 'probe,
+
+                    // Lost to a concurrent update, retry.
+                    Err(found) => entry = found,
+                }
+            }
+        }
+
+        copying
+    }
+
+    /// Returns an iterator over the keys and values of this table.
+    #[inline]
+    pub fn iter<'g, G>(&self, guard: &'g G) -> Iter<'g, K, V, G>
+    where
+        G: VerifiedGuard,
+    {
+        // Load the root table.
+        let root = self.root(guard);
+
+        // The table has not been initialized yet, return a dummy iterator.
+        if root.raw.is_null() {
+            return Iter {
+                i: 0,
+                end: 0,
+                guard,
+                table: root,
+            };
+        }
+
+        // Get a clean copy of the table to iterate over.
+        let table = self.linearize(root, guard);
+
+        Iter {
+            i: 0,
+            end: table.len(),
+            guard,
+            table,
+        }
+    }
+
+    /// Returns an iterator over a partition of the table.
+    ///
+    /// The table is split into `of` contiguous ranges of slots, and the iterator only visits
+    /// the range at the given index.
+    #[inline]
+    pub fn iter_partition<'g, G>(&self, index: usize, of: usize, guard: &'g G) -> Iter<'g, K, V, G>
+    where
+        G: VerifiedGuard,
+    {
+        assert!(
+            index < of,
+            "partition index {index} out of range for {of} partitions"
+        );
+
+        let mut iter = self.iter(guard);
+        let len = iter.end as u128;
+
+        // Note that the bounds are computed with 128-bit integers to avoid overflow.
+        iter.i = (index as u128 * len / of as u128) as usize;
+        iter.end = ((index as u128 + 1) * len / of as u128) as usize;
+        iter
+    }
+
+    /// Returns a batch of at least `count` entries starting from the given cursor, along with
+    /// the cursor to resume the scan from, or `0` if the scan is complete.
+    ///
+    /// Entries are visited in the order of their reversed hash, which is independent of the
+    /// table size. This means that the cursor remains valid across resizes.
+    ///
+    /// In incremental resize mode, this blocks until any in-progress resizes are complete.
+    pub fn scan<'g>(
+        &self,
+        cursor: u64,
+        count: usize,
+        guard: &'g impl VerifiedGuard,
+    ) -> (u64, Vec<(&'g K, &'g V)>) {
+        let mut entries = Vec::new();
+
+        // Load the root table.
+        let root = self.root(guard);
+
+        // The table has not been initialized yet.
+        if root.raw.is_null() {
+            return (0, entries);
+        }
+
+        // Complete any in-progress resizes, so that all entries are in a single table.
+        let table = self.linearize(root, guard);
+
+        // The number of hash bits used to index into the table.
+        let bits = table.len().trailing_zeros();
+
+        // The bucket containing the cursor, in reversed order.
+        let mut bucket = cursor.checked_shr(u64::BITS - bits).unwrap_or(0);
+
+        loop {
+            // The home slot of entries in the bucket.
+            let home = (bucket.checked_shl(u64::BITS - bits).unwrap_or(0)).reverse_bits() as usize;
+
+            // Probe for every entry whose probe sequence starts at the home slot.
+            let mut probe = Probe::start(home, table.mask);
+            while probe.len <= table.limit {
+                // Safety: `probe.i` is always in-bounds for the table length.
+                let meta = unsafe { table.meta(probe.i) }.load(Ordering::Acquire);
+
+                // There are no more entries in the probe sequence.
+                if meta == meta::EMPTY {
+                    break;
+                }
+
+                if meta != meta::TOMBSTONE {
+                    // Safety: `probe.i` is always in-bounds for the table length.
+                    let entry = guard
+                        .protect(unsafe { table.entry(probe.i) }, Ordering::Acquire)
+                        .unpack();
+
+                    if !entry.ptr.is_null() {
+                        // Safety: We performed a protected load of the pointer using a verified
+                        // guard with `Acquire` and ensured that it is non-null, meaning it is
+                        // valid for reads as long as we hold the guard.
+                        let entry_ref = unsafe { &(*entry.ptr) };
+
+                        // Use the cached hash if the entry has one, which is the hash the entry
+                        // was inserted with.
+                        //
+                        // Safety: The entry is valid for reads and was allocated by this map.
+                        let (h1, _) = unsafe { self.entry_hash(entry.ptr) };
+
+                        // Skip entries from other buckets, and any entries in this bucket that
+                        // were before the cursor in a larger table.
+                        if h1 & table.mask == home && (h1 as u64).reverse_bits() >= cursor {
+                            entries.push((&entry_ref.key, &entry_ref.value));
+                        }
                     }
                 }
+
+                probe.next(table.mask);
+            }
+
+            bucket += 1;
+
+            // Visited every bucket, the scan is complete.
+            if bucket == table.len() as u64 {
+                return (0, entries);
+            }
+
+            // Return complete buckets, so that the cursor is aligned for any table size.
+            if entries.len() >= count {
+                return (bucket.checked_shl(u64::BITS - bits).unwrap_or(0), entries);
+            }
+        }
+    }
+
+    /// Records the capacity and length of the table to the exported metrics, if metrics
+    /// are enabled.
+    #[cfg(feature = "metrics")]
+    pub fn record_metrics(&self, guard: &impl VerifiedGuard) {
+        if let Some(name) = &self.metrics {
+            let root = self.root(guard);
+            let capacity = if root.raw.is_null() { 0 } else { root.len() };
+            utils::metrics::record(name, capacity, self.len());
+        }
+    }
+
+    /// Returns statistics about the internal state of the table.
+    pub fn stats(&self, guard: &impl VerifiedGuard) -> MapStats {
+        let mut stats = MapStats {
+            len: self.len(),
+            capacity: 0,
+            live: 0,
+            tombstones: 0,
+            probe_lengths: Vec::new(),
+            probe_limit: 0,
+            resize: None,
+            depth: 0,
+            deferred: 0,
+            promotions: self.promotions.load(Ordering::Relaxed),
+        };
+
+        // Load the root table.
+        let root = self.root(guard);
+
+        // The table has not been initialized yet.
+        if root.raw.is_null() {
+            return stats;
+        }
+
+        stats.capacity = root.len();
+        stats.probe_limit = root.limit;
+        stats.probe_lengths = vec![0; root.limit + 1];
+
+        for i in 0..root.len() {
+            // Safety: `i` is in bounds for the table length.
+            let meta = unsafe { root.meta(i) }.load(Ordering::Acquire);
+
+            if meta == meta::EMPTY {
+                continue;
+            }
+
+            // Safety: `i` is in bounds for the table length.
+            let entry = guard
+                .protect(unsafe { root.entry(i) }, Ordering::Acquire)
+                .unpack();
+
+            // The entry was deleted.
+            if meta == meta::TOMBSTONE || entry.ptr.is_null() {
+                stats.tombstones += 1;
+                continue;
+            }
+
+            stats.live += 1;
+
+            // Find the position of the entry in its probe sequence.
+            //
+            // Safety: We performed a protected load of the pointer using a verified guard with
+            // `Acquire` and ensured that it is non-null, meaning it is valid for reads as long
+            // as we hold the guard.
+            let (h1, _) = unsafe { self.entry_hash(entry.ptr) };
+            let mut probe = Probe::start(h1, root.mask);
+            while probe.i != i && probe.len <= root.limit {
+                probe.next(root.mask);
             }
 
-            // We cleared every entry in this table.
-            if !copying {
-                break;
+            // Note that entries inserted with an incorrect hash may not be found.
+            if probe.len <= root.limit {
+                stats.probe_lengths[probe.len] += 1;
             }
+        }
 
-            // A resize prevented us from deleting all the entries in this table.
-            //
-            // Complete the resize and retry in the new table.
-            table = self.help_copy(true, &table, guard);
+        // Report the progress of any in-progress resize.
+        if let Some(next) = root.next_table() {
+            let state = next.state();
+            stats.resize = Some(ResizeStats {
+                capacity: next.len(),
+                copied: state.copied.load(Ordering::Acquire).min(root.len()),
+                claimed: state.claim.load(Ordering::Acquire).min(root.len()),
+            });
+        }
+
+        // Walk the root and any nested tables.
+        let mut table = Some(root);
+        while let Some(current) = table {
+            stats.deferred += current.state().deferred.len();
+
+            table = current.next_table();
+            if table.is_some() {
+                stats.depth += 1;
+            }
         }
+
+        stats
     }
 
-    /// Returns an iterator over the keys and values of this table.
+    /// Returns the h1 and h2 hash for the given key.
     #[inline]
-    pub fn iter<'g, G>(&self, guard: &'g G) -> Iter<'g, K, V, G>
+    pub fn hash<Q>(&self, key: &Q) -> (usize, u8)
     where
-        G: VerifiedGuard,
+        Q: Hash + ?Sized,
     {
-        // Load the root table.
-        let root = self.root(guard);
+        let hash = self.hasher.hash_one(key);
+        (meta::h1(hash), meta::h2(hash))
+    }
 
-        // The table has not been initialized yet, return a dummy iterator.
-        if root.raw.is_null() {
-            return Iter {
-                i: 0,
-                guard,
-                table: root,
-            };
+    /// Returns the h1 and h2 hash for the key of an entry, using the cached hash if hashes
+    /// are cached.
+    ///
+    /// # Safety
+    ///
+    /// The entry must be valid for reads and must have been allocated by this map.
+    #[inline]
+    unsafe fn entry_hash(&self, entry: *const Entry<K, V>) -> (usize, u8) {
+        if self.cache_hashes {
+            // Safety: Guaranteed by caller, and entries are always allocated as extended
+            // entries if hashes are cached.
+            let h1 = unsafe { Entry::hash(entry) };
+            (h1, meta::h2(h1 as u64))
+        } else {
+            // Safety: Guaranteed by caller.
+            self.hash(unsafe { &(*entry).key })
         }
+    }
 
-        // Get a clean copy of the table to iterate over.
-        let table = self.linearize(root, guard);
-
-        Iter { i: 0, guard, table }
+    /// Returns `false` if the key of the entry is known not to have the given h1 hash.
+    ///
+    /// This is used to skip key comparisons, and always returns `true` if hashes are not
+    /// cached.
+    ///
+    /// # Safety
+    ///
+    /// The entry must be valid for reads and must have been allocated by this map.
+    #[inline]
+    unsafe fn hash_matches(&self, entry: *const Entry<K, V>, h1: usize) -> bool {
+        // Safety: Guaranteed by caller, and entries are always allocated as extended
+        // entries if hashes are cached.
+        !self.cache_hashes || unsafe { Entry::hash(entry) } == h1
     }
 
-    /// Returns the h1 and h2 hash for the given key.
+    /// Returns the h1 and h2 hash for the given key, prefetching the start of its probe
+    /// sequence in the given table.
     #[inline]
-    fn hash<Q>(&self, key: &Q) -> (usize, u8)
+    fn hash_prefetch<Q>(&self, key: &Q, table: Table<Entry<K, V>>) -> (usize, u8)
     where
         Q: Hash + ?Sized,
     {
-        let hash = self.hasher.hash_one(key);
-        (meta::h1(hash), meta::h2(hash))
+        let (h1, h2) = self.hash(key);
+
+        // Safety: The probe start is always in-bounds for the table length.
+        unsafe { table.prefetch(h1 & table.mask) };
+
+        (h1, h2)
+    }
+
+    /// Stamps an entry with the next version, if versioning is enabled.
+    ///
+    /// Entries must be stamped immediately before every attempt to write them to the table,
+    /// after loading any entry they replace. This ensures versions increase monotonically for
+    /// a given key.
+    ///
+    /// # Safety
+    ///
+    /// The entry must have been allocated by `Entry::into_raw`, and must either not yet be
+    /// reachable from the table, or be accessed with exclusive access to the table.
+    #[inline]
+    unsafe fn stamp<T>(&self, entry: *mut Entry<K, T>) {
+        if self.versioned {
+            let version = self.clock.fetch_add(1, Ordering::Relaxed) + 1;
+
+            // Safety: Guaranteed by caller.
+            unsafe { (*entry.cast::<ExtendedEntry<K, T>>()).version = version };
+        }
+    }
+
+    /// Returns the version of an entry in the table.
+    ///
+    /// # Safety
+    ///
+    /// The entry must have been allocated by this map.
+    #[inline]
+    pub unsafe fn version(&self, entry: &Entry<K, V>) -> u64 {
+        // Safety: Guaranteed by caller, and all entries in the table are allocated
+        // with the versioning flag of the map.
+        unsafe { Entry::version(entry, self.versioned) }
+    }
+
+    /// Records modifications made with exclusive access to the table, if versioning is enabled.
+    #[inline]
+    fn record_modification_mut(&mut self, modifications: usize) {
+        if self.versioned {
+            *self.modifications.get_mut() += modifications as isize;
+        }
+    }
+
+    /// Returns the number of modifications made to the table.
+    ///
+    /// This is always zero if versioning is disabled.
+    #[inline]
+    pub fn modification_count(&self) -> u64 {
+        self.modifications.sum() as u64
     }
 }
 
@@ -1272,7 +3158,7 @@ struct CachedUpdate<K, V, T> {
 
 impl<'g, F, K, V, T> ComputeState<F, K, V, T>
 where
-    F: FnMut(Option<(&'g K, &'g V)>) -> Operation<V, T>,
+    F: FnMut(&K, Option<(&'g K, &'g V)>) -> Operation<V, T>,
     K: 'g,
     V: 'g,
 {
@@ -1292,7 +3178,7 @@ where
     ///
     /// The entry pointer must be valid for reads if provided.
     #[inline]
-    unsafe fn next(&mut self, entry: Option<*mut Entry<K, V>>) -> Operation<V, T> {
+    unsafe fn next(&mut self, key: &K, entry: Option<*mut Entry<K, V>>) -> Operation<V, T> {
         let Some(entry) = entry else {
             // If there is no current entry, perform a transition for the insert.
             return match self.insert.take() {
@@ -1300,7 +3186,7 @@ where
                 Some(value) => Operation::Insert(value),
 
                 // Otherwise, compute the value to insert.
-                None => (self.compute)(None),
+                None => (self.compute)(key, None),
             };
         };
 
@@ -1313,7 +3199,7 @@ where
             _ => {
                 // Safety: The caller guarantees that `entry` is valid for reads.
                 let entry_ref = unsafe { &*entry };
-                (self.compute)(Some((&entry_ref.key, &entry_ref.value)))
+                (self.compute)(key, Some((&entry_ref.key, &entry_ref.value)))
             }
         }
     }
@@ -1361,7 +3247,7 @@ impl<K, V> LazyEntry<K, V> {
     /// Initializes the entry if it has not already been initialized, returning the pointer
     /// to the entry allocation.
     #[inline]
-    fn init(&mut self) -> *mut Entry<K, MaybeUninit<V>> {
+    fn init(&mut self, hash: usize, extended: bool) -> *mut Entry<K, MaybeUninit<V>> {
         match self {
             LazyEntry::Init(entry) => *entry,
             LazyEntry::Uninit(key) => {
@@ -1371,10 +3257,11 @@ impl<K, V> LazyEntry<K, V> {
                 unsafe {
                     let key = ptr::read(key);
                     let entry = panic::catch_unwind(panic::AssertUnwindSafe(|| {
-                        Box::into_raw(Box::new(Entry {
+                        Entry {
                             value: MaybeUninit::uninit(),
                             key,
-                        }))
+                        }
+                        .into_raw(hash, extended)
                     }))
                     .unwrap_or_else(|_| std::process::abort());
                     ptr::write(self, LazyEntry::Init(entry));
@@ -1506,44 +3393,285 @@ where
                 new: (_, value), ..
             } => value,
 
-            // Return the value we inserted.
-            Compute::Inserted(_, value) => value,
+            // Return the value we inserted.
+            Compute::Inserted(_, value) => value,
+
+            _ => unreachable!(),
+        }
+    }
+
+    /// Update an entry with a CAS function.
+    ///
+    /// Note that `compute` closure is guaranteed to be called for a `None` input only once, allowing the
+    /// insertion of values that cannot be cloned or reconstructed.
+    #[inline]
+    pub fn compute<'g, F, T>(
+        &self,
+        key: K,
+        compute: F,
+        guard: &'g impl VerifiedGuard,
+    ) -> Compute<'g, K, V, T>
+    where
+        F: FnMut(Option<(&'g K, &'g V)>) -> Operation<V, T>,
+    {
+        let hash = self.hash(&key);
+        let mut compute = compute;
+        self.compute_inner(key, hash, |_: &K, entry| compute(entry), guard)
+    }
+
+    /// Update an entry with a CAS function that is also passed the key being computed.
+    ///
+    /// The key is the key that will be inserted into the map, and may differ from the key
+    /// of the current entry. As with [`compute`](HashMap::compute), the closure is called
+    /// for a `None` input at most once.
+    #[inline]
+    pub fn compute_with_key<'g, F, T>(
+        &self,
+        key: K,
+        compute: F,
+        guard: &'g impl VerifiedGuard,
+    ) -> Compute<'g, K, V, T>
+    where
+        F: FnMut(&K, Option<(&'g K, &'g V)>) -> Operation<V, T>,
+    {
+        let hash = self.hash(&key);
+        self.compute_inner(key, hash, compute, guard)
+    }
+
+    /// Update an entry with a CAS function, given the hash of the key.
+    ///
+    /// If the hash does not match the hash of the key, the key may be inserted more than once.
+    #[inline]
+    pub fn compute_hashed<'g, F, T>(
+        &self,
+        key: K,
+        hash: u64,
+        compute: F,
+        guard: &'g impl VerifiedGuard,
+    ) -> Compute<'g, K, V, T>
+    where
+        F: FnMut(Option<(&'g K, &'g V)>) -> Operation<V, T>,
+    {
+        let hash = (meta::h1(hash), meta::h2(hash));
+        let mut compute = compute;
+        self.compute_inner(key, hash, |_: &K, entry| compute(entry), guard)
+    }
+
+    /// Update an entry with a CAS function, given the h1 and h2 hash of the key.
+    #[inline]
+    fn compute_inner<'g, F, T>(
+        &self,
+        key: K,
+        hash: (usize, u8),
+        compute: F,
+        guard: &'g impl VerifiedGuard,
+    ) -> Compute<'g, K, V, T>
+    where
+        F: FnMut(&K, Option<(&'g K, &'g V)>) -> Operation<V, T>,
+    {
+        // Lazy initialize the entry allocation.
+        let mut entry = LazyEntry::Uninit(key);
+
+        // Perform the update.
+        //
+        // Safety: We just allocated the entry above.
+        let result =
+            unsafe { self.compute_with(&mut entry, hash, ComputeState::new(compute), guard) };
+
+        // Deallocate the entry if it was not inserted.
+        if matches!(result, Compute::Removed(..) | Compute::Aborted(_)) {
+            if let LazyEntry::Init(entry) = entry {
+                // Safety: The entry was allocated but not inserted into the map.
+                let _ = unsafe { Entry::from_raw(entry, self.extended()) };
+            }
+        }
+
+        result
+    }
+
+    /// Replaces the value of an existing entry if it is equal to `expected`.
+    ///
+    /// On failure, the current value is returned, along with the value that was not inserted.
+    #[inline]
+    pub fn compare_exchange<'g>(
+        &self,
+        key: K,
+        expected: &V,
+        new: V,
+        guard: &'g impl VerifiedGuard,
+    ) -> Result<&'g V, (Option<&'g V>, V)>
+    where
+        V: PartialEq,
+    {
+        // Lazy initialize the entry allocation.
+        let mut entry = LazyEntry::Uninit(key);
+        let mut value = Some(new);
+
+        match self.compare_exchange_with(&mut entry, expected, &mut value, guard) {
+            Ok(value) => Ok(value),
+            Err(current) => {
+                let new = match entry {
+                    // Recover the value from the entry allocation.
+                    LazyEntry::Init(entry) => {
+                        // Safety: The entry was allocated and initialized with the new value,
+                        // but not inserted into the map.
+                        let entry = unsafe { Entry::from_raw(entry, self.extended()) };
+                        unsafe { entry.value.assume_init_read() }
+                    }
+
+                    // The value was never moved into an allocation.
+                    //
+                    // Safety: The value is only taken when the entry is initialized.
+                    LazyEntry::Uninit(_) => unsafe { value.unwrap_unchecked() },
+                };
+
+                Err((current, new))
+            }
+        }
+    }
+
+    /// Replaces the value of an existing entry if it is equal to `expected`.
+    ///
+    /// Unlike a `compute` transition, the new value is written into the entry allocation once
+    /// and is never recomputed, meaning it does not have to be cloned across retries.
+    #[inline]
+    fn compare_exchange_with<'g>(
+        &self,
+        new_entry: &mut LazyEntry<K, V>,
+        expected: &V,
+        value: &mut Option<V>,
+        guard: &'g impl VerifiedGuard,
+    ) -> Result<&'g V, Option<&'g V>>
+    where
+        V: PartialEq,
+    {
+        // Load the root table.
+        let mut table = self.root(guard);
+
+        // The table has not been initialized yet.
+        if table.raw.is_null() {
+            return Err(None);
+        }
+
+        let (h1, h2) = self.hash(new_entry.key());
+
+        let mut help_copy = true;
+        loop {
+            // Initialize the probe state.
+            let mut probe = Probe::start(h1, table.mask);
+
+            // Probe until we reach the limit.
+            let copying = 'probe: loop {
+                if probe.len > table.limit {
+                    break None;
+                }
+
+                // Load the entry metadata first for cheap searches.
+                //
+                // Safety: `probe.i` is always in-bounds for the table length.
+                let meta = unsafe { table.meta(probe.i).load(Ordering::Acquire) };
+
+                // The key is not in the table.
+                // It also cannot be in the next table because we have not went over the probe limit.
+                if meta == meta::EMPTY {
+                    return Err(None);
+                }
+
+                // Check for a potential match.
+                if meta != h2 {
+                    probe.next(table.mask);
+                    continue 'probe;
+                }
+
+                // Load the full entry.
+                //
+                // Safety: `probe.i` is always in-bounds for the table length.
+                let mut entry = guard
+                    .protect(unsafe { table.entry(probe.i) }, Ordering::Acquire)
+                    .unpack();
+
+                // The entry was deleted, keep probing.
+                if entry.ptr.is_null() {
+                    probe.next(table.mask);
+                    continue 'probe;
+                }
+
+                // Check for a full match.
+                //
+                // Safety: We performed a protected load of the pointer using a verified guard with
+                // `Acquire` and ensured that it is non-null, meaning it is valid for reads as long
+                // as we hold the guard.
+                if !unsafe { self.hash_matches(entry.ptr, h1) }
+                    || unsafe { (*entry.ptr).key != *new_entry.key() }
+                {
+                    probe.next(table.mask);
+                    continue 'probe;
+                }
+
+                // The entry is being copied to the new table, we have to complete the copy before
+                // we can update it.
+                if entry.tag() & Entry::COPYING != 0 {
+                    break 'probe Some(probe.i);
+                }
+
+                loop {
+                    // Safety: `entry` is a valid, non-null, protected entry that we found in the map.
+                    let entry_ref = unsafe { &(*entry.ptr) };
+
+                    // Ensure that the current value is the expected one.
+                    if entry_ref.value != *expected {
+                        return Err(Some(&entry_ref.value));
+                    }
+
+                    let new_entry = new_entry.init(h1, self.extended());
+
+                    // Move the value into the entry allocation, if we have not already.
+                    if let Some(value) = value.take() {
+                        // Safety: `new_entry` was just allocated above and is valid for writes.
+                        unsafe { (*new_entry).value = MaybeUninit::new(value) }
+                    }
+
+                    // Try to perform the update.
+                    //
+                    // Safety:
+                    // - `probe.i` is always in-bounds for the table length
+                    // - `entry` is a valid non-null entry that we found in the map.
+                    // - `new_entry` was initialized above and never shared.
+                    let status = unsafe {
+                        self.update_at(probe.i, h1, entry, new_entry.cast(), table, guard)
+                    };
+
+                    match status {
+                        // Successfully updated.
+                        UpdateStatus::Replaced(_) => {
+                            // Safety: `new_entry` was initialized above.
+                            let new_ref = unsafe { &*new_entry.cast::<Entry<K, V>>() };
+                            return Ok(&new_ref.value);
+                        }
+
+                        // The entry is being copied to the new table, we have to complete the copy
+                        // before we can update it.
+                        UpdateStatus::Found(EntryStatus::Copied(_)) => break 'probe Some(probe.i),
 
-            _ => unreachable!(),
-        }
-    }
+                        // The entry was deleted.
+                        //
+                        // We know that at some point during our execution the key was not in the map.
+                        UpdateStatus::Found(EntryStatus::Null) => return Err(None),
 
-    /// Update an entry with a CAS function.
-    ///
-    /// Note that `compute` closure is guaranteed to be called for a `None` input only once, allowing the
-    /// insertion of values that cannot be cloned or reconstructed.
-    #[inline]
-    pub fn compute<'g, F, T>(
-        &self,
-        key: K,
-        compute: F,
-        guard: &'g impl VerifiedGuard,
-    ) -> Compute<'g, K, V, T>
-    where
-        F: FnMut(Option<(&'g K, &'g V)>) -> Operation<V, T>,
-    {
-        // Lazy initialize the entry allocation.
-        let mut entry = LazyEntry::Uninit(key);
+                        // Lost to a concurrent update, retry.
+                        UpdateStatus::Found(EntryStatus::Value(found)) => entry = found,
+                    }
+                }
+            };
 
-        // Perform the update.
-        //
-        // Safety: We just allocated the entry above.
-        let result = unsafe { self.compute_with(&mut entry, ComputeState::new(compute), guard) };
+            // Prepare to retry in the next table.
+            table = match self.prepare_retry(copying, &mut help_copy, table, guard) {
+                Some(table) => table,
 
-        // Deallocate the entry if it was not inserted.
-        if matches!(result, Compute::Removed(..) | Compute::Aborted(_)) {
-            if let LazyEntry::Init(entry) = entry {
-                // Safety: The entry was allocated but not inserted into the map.
-                let _ = unsafe { Box::from_raw(entry) };
+                // The search was exhausted.
+                None => return Err(None),
             }
         }
-
-        result
     }
 
     /// Update an entry with a CAS function.
@@ -1555,11 +3683,12 @@ where
     unsafe fn compute_with<'g, F, T>(
         &self,
         new_entry: &mut LazyEntry<K, V>,
+        (h1, h2): (usize, u8),
         mut state: ComputeState<F, K, V, T>,
         guard: &'g impl VerifiedGuard,
     ) -> Compute<'g, K, V, T>
     where
-        F: FnMut(Option<(&'g K, &'g V)>) -> Operation<V, T>,
+        F: FnMut(&K, Option<(&'g K, &'g V)>) -> Operation<V, T>,
     {
         // Load the root table.
         let mut table = self.root(guard);
@@ -1569,7 +3698,7 @@ where
             // Compute the value to insert.
             //
             // Safety: Insert transitions are always sound.
-            match unsafe { state.next(None) } {
+            match unsafe { state.next(new_entry.key(), None) } {
                 op @ Operation::Insert(_) => state.restore(None, op),
                 Operation::Remove => panic!("Cannot remove `None` entry."),
                 Operation::Abort(value) => return Compute::Aborted(value),
@@ -1579,7 +3708,6 @@ where
             table = self.init(None);
         }
 
-        let (h1, h2) = self.hash(new_entry.key());
         let mut help_copy = false;
 
         loop {
@@ -1602,13 +3730,13 @@ where
                     // Compute the value to insert.
                     //
                     // Safety: Insert transitions are always sound.
-                    let value = match unsafe { state.next(None) } {
+                    let value = match unsafe { state.next(new_entry.key(), None) } {
                         Operation::Insert(value) => value,
                         Operation::Remove => panic!("Cannot remove `None` entry."),
                         Operation::Abort(value) => return Compute::Aborted(value),
                     };
 
-                    let new_entry = new_entry.init();
+                    let new_entry = new_entry.init(h1, self.extended());
                     // Safety: `new_entry` was just allocated above and is valid for writes.
                     unsafe { (*new_entry).value = MaybeUninit::new(value) }
 
@@ -1616,7 +3744,8 @@ where
                     //
                     // Safety: `probe.i` is always in-bounds for the table length.Additionally,
                     // `new_entry` was allocated above and never shared.
-                    match unsafe { self.insert_at(probe.i, h2, new_entry.cast(), table, guard) } {
+                    match unsafe { self.insert_at(probe.i, h1, h2, new_entry.cast(), table, guard) }
+                    {
                         // Successfully inserted.
                         InsertStatus::Inserted => {
                             // Increment the table length.
@@ -1686,7 +3815,9 @@ where
                 // Safety: We performed a protected load of the pointer using a verified guard with
                 // `Acquire` and ensured that it is non-null, meaning it is valid for reads as long
                 // as we hold the guard.
-                if unsafe { (*entry.ptr).key != *new_entry.key() } {
+                if !unsafe { self.hash_matches(entry.ptr, h1) }
+                    || unsafe { (*entry.ptr).key != *new_entry.key() }
+                {
                     probe.next(table.mask);
                     continue 'probe;
                 }
@@ -1700,13 +3831,13 @@ where
                     // Compute the value to insert.
                     //
                     // Safety: `entry` is valid for reads.
-                    let failure = match unsafe { state.next(Some(entry.ptr)) } {
+                    let failure = match unsafe { state.next(new_entry.key(), Some(entry.ptr)) } {
                         // The operation was aborted.
                         Operation::Abort(value) => return Compute::Aborted(value),
 
                         // Update the value.
                         Operation::Insert(value) => {
-                            let new_entry = new_entry.init();
+                            let new_entry = new_entry.init(h1, self.extended());
 
                             // Safety: `new_entry` was just allocated above and is valid for writes.
                             unsafe { (*new_entry).value = MaybeUninit::new(value) }
@@ -1718,7 +3849,7 @@ where
                             // - `entry` is a valid non-null entry that we found in the map.
                             // - `new_entry` was initialized above and never shared.
                             let status = unsafe {
-                                self.update_at(probe.i, entry, new_entry.cast(), table, guard)
+                                self.update_at(probe.i, h1, entry, new_entry.cast(), table, guard)
                             };
 
                             match status {
@@ -1734,6 +3865,9 @@ where
                                     return Compute::Updated {
                                         old: (&entry_ref.key, &entry_ref.value),
                                         new: (&new_ref.key, &new_ref.value),
+                                        // Safety: Both entries were allocated by this map.
+                                        old_version: unsafe { self.version(entry_ref) },
+                                        new_version: unsafe { self.version(new_ref) },
                                     };
                                 }
 
@@ -1759,7 +3893,7 @@ where
                             // - `probe.i` is always in-bounds for the table length
                             // - `entry` is a valid non-null entry that we found in the map.
                             let status = unsafe {
-                                self.update_at(probe.i, entry, Entry::TOMBSTONE, table, guard)
+                                self.update_at(probe.i, h1, entry, Entry::TOMBSTONE, table, guard)
                             };
 
                             match status {
@@ -1779,6 +3913,9 @@ where
                                     // Decrement the table length.
                                     self.count.get(guard).fetch_sub(1, Ordering::Relaxed);
 
+                                    // Check if the table should be shrunk.
+                                    self.check_shrink(probe.i, table, guard);
+
                                     // Safety: `entry` is a valid non-null entry that we found in the map
                                     // before replacing it.
                                     let entry_ref = unsafe { &(*entry.ptr) };
@@ -1807,7 +3944,7 @@ where
                             // Compute the next operation.
                             //
                             // Safety: Insert transitions are always sound.
-                            match unsafe { state.next(None) } {
+                            match unsafe { state.next(new_entry.key(), None) } {
                                 Operation::Insert(value) => {
                                     // Save the computed value.
                                     state.restore(None, Operation::Insert(value));
@@ -1838,7 +3975,7 @@ where
             // Otherwise, the key is not in the map.
             //
             // Safety: Insert transitions are always sound.
-            match unsafe { state.next(None) } {
+            match unsafe { state.next(new_entry.key(), None) } {
                 // Need to insert into the new table.
                 op @ Operation::Insert(_) => {
                     table = self.prepare_retry_insert(None, &mut help_copy, table, guard);
@@ -1852,6 +3989,295 @@ where
     }
 }
 
+/// Exclusive access operations.
+///
+/// These operations bypass the reclamation scheme entirely, and so rely on the caller to guarantee
+/// that no guards are active for the map's collector. This is not implied by `&mut self` alone
+/// when the collector is shared.
+impl<K, V, S, C> HashMap<K, V, S, C>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+{
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// # Safety
+    ///
+    /// There must be no active guards for the collector of this map.
+    #[inline]
+    pub unsafe fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let table = self.root_mut();
+
+        // The table has not been initialized yet.
+        if table.raw.is_null() {
+            return None;
+        }
+
+        let (_, entry) = self.find_mut(key, table)?;
+
+        // The value may be modified through the returned reference.
+        //
+        // Safety: The entry is reachable from the root table, and the caller guarantees that
+        // we have unique access to it.
+        unsafe { self.stamp(entry) };
+        self.record_modification_mut(1);
+
+        // Safety: The entry is reachable from the root table, and the caller guarantees that
+        // we have unique access to it.
+        Some(unsafe { &mut (*entry).value })
+    }
+
+    /// Inserts a key-value pair into the table, returning the previous value if the key was
+    /// already present.
+    ///
+    /// # Safety
+    ///
+    /// There must be no active guards for the collector of this map.
+    #[inline]
+    pub unsafe fn insert_mut(&mut self, key: K, value: V) -> Option<V> {
+        let mut table = self.root_mut();
+
+        // Allocate the table if it has not been initialized yet.
+        if table.raw.is_null() {
+            table = self.init(None);
+        }
+
+        let (h1, h2) = self.hash(&key);
+        let mut probe = Probe::start(h1, table.mask);
+
+        // Probe until we reach the limit.
+        while probe.len <= table.limit {
+            // Safety: `probe.i` is always in-bounds for the table length.
+            let meta = unsafe { table.meta(probe.i) }.load(Ordering::Relaxed);
+
+            // Found an empty slot, and the key cannot be present further in the probe sequence.
+            if meta == meta::EMPTY {
+                let entry = Entry { key, value }.into_raw(h1, self.extended());
+
+                // Safety: `probe.i` is always in-bounds for the table length, and we just
+                // allocated the entry.
+                unsafe {
+                    self.stamp(entry);
+                    table.entry(probe.i).store(entry, Ordering::Relaxed);
+                    table.meta(probe.i).store(h2, Ordering::Relaxed);
+                }
+
+                *self.count.get_mut() += 1;
+                self.record_modification_mut(1);
+
+                if let Some(subscribers) = self.subscribers() {
+                    // Safety: We just allocated the entry and have unique access to it.
+                    let entry = unsafe { &*entry };
+                    subscribers.emit(RawChange::Inserted(&entry.key, &entry.value));
+                }
+
+                return None;
+            }
+
+            if meta == h2 {
+                // Safety: `probe.i` is always in-bounds for the table length.
+                let entry = unsafe { table.entry(probe.i) }
+                    .load(Ordering::Relaxed)
+                    .unpack();
+
+                // Safety: The entry is reachable from the root table, and the caller guarantees
+                // that we have unique access to it.
+                if !entry.ptr.is_null()
+                    && unsafe { self.hash_matches(entry.ptr, h1) }
+                    && unsafe { (*entry.ptr).key == key }
+                {
+                    self.record_modification_mut(1);
+
+                    // Replace the value in-place, there are no readers to observe the update.
+                    unsafe {
+                        self.stamp(entry.ptr);
+                        let old = std::mem::replace(&mut (*entry.ptr).value, value);
+
+                        if let Some(subscribers) = self.subscribers() {
+                            let entry = &*entry.ptr;
+                            subscribers.emit(RawChange::Updated(&entry.key, &old, &entry.value));
+                        }
+
+                        return Some(old);
+                    }
+                }
+            }
+
+            probe.next(table.mask);
+        }
+
+        // Went over the probe limit, we need to resize.
+        self.insert_mut_slow(key, value)
+    }
+
+    /// Inserts a key-value pair into the table after going over the probe limit.
+    #[cold]
+    #[inline(never)]
+    fn insert_mut_slow(&mut self, key: K, value: V) -> Option<V> {
+        // The resize machinery relies on a guard to retire the old table.
+        let guard = self.guard();
+
+        // We already verified that the key is not present in the table.
+        match self.insert(key, value, true, &guard) {
+            InsertResult::Inserted(_) => None,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Removes a key from the map, returning the owned entry if the key was previously
+    /// in the map.
+    ///
+    /// # Safety
+    ///
+    /// There must be no active guards for the collector of this map.
+    #[inline]
+    pub unsafe fn remove_mut<Q>(&mut self, key: &Q) -> Option<(K, V)>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let table = self.root_mut();
+
+        // The table has not been initialized yet.
+        if table.raw.is_null() {
+            return None;
+        }
+
+        let (i, entry) = self.find_mut(key, table)?;
+
+        // Safety: `find_mut` returns an in-bounds index.
+        unsafe {
+            table.entry(i).store(Entry::TOMBSTONE, Ordering::Relaxed);
+            table.meta(i).store(meta::TOMBSTONE, Ordering::Relaxed);
+        }
+
+        *self.count.get_mut() -= 1;
+        self.record_modification_mut(1);
+
+        // Safety: We removed the entry from the root table, and the caller guarantees that
+        // there are no active guards that may hold a reference to it. Additionally, entries
+        // are never reachable from previous tables once the root table has been promoted.
+        let entry = unsafe { Entry::from_raw(entry, self.extended()) };
+
+        if let Some(subscribers) = self.subscribers() {
+            subscribers.emit(RawChange::Removed(&entry.key, &entry.value));
+        }
+
+        Some((entry.key, entry.value))
+    }
+
+    /// Returns an iterator over the keys and mutable values of this table.
+    ///
+    /// # Safety
+    ///
+    /// There must be no active guards for the collector of this map.
+    #[inline]
+    pub unsafe fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut {
+            i: 0,
+            table: self.root_mut(),
+            clock: self.versioned.then_some(&self.clock),
+            modifications: &mut self.modifications,
+            _entries: PhantomData,
+        }
+    }
+
+    /// Returns an iterator that removes and yields all entries in the table.
+    ///
+    /// # Safety
+    ///
+    /// There must be no active guards for the collector of this map.
+    #[inline]
+    pub unsafe fn drain(&mut self) -> Drain<'_, K, V> {
+        let extended = self.extended();
+        let table = self.root_mut();
+        self.record_modification_mut(self.count.sum());
+
+        Drain {
+            i: 0,
+            table,
+            count: &mut self.count,
+            extended,
+            subscribers: self
+                .changes
+                .get()
+                .filter(|subscribers| subscribers.is_active()),
+        }
+    }
+
+    /// Returns the index and entry pointer for the given key in the root table.
+    #[inline]
+    fn find_mut<Q>(&self, key: &Q, table: Table<Entry<K, V>>) -> Option<(usize, *mut Entry<K, V>)>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let (h1, h2) = self.hash(key);
+        let mut probe = Probe::start(h1, table.mask);
+
+        // Probe until we reach the limit.
+        while probe.len <= table.limit {
+            // Safety: `probe.i` is always in-bounds for the table length.
+            let meta = unsafe { table.meta(probe.i) }.load(Ordering::Relaxed);
+
+            // The key is not in the table.
+            if meta == meta::EMPTY {
+                return None;
+            }
+
+            if meta == h2 {
+                // Safety: `probe.i` is always in-bounds for the table length.
+                let entry = unsafe { table.entry(probe.i) }
+                    .load(Ordering::Relaxed)
+                    .unpack();
+
+                // Safety: The entry is non-null and reachable from the root table.
+                if !entry.ptr.is_null()
+                    && unsafe { self.hash_matches(entry.ptr, h1) }
+                    && key.equivalent(unsafe { &(*entry.ptr).key })
+                {
+                    return Some((probe.i, entry.ptr));
+                }
+            }
+
+            probe.next(table.mask);
+        }
+
+        None
+    }
+
+    /// Returns the root table, completing any in-progress resizes.
+    ///
+    /// After this call all entries are uniquely owned by the root table.
+    #[inline]
+    fn root_mut(&mut self) -> Table<Entry<K, V>> {
+        // Safety: The root table is either null or a valid table allocation.
+        let table = unsafe { Table::from_raw(*self.table.get_mut()) };
+
+        if !table.raw.is_null() && table.next_table().is_some() {
+            return self.complete_resize(table);
+        }
+
+        table
+    }
+
+    /// Completes any in-progress resizes, returning the new root table.
+    #[cold]
+    #[inline(never)]
+    fn complete_resize(&self, mut table: Table<Entry<K, V>>) -> Table<Entry<K, V>> {
+        // The resize machinery relies on a guard to retire the old table.
+        let guard = self.guard();
+
+        while table.next_table().is_some() {
+            table = self.help_copy(true, &table, &guard);
+        }
+
+        table
+    }
+}
+
 /// Resize operations.
 impl<K, V, S, C> HashMap<K, V, S, C>
 where
@@ -1866,7 +4292,7 @@ where
         const CAPACITY: usize = 32;
 
         // Allocate the table and mark it as the root.
-        let mut new = Table::alloc(capacity.unwrap_or(CAPACITY));
+        let mut new = Table::alloc(capacity.unwrap_or(CAPACITY), self.track_access);
         *new.state_mut().status.get_mut() = State::PROMOTED;
 
         // Race to write the initial table.
@@ -1877,7 +4303,14 @@ where
             Ordering::Acquire,
         ) {
             // Successfully initialized the table.
-            Ok(_) => new,
+            Ok(_) => {
+                #[cfg(feature = "metrics")]
+                if let Some(name) = &self.metrics {
+                    utils::metrics::record(name, new.len(), self.len());
+                }
+
+                new
+            }
 
             // Someone beat us, deallocate our table and use the table that was written.
             Err(found) => {
@@ -1958,36 +4391,122 @@ where
             // Double the table capacity if we are at least 50% full.
             false if active_entries >= (current_capacity >> 1) => current_capacity << 1,
 
-            // Halve the table if we are at most 12.5% full.
-            //
-            // This heuristic is intentionally pessimistic as unnecessarily shrinking
-            // is an expensive operation, but it may change in the future. We also respect
-            // the initial capacity to give the user a way to retain a strict minimum table
-            // size.
-            false if active_entries <= (current_capacity >> 3) => {
-                self.initial_capacity.max(current_capacity >> 1)
+            // Halve the table if we are at most 12.5% full.
+            //
+            // This heuristic is intentionally pessimistic as unnecessarily shrinking
+            // is an expensive operation, but it may change in the future. We also respect
+            // the initial capacity to give the user a way to retain a strict minimum table
+            // size.
+            false if active_entries <= (current_capacity >> 3) => {
+                self.min_len().max(current_capacity >> 1)
+            }
+
+            // Otherwise keep the capacity the same.
+            //
+            // This can occur due to poor hash distribution or frequent cycling of
+            // insertions and deletions, in which case we want to avoid continuously
+            // growing the table.
+            false => current_capacity,
+        };
+
+        let next_capacity = capacity.unwrap_or(next_capacity);
+        assert!(
+            next_capacity <= isize::MAX as usize,
+            "`HashMap` exceeded maximum capacity"
+        );
+
+        // Allocate the new table while holding the lock.
+        utils::span!(
+            DEBUG,
+            "alloc_table",
+            capacity = current_capacity,
+            next_capacity
+        );
+        let next = Table::alloc(next_capacity, self.track_access);
+
+        if let Some(observer) = &self.observer {
+            let _ = next.state().started.set(Instant::now());
+            observer.resize_started(current_capacity, next_capacity);
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(name) = &self.metrics {
+            utils::metrics::resize_started(name, active_entries);
+        }
+
+        state.next.store(next.raw, Ordering::Release);
+        drop(_allocating);
+
+        next
+    }
+
+    /// Check whether the table should be shrunk after a removal at the given index.
+    ///
+    /// Loading the length of the table is relatively expensive, so this check is only
+    /// performed for a fraction of removals.
+    #[inline]
+    fn check_shrink(&self, i: usize, table: Table<Entry<K, V>>, guard: &impl VerifiedGuard) {
+        // The probe index is derived from the hash, so it serves as a cheap source of randomness.
+        const SHRINK_SAMPLE: usize = 64;
+
+        if i & (SHRINK_SAMPLE - 1) == 0 {
+            self.try_shrink(table, guard);
+        }
+    }
+
+    /// Initiate a resize to shrink the table if it's occupancy has fallen below the threshold.
+    ///
+    /// If a resize is already in-progress, this helps with the copy instead.
+    #[cold]
+    #[inline(never)]
+    fn try_shrink(&self, table: Table<Entry<K, V>>, guard: &impl VerifiedGuard) {
+        // Avoid shrinking in stress tests, similar to growing.
+        if cfg!(papaya_stress) {
+            return;
+        }
+
+        let root = self.root(guard);
+
+        if root.next_table().is_none() {
+            // Only shrink the root table.
+            if table.raw != root.raw {
+                return;
+            }
+
+            let len = self.len();
+
+            // We loaded the length anyways, so take the opportunity to record it.
+            #[cfg(feature = "metrics")]
+            if let Some(name) = &self.metrics {
+                utils::metrics::record_len(name, len);
+            }
+
+            // Shrink the table if we are at most 12.5% full, matching the heuristic in
+            // `get_or_alloc_next`.
+            if root.len() <= self.min_len() || len > (root.len() >> 3) {
+                return;
             }
 
-            // Otherwise keep the capacity the same.
-            //
-            // This can occur due to poor hash distribution or frequent cycling of
-            // insertions and deletions, in which case we want to avoid continuously
-            // growing the table.
-            false => current_capacity,
-        };
+            // Leave enough room in the new table to avoid immediately growing again.
+            let capacity = probe::entries_for(len.saturating_mul(2)).max(self.min_len());
 
-        let next_capacity = capacity.unwrap_or(next_capacity);
-        assert!(
-            next_capacity <= isize::MAX as usize,
-            "`HashMap` exceeded maximum capacity"
-        );
+            // Race to allocate the new table.
+            self.get_or_alloc_next(Some(capacity), root);
+        }
 
-        // Allocate the new table while holding the lock.
-        let next = Table::alloc(next_capacity);
-        state.next.store(next.raw, Ordering::Release);
-        drop(_allocating);
+        match self.resize {
+            // In blocking mode we must complete the resize before proceeding.
+            ResizeMode::Blocking => {
+                self.help_copy(true, &root, guard);
+            }
 
-        next
+            // In incremental mode we help out with a single chunk. The copy is continued by
+            // later removals, by writers that run into the resize, or by operations that
+            // require a linearized table.
+            ResizeMode::Incremental(_) => {
+                self.help_copy(false, &root, guard);
+            }
+        }
     }
 
     /// Help along with an existing resize operation, returning the new root table.
@@ -2050,6 +4569,8 @@ where
 
                 // Claim a chunk to copy.
                 let copy_start = next.state().claim.fetch_add(copy_chunk, Ordering::Relaxed);
+                let start = self.observe_start();
+                utils::span!(TRACE, "copy_chunk", start = copy_start, len = copy_chunk);
 
                 // Copy our chunk of entries.
                 let mut copied = 0;
@@ -2072,6 +4593,15 @@ where
                         // to threads that are unparked.
                         next.state().status.store(State::ABORTED, Ordering::SeqCst);
 
+                        if let Some(observer) = &self.observer {
+                            observer.resize_aborted(next.len());
+                        }
+
+                        #[cfg(feature = "metrics")]
+                        if let Some(name) = &self.metrics {
+                            utils::metrics::resize_aborted(name);
+                        }
+
                         // Allocate the next table.
                         let allocated = self.get_or_alloc_next(None, next);
 
@@ -2087,6 +4617,10 @@ where
                     copied += 1;
                 }
 
+                if let (Some(observer), Some(start)) = (&self.observer, start) {
+                    observer.copied(copied, start.elapsed());
+                }
+
                 // Are we done?
                 if self.try_promote(table, &next, copied, guard) {
                     return next;
@@ -2137,9 +4671,9 @@ where
                 }
 
                 // Park until the table is promoted.
-                state
-                    .parker
-                    .park(&state.status, |status| status == State::PENDING);
+                self.park(&state.parker, &state.status, |status| {
+                    status == State::PENDING
+                });
             }
         }
     }
@@ -2190,8 +4724,34 @@ where
         // away without a protected load. Additionally, we verified that the
         // entry is non-null, meaning that it is valid for reads.
         unsafe {
-            self.insert_copy(entry.ptr.unpack(), false, next_table, guard)
-                .is_some()
+            match self.insert_copy(entry.ptr.unpack(), false, next_table, guard) {
+                Some((next_table, j)) => {
+                    self.copy_access(table, i, &next_table, j);
+                    true
+                }
+                None => false,
+            }
+        }
+    }
+
+    /// Carries over the access bit of an entry that was copied to the next table, if access
+    /// tracking is enabled.
+    ///
+    /// # Safety
+    ///
+    /// The indices must be in-bounds for their respective tables.
+    #[inline]
+    unsafe fn copy_access(
+        &self,
+        table: &Table<Entry<K, V>>,
+        i: usize,
+        next_table: &Table<Entry<K, V>>,
+        j: usize,
+    ) {
+        // Safety: Guaranteed by caller, and all tables are allocated with access bits if
+        // tracking is enabled.
+        if self.track_access && unsafe { table.access(i) }.load(Ordering::Relaxed) != 0 {
+            unsafe { next_table.touch(j) };
         }
     }
 
@@ -2227,6 +4787,8 @@ where
 
                 // Claim a chunk to copy.
                 let copy_start = next.state().claim.fetch_add(chunk, Ordering::Relaxed);
+                let start = self.observe_start();
+                utils::span!(TRACE, "copy_chunk", start = copy_start, len = chunk);
 
                 // Copy our chunk of entries.
                 let mut copied = 0;
@@ -2244,6 +4806,10 @@ where
                     copied += 1;
                 }
 
+                if let (Some(observer), Some(start)) = (&self.observer, start) {
+                    observer.copied(copied, start.elapsed());
+                }
+
                 // Update the copy state, and try to promote the table.
                 //
                 // Only copy a single chunk if promotion fails, unless we are forced
@@ -2291,9 +4857,9 @@ where
                 }
 
                 // Park until the table is promoted.
-                state
-                    .parker
-                    .park(&state.status, |status| status == State::PENDING);
+                self.park(&state.parker, &state.status, |status| {
+                    status == State::PENDING
+                });
             }
         }
     }
@@ -2340,8 +4906,10 @@ where
         // away without a protected load. Additionally, we verified that the
         // entry is non-null, meaning that it is valid for reads.
         unsafe {
-            self.insert_copy(new_entry, true, next_table, guard)
+            let (next_table, j) = self
+                .insert_copy(new_entry, true, next_table, guard)
                 .unwrap();
+            self.copy_access(table, i, &next_table, j);
         }
 
         // Mark the entry as copied.
@@ -2375,11 +4943,10 @@ where
         table: &Table<Entry<K, V>>,
         guard: &impl VerifiedGuard,
     ) -> Option<(Table<Entry<K, V>>, usize)> {
-        // Safety: The new entry is guaranteed to be valid for reads.
-        let key = unsafe { &(*new_entry.ptr).key };
-
         let mut table = *table;
-        let (h1, h2) = self.hash(key);
+
+        // Safety: The new entry is guaranteed to be valid for reads.
+        let (h1, h2) = unsafe { self.entry_hash(new_entry.ptr) };
 
         loop {
             // Initialize the probe state.
@@ -2419,14 +4986,13 @@ where
                             let meta = if found.ptr.is_null() {
                                 meta::TOMBSTONE
                             } else {
+                                // Ensure the meta table is updated to avoid breaking the probe chain.
+                                //
                                 // Safety: We performed a protected load of the pointer using a verified guard with
                                 // `Acquire` and ensured that it is non-null, meaning it is valid for reads as long
                                 // as we hold the guard.
-                                let found_ref = unsafe { &(*found.ptr) };
-
-                                // Ensure the meta table is updated to avoid breaking the probe chain.
-                                let hash = self.hasher.hash_one(&found_ref.key);
-                                meta::h2(hash)
+                                let (_, h2) = unsafe { self.entry_hash(found.ptr) };
+                                h2
                             };
 
                             if meta_entry.load(Ordering::Relaxed) == meta::EMPTY {
@@ -2488,18 +5054,36 @@ where
                     // Note that the `SeqCst` is necessary to make the store visible to threads
                     // that are unparked.
                     state.status.store(State::PROMOTED, Ordering::SeqCst);
+                    self.promotions.fetch_add(1, Ordering::Relaxed);
+
+                    if let Some(observer) = &self.observer {
+                        let elapsed = state.started.get().map(Instant::elapsed);
+                        observer.promoted(next.len(), elapsed.unwrap_or_default());
+                    }
+
+                    #[cfg(feature = "metrics")]
+                    if let Some(name) = &self.metrics {
+                        utils::metrics::promoted(name, next.len(), self.len());
+                    }
 
                     // Retire the old table.
                     //
                     // Safety: `table.raw` is a valid pointer to the table we just copied from.
                     // Additionally, the CAS above made the previous table unreachable from the
                     // root pointer, allowing it to be safely retired.
+                    //
+                    // Note that we do not drop entries because they have been copied to
+                    // the new root.
                     unsafe {
-                        guard.defer_retire(table.raw, |table, collector| {
-                            // Note that we do not drop entries because they have been copied to
-                            // the new root.
-                            drop_table(Table::from_raw(table), collector);
-                        });
+                        if self.extended() {
+                            guard.defer_retire(table.raw, |table, collector| {
+                                drop_table(Table::from_raw(table), collector, true);
+                            });
+                        } else {
+                            guard.defer_retire(table.raw, |table, collector| {
+                                drop_table(Table::from_raw(table), collector, false);
+                            });
+                        }
                     }
                 }
 
@@ -2584,14 +5168,14 @@ where
             // Safety: In blocking resize mode, we only ever write to the root table, so the entry
             // is inaccessible from all tables.
             ResizeMode::Blocking => unsafe {
-                guard.defer_retire(entry.ptr, seize::reclaim::boxed);
+                guard.defer_retire(entry.ptr, Entry::reclaim(self.extended()));
             },
             // In incremental resize mode, the entry may be accessible in previous tables.
             ResizeMode::Incremental(_) => {
                 if entry.tag() & Entry::BORROWED == 0 {
                     // Safety: If the entry is not borrowed, meaning it is not in any previous tables,
                     // it is inaccessible even if the current table is not root. Thus we can safely retire.
-                    unsafe { guard.defer_retire(entry.ptr, seize::reclaim::boxed) };
+                    unsafe { guard.defer_retire(entry.ptr, Entry::reclaim(self.extended())) };
                     return;
                 }
 
@@ -2603,7 +5187,7 @@ where
                     if table.raw == root.raw {
                         // Safety: The root table is our table or a table that succeeds ours.
                         // Thus any previous tables are unreachable from the root, so we can safely retire.
-                        unsafe { guard.defer_retire(entry.ptr, seize::reclaim::boxed) };
+                        unsafe { guard.defer_retire(entry.ptr, Entry::reclaim(self.extended())) };
                         return;
                     }
 
@@ -2634,10 +5218,35 @@ where
 // An iterator over the keys and values of this table.
 pub struct Iter<'g, K, V, G> {
     i: usize,
+    end: usize,
     table: Table<Entry<K, V>>,
     guard: &'g G,
 }
 
+#[cfg(feature = "rayon")]
+impl<K, V, G> Iter<'_, K, V, G> {
+    // Returns the number of table slots left to scan.
+    #[inline]
+    pub fn slots(&self) -> usize {
+        self.end - self.i
+    }
+
+    // Splits the remaining slots of this iterator in half.
+    #[inline]
+    pub fn split(self) -> (Self, Option<Self>) {
+        if self.slots() < 2 {
+            return (self, None);
+        }
+
+        let mid = self.i + self.slots() / 2;
+        let right = Iter {
+            i: mid,
+            ..self.clone()
+        };
+        (Iter { end: mid, ..self }, Some(right))
+    }
+}
+
 impl<'g, K: 'g, V: 'g, G> Iterator for Iter<'g, K, V, G>
 where
     G: VerifiedGuard,
@@ -2646,14 +5255,22 @@ where
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        // The table has not yet been allocated.
-        if self.table.raw.is_null() {
-            return None;
-        }
+        self.next_entry().map(|entry| (&entry.key, &entry.value))
+    }
+}
 
+impl<'g, K: 'g, V: 'g, G> Iter<'g, K, V, G>
+where
+    G: VerifiedGuard,
+{
+    // Returns the next entry allocation in the table.
+    #[inline]
+    pub fn next_entry(&mut self) -> Option<&'g Entry<K, V>> {
         loop {
-            // Iterated over every entry in the table, we're done.
-            if self.i >= self.table.len() {
+            // Iterated over every entry in our range of the table, we're done.
+            //
+            // Note that the range is empty if the table has not yet been allocated.
+            if self.i >= self.end {
                 return None;
             }
 
@@ -2688,7 +5305,7 @@ where
             let entry_ref = unsafe { &(*entry.ptr) };
 
             self.i += 1;
-            return Some((&entry_ref.key, &entry_ref.value));
+            return Some(entry_ref);
         }
     }
 }
@@ -2721,12 +5338,336 @@ impl<K, V, G> Clone for Iter<'_, K, V, G> {
     fn clone(&self) -> Self {
         Iter {
             i: self.i,
+            end: self.end,
             table: self.table,
             guard: self.guard,
         }
     }
 }
 
+// An iterator over the keys and mutable values of this table.
+pub struct IterMut<'a, K, V> {
+    i: usize,
+    table: Table<Entry<K, V>>,
+    clock: Option<&'a AtomicU64>,
+    modifications: &'a mut Counter,
+    _entries: PhantomData<&'a mut Entry<K, V>>,
+}
+
+impl<'a, K, V> IterMut<'a, K, V> {
+    // Returns an iterator over the remaining entries, by reference.
+    #[inline]
+    pub fn remaining(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        // Safety: The table was linearized and we have unique access to its entries.
+        unsafe { entries(self.table, self.i) }.map(|entry| {
+            // Safety: The entry is valid for as long as we hold a reference to the map.
+            let entry = unsafe { &*entry };
+            (&entry.key, &entry.value)
+        })
+    }
+}
+
+impl<'a, K: 'a, V: 'a> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // Safety: The table was linearized and we have unique access to its entries.
+        let (i, entry) = unsafe { next_entry(self.table, self.i) }?;
+        self.i = i + 1;
+
+        // The value may be modified through the returned reference.
+        //
+        // Note that only versioned maps count modifications.
+        if let Some(clock) = self.clock {
+            *self.modifications.get_mut() += 1;
+            let version = clock.fetch_add(1, Ordering::Relaxed) + 1;
+
+            // Safety: Entries in a versioned table are always extended entries, and we have
+            // unique access to the entry.
+            unsafe { (*entry.cast::<ExtendedEntry<K, V>>()).version = version };
+        }
+
+        // Safety: The entry is valid for as long as we hold a mutable reference to the map,
+        // and each entry is yielded at most once.
+        let entry = unsafe { &mut *entry };
+        Some((&entry.key, &mut entry.value))
+    }
+}
+
+// Safety: A mutable iterator holds a unique reference to the HashMap and outputs
+// shared references to keys and mutable references to values.
+unsafe impl<K, V> Send for IterMut<'_, K, V>
+where
+    K: Sync,
+    V: Send,
+{
+}
+
+unsafe impl<K, V> Sync for IterMut<'_, K, V>
+where
+    K: Sync,
+    V: Sync,
+{
+}
+
+// An iterator that removes and yields all entries in this table.
+pub struct Drain<'a, K, V> {
+    i: usize,
+    table: Table<Entry<K, V>>,
+    count: &'a mut Counter,
+    extended: bool,
+    subscribers: Option<&'a Subscribers<K, V>>,
+}
+
+impl<K, V> Drain<'_, K, V> {
+    // Returns an iterator over the remaining entries, by reference.
+    #[inline]
+    pub fn remaining(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        // Safety: The table was linearized and we have unique access to its entries.
+        unsafe { entries(self.table, self.i) }.map(|entry| {
+            // Safety: Entries are valid until they are yielded by the iterator.
+            let entry = unsafe { &*entry };
+            (&entry.key, &entry.value)
+        })
+    }
+}
+
+impl<K, V> Iterator for Drain<'_, K, V> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // Safety: The table was linearized and we have unique access to its entries.
+        let (i, entry) = unsafe { next_entry(self.table, self.i) }?;
+        self.i = i + 1;
+
+        // Remove the entry from the table, in case the iterator is leaked.
+        //
+        // Safety: `next_entry` returns an in-bounds index.
+        unsafe {
+            self.table
+                .entry(i)
+                .store(Entry::TOMBSTONE, Ordering::Relaxed);
+            self.table.meta(i).store(meta::TOMBSTONE, Ordering::Relaxed);
+        }
+
+        *self.count.get_mut() -= 1;
+
+        // Safety: We removed the entry from the root table and have unique access to it.
+        let entry = unsafe { Entry::from_raw(entry, self.extended) };
+
+        if let Some(subscribers) = self.subscribers {
+            subscribers.emit(RawChange::Removed(&entry.key, &entry.value));
+        }
+
+        Some((entry.key, entry.value))
+    }
+}
+
+impl<K, V> Drop for Drain<'_, K, V> {
+    fn drop(&mut self) {
+        // Drop any remaining entries.
+        self.for_each(drop);
+
+        // The table has not been initialized.
+        if self.table.raw.is_null() {
+            return;
+        }
+
+        // Clear out the tombstones, the table is now empty.
+        for i in 0..self.table.len() {
+            // Safety: `i` is in-bounds and we have unique access to the table.
+            unsafe {
+                self.table
+                    .entry(i)
+                    .store(ptr::null_mut(), Ordering::Relaxed);
+                self.table.meta(i).store(meta::EMPTY, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+// Safety: A draining iterator holds a unique reference to the HashMap
+// and outputs owned keys and values.
+unsafe impl<K, V> Send for Drain<'_, K, V>
+where
+    K: Send,
+    V: Send,
+{
+}
+
+unsafe impl<K, V> Sync for Drain<'_, K, V>
+where
+    K: Sync,
+    V: Sync,
+{
+}
+
+// Returns the next present entry in the table, starting from index `i`.
+//
+// # Safety
+//
+// The table must be linearized and the caller must have unique access to its entries.
+#[inline]
+unsafe fn next_entry<K, V>(
+    table: Table<Entry<K, V>>,
+    mut i: usize,
+) -> Option<(usize, *mut Entry<K, V>)> {
+    // The table has not yet been allocated.
+    if table.raw.is_null() {
+        return None;
+    }
+
+    while i < table.len() {
+        // Safety: We verified that `i` is in-bounds above.
+        let entry = unsafe { table.entry(i) }.load(Ordering::Relaxed).unpack();
+
+        // The entry is empty or deleted.
+        if !entry.ptr.is_null() {
+            return Some((i, entry.ptr));
+        }
+
+        i += 1;
+    }
+
+    None
+}
+
+// Returns an iterator over the present entries in the table, starting from index `i`.
+//
+// # Safety
+//
+// The table must be linearized and the caller must have unique access to its entries.
+#[inline]
+unsafe fn entries<K, V>(
+    table: Table<Entry<K, V>>,
+    mut i: usize,
+) -> impl Iterator<Item = *mut Entry<K, V>> {
+    std::iter::from_fn(move || {
+        // Safety: Guaranteed by caller.
+        let (next, entry) = unsafe { next_entry(table, i) }?;
+        i = next + 1;
+        Some(entry)
+    })
+}
+
+// An owning iterator over the entries of a table.
+pub struct IntoIter<K, V> {
+    i: usize,
+    remaining: usize,
+    table: Table<Entry<K, V>>,
+    extended: bool,
+}
+
+impl<K, V> IntoIter<K, V> {
+    // Returns an iterator over the remaining entries, by reference.
+    #[inline]
+    pub fn remaining(&self) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let (mut table, mut i) = (self.table, self.i);
+
+        std::iter::from_fn(move || loop {
+            // Reached the end of the table chain.
+            if table.raw.is_null() {
+                return None;
+            }
+
+            // Continue in the next table.
+            if i >= table.len() {
+                // Safety: The next table is either null or a valid table allocation.
+                table = unsafe { Table::from_raw(table.state().next.load(Ordering::Relaxed)) };
+                i = 0;
+                continue;
+            }
+
+            // Safety: We verified that `i` is in-bounds above.
+            let entry = unsafe { table.entry(i) }.load(Ordering::Relaxed).unpack();
+            i += 1;
+
+            // The entry is empty, deleted, or was copied to the next table.
+            if entry.ptr.is_null() || entry.tag() & Entry::COPYING != 0 {
+                continue;
+            }
+
+            // Safety: We own the table, and the entry has not been moved out yet.
+            let entry = unsafe { &*entry.ptr };
+            return Some((&entry.key, &entry.value));
+        })
+    }
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Every table has been consumed.
+            if self.table.raw.is_null() {
+                return None;
+            }
+
+            // Continue in the next table.
+            if self.i >= self.table.len() {
+                let next = *self.table.state_mut().next.get_mut();
+
+                // Safety: We own the table and moved out all of its entries.
+                unsafe { dealloc_table(self.table, self.extended) };
+
+                // Safety: The next table is either null or a valid table allocation.
+                self.table = unsafe { Table::from_raw(next) };
+                self.i = 0;
+                continue;
+            }
+
+            // Safety: `self.i` is in-bounds and we have unique access to the table.
+            let entry = unsafe { (*self.table.entry(self.i).as_ptr()).unpack() };
+            self.i += 1;
+
+            // The entry is empty, deleted, or was copied to the next table, in which case
+            // it will be yielded from there.
+            if entry.ptr.is_null() || entry.tag() & Entry::COPYING != 0 {
+                continue;
+            }
+
+            self.remaining = self.remaining.saturating_sub(1);
+
+            // Safety: We own the table, and skipped any entries that were copied to the next
+            // table, so every entry is only reachable from a single table. Additionally, the
+            // table is not accessed at this index again.
+            let entry = unsafe { Entry::from_raw(entry.ptr, self.extended) };
+            return Some((entry.key, entry.value));
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> Drop for IntoIter<K, V> {
+    fn drop(&mut self) {
+        // Drop any remaining entries, deallocating the tables as we go.
+        self.for_each(drop);
+    }
+}
+
+// Safety: An owning iterator owns its keys and values.
+unsafe impl<K, V> Send for IntoIter<K, V>
+where
+    K: Send,
+    V: Send,
+{
+}
+
+unsafe impl<K, V> Sync for IntoIter<K, V>
+where
+    K: Sync,
+    V: Sync,
+{
+}
+
 impl<K, V, S, C> Drop for HashMap<K, V, S, C>
 where
     C: Borrow<Collector>,
@@ -2742,6 +5683,11 @@ where
         // Safety: We have a unique reference to the collector.
         unsafe { self.collector.borrow().reclaim_all() };
 
+        #[cfg(feature = "metrics")]
+        if let Some(name) = &self.metrics {
+            utils::metrics::dropped(name);
+        }
+
         // Drop all nested tables and entries.
         while !raw.is_null() {
             // Safety: The root and next tables are always valid pointers to a
@@ -2753,11 +5699,11 @@ where
 
             // Safety: We have unique access to the table and do
             // not access the entries after this call.
-            unsafe { drop_entries(table) };
+            unsafe { drop_entries(table, self.extended()) };
 
             // Safety: We have unique access to the table and do
             // not access it after this call.
-            unsafe { drop_table(table, &self.collector) };
+            unsafe { drop_table(table, &self.collector, self.extended()) };
 
             // Continue for all nested tables.
             raw = next;
@@ -2770,7 +5716,7 @@ where
 // # Safety
 //
 // The table entries must not be accessed after this call.
-unsafe fn drop_entries<K, V>(table: Table<Entry<K, V>>) {
+unsafe fn drop_entries<K, V>(table: Table<Entry<K, V>>, extended: bool) {
     for i in 0..table.len() {
         // Safety: `i` is in-bounds and we have unique access to the table.
         let entry = unsafe { (*table.entry(i).as_ptr()).unpack() };
@@ -2786,7 +5732,7 @@ unsafe fn drop_entries<K, V>(table: Table<Entry<K, V>>) {
         // not be accessed after this call. Additionally, we ensured
         // that the entry is not copied to avoid double freeing entries
         // that may exist in multiple tables.
-        unsafe { drop(Box::from_raw(entry.ptr)) }
+        unsafe { drop(Entry::from_raw(entry.ptr, extended)) }
     }
 }
 
@@ -2795,7 +5741,11 @@ unsafe fn drop_entries<K, V>(table: Table<Entry<K, V>>) {
 // # Safety
 //
 // The table must not be accessed after this call.
-unsafe fn drop_table<K, V, C: Borrow<Collector>>(mut table: Table<Entry<K, V>>, collector: &C) {
+unsafe fn drop_table<K, V, C: Borrow<Collector>>(
+    mut table: Table<Entry<K, V>>,
+    collector: &C,
+    extended: bool,
+) {
     // Drop any entries that were deferred during an incremental resize.
     //
     // Safety: Entries are deferred after they are made unreachable from the
@@ -2808,7 +5758,7 @@ unsafe fn drop_table<K, V, C: Borrow<Collector>>(mut table: Table<Entry<K, V>>,
     table
         .state_mut()
         .deferred
-        .drain(|entry| unsafe { collector.borrow().retire(entry, seize::reclaim::boxed) });
+        .drain(|entry| unsafe { collector.borrow().retire(entry, Entry::reclaim(extended)) });
 
     // Deallocate the table.
     //
@@ -2816,6 +5766,24 @@ unsafe fn drop_table<K, V, C: Borrow<Collector>>(mut table: Table<Entry<K, V>>,
     unsafe { Table::dealloc(table) };
 }
 
+// Drop any deferred entries and deallocate the table, without going through the collector.
+//
+// # Safety
+//
+// The table must not be accessed after this call, and there must be no active guards that
+// may hold references to entries in the table.
+unsafe fn dealloc_table<K, V>(mut table: Table<Entry<K, V>>, extended: bool) {
+    // Safety: Deferred entries have been removed from the map, and the caller guarantees
+    // there are no active guards.
+    table
+        .state_mut()
+        .deferred
+        .drain(|entry| unsafe { drop(Entry::from_raw(entry, extended)) });
+
+    // Safety: The caller guarantees that the table will not be accessed after this call.
+    unsafe { Table::dealloc(table) };
+}
+
 // Entry metadata, inspired by `hashbrown`.
 mod meta {
     use std::mem;
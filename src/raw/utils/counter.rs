@@ -46,6 +46,12 @@ impl Counter {
         &self.0[shard].value
     }
 
+    // Returns a mutable reference to a counter shard, given exclusive access.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut isize {
+        self.0[0].value.get_mut()
+    }
+
     // Returns the sum of all counter shards.
     #[inline]
     pub fn sum(&self) -> usize {
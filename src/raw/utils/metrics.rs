@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+// Metrics exported through the `metrics` crate.
+//
+// Handles are resolved on every event rather than when the map is built, so
+// metrics are recorded even if the recorder is installed after the map is
+// created. Every event is on a slow path, so this cost is negligible.
+
+/// The label identifying the map that recorded a metric.
+const LABEL: &str = "map";
+
+/// The number of slots in the root table.
+const CAPACITY: &str = "papaya_capacity";
+
+/// The number of entries in the map.
+const LEN: &str = "papaya_len";
+
+/// The number of resizes in progress.
+const RESIZES: &str = "papaya_resizes_in_progress";
+
+/// The number of times a table has been promoted to the root.
+const PROMOTIONS: &str = "papaya_promotions";
+
+/// Records the capacity and length of the map.
+pub fn record(name: &Arc<str>, capacity: usize, len: usize) {
+    ::metrics::gauge!(CAPACITY, LABEL => name.clone()).set(capacity as f64);
+    ::metrics::gauge!(LEN, LABEL => name.clone()).set(len as f64);
+}
+
+/// Records the length of the map.
+pub fn record_len(name: &Arc<str>, len: usize) {
+    ::metrics::gauge!(LEN, LABEL => name.clone()).set(len as f64);
+}
+
+/// Records that a resize was started while the map contained `len` entries.
+pub fn resize_started(name: &Arc<str>, len: usize) {
+    ::metrics::gauge!(RESIZES, LABEL => name.clone()).increment(1.0);
+    record_len(name, len);
+}
+
+/// Records that a resize was aborted.
+pub fn resize_aborted(name: &Arc<str>) {
+    ::metrics::gauge!(RESIZES, LABEL => name.clone()).decrement(1.0);
+}
+
+/// Records that a table with `capacity` slots was promoted to the root.
+pub fn promoted(name: &Arc<str>, capacity: usize, len: usize) {
+    ::metrics::gauge!(RESIZES, LABEL => name.clone()).decrement(1.0);
+    ::metrics::counter!(PROMOTIONS, LABEL => name.clone()).increment(1);
+    record(name, capacity, len);
+}
+
+/// Records that the map was dropped, resetting its gauges.
+pub fn dropped(name: &Arc<str>) {
+    ::metrics::gauge!(RESIZES, LABEL => name.clone()).set(0.0);
+    record(name, 0, 0);
+}
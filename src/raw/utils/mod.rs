@@ -1,12 +1,28 @@
 mod counter;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 mod parker;
 mod stack;
 mod tagged;
+mod waiters;
 
 pub use counter::Counter;
-pub use parker::Parker;
+pub use parker::{Atomic, Parker};
 pub use stack::Stack;
 pub use tagged::{untagged, AtomicPtrFetchOps, StrictProvenance, Tagged, Unpack};
+pub use waiters::{Waiter, Waiters};
+
+/// Enters a `tracing` span at the given level until the end of the current scope.
+///
+/// Expands to nothing if the `tracing` feature is disabled.
+macro_rules! span {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $($arg)*).entered();
+    };
+}
+
+pub(crate) use span;
 
 /// A `seize::Guard` that has been verified to belong to a given map.
 pub trait VerifiedGuard: seize::Guard {}
@@ -147,3 +147,9 @@ impl Atomic<u8> for AtomicU8 {
         self.load(ordering)
     }
 }
+
+impl Atomic<usize> for AtomicUsize {
+    fn load(&self, ordering: Ordering) -> usize {
+        self.load(ordering)
+    }
+}
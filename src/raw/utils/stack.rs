@@ -1,5 +1,5 @@
 use std::ptr;
-use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
 
 /// A simple lock-free, append-only, stack of pointers.
 ///
@@ -11,6 +11,7 @@ use std::sync::atomic::{AtomicPtr, Ordering};
 /// it's not clear whether this is better than an allocation or even lock.
 pub struct Stack<T> {
     head: AtomicPtr<Node<T>>,
+    len: AtomicUsize,
 }
 
 /// A node in the stack.
@@ -24,6 +25,7 @@ impl<T> Stack<T> {
     pub fn new() -> Self {
         Self {
             head: AtomicPtr::new(ptr::null_mut()),
+            len: AtomicUsize::new(0),
         }
     }
 
@@ -54,11 +56,21 @@ impl<T> Stack<T> {
                 break;
             }
         }
+
+        self.len.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the number of elements in the stack.
+    ///
+    /// Note that the length may be stale if elements are pushed concurrently.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
     }
 
     /// Drain all elements from the stack.
     pub fn drain(&mut self, mut f: impl FnMut(T)) {
-        let mut head = *self.head.get_mut();
+        let mut head = std::mem::replace(self.head.get_mut(), ptr::null_mut());
+        *self.len.get_mut() = 0;
 
         while !head.is_null() {
             // Safety: We have `&mut self` and the node is non-null.
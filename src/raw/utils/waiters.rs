@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::sync::atomic::{self, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+use std::task::Waker;
+
+// The number of shards that waiters are distributed across.
+const SHARDS: usize = 64;
+
+// A set of asynchronous tasks waiting for keys to be written.
+//
+// Similar to `Parker`, waiters are keyed by an arbitrary value, in this case the hash of the key
+// they are waiting on. Waiters are sharded by hash to avoid contention between tasks waiting
+// on different keys, and the shards are lazily allocated so that maps without any waiters do not
+// pay for them.
+#[derive(Default)]
+pub struct Waiters {
+    pending: AtomicUsize,
+    next_id: AtomicU64,
+    shards: OnceLock<Box<[Mutex<Shard>]>>,
+}
+
+// The waiters in a given shard, keyed by their unique ID.
+type Shard = HashMap<u64, (u64, Waker)>;
+
+impl Waiters {
+    // Returns a waiter for the key with the given hash.
+    //
+    // The waiter is not registered until `Waiter::register` is called.
+    pub fn waiter(&self, hash: u64) -> Waiter<'_> {
+        Waiter {
+            waiters: self,
+            hash,
+            id: None,
+        }
+    }
+
+    // Wake all tasks waiting on the key with the given primary hash.
+    //
+    // This must be called after the write that satisfies the wait condition. Writes to the map
+    // are a release read-modify-write, and waiting tasks pay for the ordering instead, with a
+    // `SeqCst` fence between their registration and checking the wait condition. As a result,
+    // the only cost paid by maps without waiters is the load below, which is a plain load on x86.
+    #[inline]
+    pub fn wake(&self, hash: usize) {
+        // Fast-path, no one waiting to be woken.
+        //
+        // Note that `SeqCst` is necessary here to participate in the total order with the
+        // `SeqCst` fence in `register`.
+        if self.pending.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+
+        self.wake_slow(hash);
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn wake_slow(&self, hash: usize) {
+        let Some(shards) = self.shards.get() else {
+            return;
+        };
+
+        // Remove any tasks waiting on the key.
+        //
+        // Note that tasks waiting on keys with the same primary hash are woken spuriously.
+        let mut wakers = Vec::new();
+        {
+            let mut shard = lock(&shards[hash % SHARDS]);
+            shard.retain(|_, (waiting, waker)| {
+                if *waiting as usize != hash {
+                    return true;
+                }
+
+                wakers.push(waker.clone());
+                false
+            });
+        }
+
+        self.pending.fetch_sub(wakers.len(), Ordering::Relaxed);
+
+        // Wake the tasks outside of the lock.
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    // Returns the shard for the given hash.
+    fn shard(&self, hash: u64) -> MutexGuard<'_, Shard> {
+        let shards = self
+            .shards
+            .get_or_init(|| (0..SHARDS).map(|_| Mutex::default()).collect());
+
+        lock(&shards[hash as usize % SHARDS])
+    }
+}
+
+// A task waiting for a key to be written.
+//
+// The waiter is deregistered when it is dropped.
+pub struct Waiter<'a> {
+    waiters: &'a Waiters,
+    hash: u64,
+    id: Option<u64>,
+}
+
+impl Waiter<'_> {
+    // Returns the hash of the key this waiter is waiting on.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    // Register the waker to be woken when the key is next written.
+    //
+    // The wait condition must be checked after this method returns, which is guaranteed to
+    // observe any writes that did not observe the registration.
+    pub fn register(&mut self, waker: &Waker) {
+        {
+            let mut shard = self.waiters.shard(self.hash);
+
+            match self.id.and_then(|id| shard.get_mut(&id)) {
+                // We are still registered, update our waker.
+                Some((_, registered)) => {
+                    if !registered.will_wake(waker) {
+                        registered.clone_from(waker);
+                    }
+                }
+
+                // We are not registered, or were woken since.
+                None => {
+                    // Announce our task.
+                    //
+                    // Note that the `SeqCst` increment here is ordered before the fence below.
+                    self.waiters.pending.fetch_add(1, Ordering::SeqCst);
+
+                    let id = self.waiters.next_id.fetch_add(1, Ordering::Relaxed);
+                    shard.insert(id, (self.hash, waker.clone()));
+                    self.id = Some(id);
+                }
+            }
+        }
+
+        // Establish a total order with the `SeqCst` load of `pending` by writers, ensuring that
+        // either the writer observes our registration or we observe the write.
+        atomic::fence(Ordering::SeqCst);
+    }
+
+    // Deregister the waiter, if it is registered.
+    pub fn cancel(&mut self) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+
+        let removed = self.waiters.shard(self.hash).remove(&id);
+
+        // We were not already woken.
+        if removed.is_some() {
+            self.waiters.pending.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for Waiter<'_> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+// Lock a shard, ignoring poisoning.
+fn lock(shard: &Mutex<Shard>) -> MutexGuard<'_, Shard> {
+    shard.lock().unwrap_or_else(PoisonError::into_inner)
+}
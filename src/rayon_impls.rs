@@ -0,0 +1,646 @@
+use rayon::iter::plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer};
+use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
+use seize::Collector;
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+use crate::raw::{self, utils::MapGuard};
+use crate::{Guard, HashMap, HashMapRef, HashSet, HashSetRef, ResizeMode};
+
+// The minimum number of table slots scanned by a single task.
+const MIN_SLOTS: usize = 1024;
+
+// A producer that splits the table into ranges of slots.
+struct IterProducer<'g, K, V, G> {
+    raw: raw::Iter<'g, K, V, MapGuard<G>>,
+}
+
+impl<'g, K: 'g, V: 'g, G> UnindexedProducer for IterProducer<'g, K, V, G>
+where
+    K: Sync,
+    V: Sync,
+    G: Guard + Sync,
+{
+    type Item = (&'g K, &'g V);
+
+    fn split(self) -> (Self, Option<Self>) {
+        if self.raw.slots() < MIN_SLOTS * 2 {
+            return (self, None);
+        }
+
+        let (left, right) = self.raw.split();
+        (
+            IterProducer { raw: left },
+            right.map(|raw| IterProducer { raw }),
+        )
+    }
+
+    fn fold_with<F>(self, folder: F) -> F
+    where
+        F: Folder<Self::Item>,
+    {
+        folder.consume_iter(self.raw)
+    }
+}
+
+impl<K, V, S, C, G> HashMapRef<'_, K, V, S, C, G>
+where
+    K: Hash + Eq + Sync,
+    V: Sync,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+    G: Guard + Sync,
+{
+    /// A parallel iterator visiting all key-value pairs in arbitrary order.
+    /// The iterator element type is `(&K, &V)`.
+    ///
+    /// Each task scans a separate range of the table. Note that the guard must be
+    /// `Sync` to be shared across threads, meaning the map must be pinned with
+    /// [`HashMap::pin_owned`].
+    ///
+    /// Note that this method will block until any in-progress resizes are
+    /// completed before proceeding. See the [consistency](crate#consistency)
+    /// section for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    /// use rayon::prelude::*;
+    ///
+    /// let map: HashMap<i32, i32> = (0..1000).map(|x| (x, x)).collect();
+    /// let sum: i32 = map.pin_owned().par_iter().map(|(_, v)| v).sum();
+    /// assert_eq!(sum, 499500);
+    /// ```
+    #[inline]
+    pub fn par_iter(&self) -> ParIter<'_, K, V, G> {
+        ParIter {
+            raw: self.iter().raw,
+        }
+    }
+
+    /// A parallel iterator visiting all keys in arbitrary order.
+    /// The iterator element type is `&K`.
+    ///
+    /// See [`HashMapRef::par_iter`] for details.
+    #[inline]
+    pub fn par_keys(&self) -> ParKeys<'_, K, V, G> {
+        ParKeys {
+            iter: self.par_iter(),
+        }
+    }
+
+    /// A parallel iterator visiting all values in arbitrary order.
+    /// The iterator element type is `&V`.
+    ///
+    /// See [`HashMapRef::par_iter`] for details.
+    #[inline]
+    pub fn par_values(&self) -> ParValues<'_, K, V, G> {
+        ParValues {
+            iter: self.par_iter(),
+        }
+    }
+}
+
+impl<'a, K, V, S, C, G> IntoParallelIterator for &'a HashMapRef<'_, K, V, S, C, G>
+where
+    K: Hash + Eq + Sync,
+    V: Sync,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+    G: Guard + Sync,
+{
+    type Item = (&'a K, &'a V);
+    type Iter = ParIter<'a, K, V, G>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+impl<K, V, S, C> HashMap<K, V, S, C>
+where
+    K: Hash + Eq + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+{
+    /// Retains only the elements specified by the predicate, scanning the map in parallel.
+    ///
+    /// Each task scans a separate range of the table, pinning its own guard. Otherwise this
+    /// method behaves like [`HashMap::retain`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashMap;
+    ///
+    /// let map: HashMap<i32, i32> = (0..8).map(|x| (x, x * 10)).collect();
+    /// map.par_retain(|&k, _| k % 2 == 0, &map.guard());
+    /// assert_eq!(map.len(), 4);
+    /// ```
+    #[inline]
+    pub fn par_retain<F>(&self, f: F, guard: &impl Guard)
+    where
+        F: Fn(&K, &V) -> bool + Sync,
+    {
+        self.raw.par_retain(f, self.raw.verify(guard))
+    }
+}
+
+impl<K, V, S, C, G> HashMapRef<'_, K, V, S, C, G>
+where
+    K: Hash + Eq + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+    G: Guard,
+{
+    /// Retains only the elements specified by the predicate, scanning the map in parallel.
+    ///
+    /// See [`HashMap::par_retain`] for details.
+    #[inline]
+    pub fn par_retain<F>(&self, f: F)
+    where
+        F: Fn(&K, &V) -> bool + Sync,
+    {
+        self.map.raw.par_retain(f, &self.guard)
+    }
+}
+
+impl<K, V, S, C> ParallelExtend<(K, V)> for &HashMap<K, V, S, C>
+where
+    K: Hash + Eq + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let map = *self;
+        let par_iter = par_iter.into_par_iter();
+
+        // See `Extend::extend`.
+        if let Some(len) = par_iter.opt_len() {
+            let reserve = if map.is_empty() { len } else { (len + 1) / 2 };
+            map.reserve(reserve, &map.guard());
+        }
+
+        // Every task pins its own guard.
+        par_iter.for_each_init(
+            || map.guard(),
+            |guard, (key, value)| {
+                map.insert(key, value, guard);
+            },
+        );
+    }
+}
+
+impl<'a, K, V, S, C> ParallelExtend<(&'a K, &'a V)> for &HashMap<K, V, S, C>
+where
+    K: Copy + Hash + Eq + Send + Sync,
+    V: Copy + Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (&'a K, &'a V)>,
+    {
+        self.par_extend(par_iter.into_par_iter().map(|(&key, &value)| (key, value)));
+    }
+}
+
+impl<K, V, S, C> ParallelExtend<(K, V)> for HashMap<K, V, S, C>
+where
+    K: Hash + Eq + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        (&*self).par_extend(par_iter);
+    }
+}
+
+impl<'a, K, V, S, C> ParallelExtend<(&'a K, &'a V)> for HashMap<K, V, S, C>
+where
+    K: Copy + Hash + Eq + Send + Sync,
+    V: Copy + Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (&'a K, &'a V)>,
+    {
+        (&*self).par_extend(par_iter);
+    }
+}
+
+impl<K, V, S, C> FromParallelIterator<(K, V)> for HashMap<K, V, S, C>
+where
+    K: Hash + Eq + Send + Sync,
+    V: Send + Sync,
+    S: BuildHasher + Default + Sync,
+    C: Borrow<Collector> + Default + Sync,
+{
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let map = HashMap {
+            raw: raw::HashMap::new(0, S::default(), C::default(), ResizeMode::default()),
+        };
+
+        (&map).par_extend(par_iter);
+        map
+    }
+}
+
+/// A parallel iterator over a map's entries.
+///
+/// This struct is created by the [`par_iter`](HashMapRef::par_iter) method on [`HashMapRef`]. See its documentation for details.
+pub struct ParIter<'g, K, V, G> {
+    raw: raw::Iter<'g, K, V, MapGuard<G>>,
+}
+
+impl<'g, K: 'g, V: 'g, G> ParallelIterator for ParIter<'g, K, V, G>
+where
+    K: Sync,
+    V: Sync,
+    G: Guard + Sync,
+{
+    type Item = (&'g K, &'g V);
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge_unindexed(IterProducer { raw: self.raw }, consumer)
+    }
+}
+
+impl<K, V, G> fmt::Debug for ParIter<'_, K, V, G>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+    G: Guard,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.raw.clone()).finish()
+    }
+}
+
+/// A parallel iterator over a map's keys.
+///
+/// This struct is created by the [`par_keys`](HashMapRef::par_keys) method on [`HashMapRef`]. See its documentation for details.
+pub struct ParKeys<'g, K, V, G> {
+    iter: ParIter<'g, K, V, G>,
+}
+
+impl<'g, K: 'g, V: 'g, G> ParallelIterator for ParKeys<'g, K, V, G>
+where
+    K: Sync,
+    V: Sync,
+    G: Guard + Sync,
+{
+    type Item = &'g K;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.iter.map(|(key, _)| key).drive_unindexed(consumer)
+    }
+}
+
+impl<K, V, G> fmt::Debug for ParKeys<'_, K, V, G>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+    G: Guard,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ParKeys").field(&self.iter).finish()
+    }
+}
+
+/// A parallel iterator over a map's values.
+///
+/// This struct is created by the [`par_values`](HashMapRef::par_values) method on [`HashMapRef`]. See its documentation for details.
+pub struct ParValues<'g, K, V, G> {
+    iter: ParIter<'g, K, V, G>,
+}
+
+impl<'g, K: 'g, V: 'g, G> ParallelIterator for ParValues<'g, K, V, G>
+where
+    K: Sync,
+    V: Sync,
+    G: Guard + Sync,
+{
+    type Item = &'g V;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.iter.map(|(_, value)| value).drive_unindexed(consumer)
+    }
+}
+
+impl<K, V, G> fmt::Debug for ParValues<'_, K, V, G>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+    G: Guard,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ParValues").field(&self.iter).finish()
+    }
+}
+
+impl<K, S, C, G> HashSetRef<'_, K, S, C, G>
+where
+    K: Hash + Eq + Sync,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+    G: Guard + Sync,
+{
+    /// A parallel iterator visiting all values in arbitrary order.
+    /// The iterator element type is `&K`.
+    ///
+    /// Each task scans a separate range of the table. Note that the guard must be
+    /// `Sync` to be shared across threads, meaning the set must be pinned with
+    /// [`HashSet::pin_owned`].
+    ///
+    /// Note that this method will block until any in-progress resizes are
+    /// completed before proceeding. See the [consistency](crate#consistency)
+    /// section for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashSet;
+    /// use rayon::prelude::*;
+    ///
+    /// let set: HashSet<i32> = (0..1000).collect();
+    /// let sum: i32 = set.pin_owned().par_iter().sum();
+    /// assert_eq!(sum, 499500);
+    /// ```
+    #[inline]
+    pub fn par_iter(&self) -> ParSetIter<'_, K, G> {
+        ParSetIter {
+            iter: ParIter {
+                raw: self.iter().raw,
+            },
+        }
+    }
+}
+
+impl<'a, K, S, C, G> IntoParallelIterator for &'a HashSetRef<'_, K, S, C, G>
+where
+    K: Hash + Eq + Sync,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+    G: Guard + Sync,
+{
+    type Item = &'a K;
+    type Iter = ParSetIter<'a, K, G>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+impl<K, S, C> HashSet<K, S, C>
+where
+    K: Hash + Eq + Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+{
+    /// Retains only the elements specified by the predicate, scanning the set in parallel.
+    ///
+    /// Each task scans a separate range of the table, pinning its own guard. Otherwise this
+    /// method behaves like [`HashSet::retain`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashSet;
+    ///
+    /// let set: HashSet<i32> = (0..8).collect();
+    /// set.par_retain(|&k| k % 2 == 0, &set.guard());
+    /// assert_eq!(set.len(), 4);
+    /// ```
+    #[inline]
+    pub fn par_retain<F>(&self, f: F, guard: &impl Guard)
+    where
+        F: Fn(&K) -> bool + Sync,
+    {
+        self.raw.par_retain(|k, _| f(k), self.raw.verify(guard))
+    }
+}
+
+impl<K, S, C, G> HashSetRef<'_, K, S, C, G>
+where
+    K: Hash + Eq + Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+    G: Guard,
+{
+    /// Retains only the elements specified by the predicate, scanning the set in parallel.
+    ///
+    /// See [`HashSet::par_retain`] for details.
+    #[inline]
+    pub fn par_retain<F>(&self, f: F)
+    where
+        F: Fn(&K) -> bool + Sync,
+    {
+        self.set.raw.par_retain(|k, _| f(k), &self.guard)
+    }
+}
+
+impl<K, S, C> ParallelExtend<K> for &HashSet<K, S, C>
+where
+    K: Hash + Eq + Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = K>,
+    {
+        let set = *self;
+        let par_iter = par_iter.into_par_iter();
+
+        // See `Extend::extend`.
+        if let Some(len) = par_iter.opt_len() {
+            let reserve = if set.is_empty() { len } else { (len + 1) / 2 };
+            set.reserve(reserve, &set.guard());
+        }
+
+        // Every task pins its own guard.
+        par_iter.for_each_init(
+            || set.guard(),
+            |guard, key| {
+                set.insert(key, guard);
+            },
+        );
+    }
+}
+
+impl<'a, K, S, C> ParallelExtend<&'a K> for &HashSet<K, S, C>
+where
+    K: Copy + Hash + Eq + Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = &'a K>,
+    {
+        self.par_extend(par_iter.into_par_iter().copied());
+    }
+}
+
+impl<K, S, C> ParallelExtend<K> for HashSet<K, S, C>
+where
+    K: Hash + Eq + Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = K>,
+    {
+        (&*self).par_extend(par_iter);
+    }
+}
+
+impl<'a, K, S, C> ParallelExtend<&'a K> for HashSet<K, S, C>
+where
+    K: Copy + Hash + Eq + Send + Sync,
+    S: BuildHasher + Sync,
+    C: Borrow<Collector> + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = &'a K>,
+    {
+        (&*self).par_extend(par_iter);
+    }
+}
+
+impl<K, S, C> FromParallelIterator<K> for HashSet<K, S, C>
+where
+    K: Hash + Eq + Send + Sync,
+    S: BuildHasher + Default + Sync,
+    C: Borrow<Collector> + Default + Sync,
+{
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = K>,
+    {
+        let set = HashSet {
+            raw: raw::HashMap::new(0, S::default(), C::default(), ResizeMode::default()),
+        };
+
+        (&set).par_extend(par_iter);
+        set
+    }
+}
+
+/// A parallel iterator over a set's keys.
+///
+/// This struct is created by the [`par_iter`](HashSetRef::par_iter) method on [`HashSetRef`]. See its documentation for details.
+pub struct ParSetIter<'g, K, G> {
+    iter: ParIter<'g, K, (), G>,
+}
+
+impl<'g, K: 'g, G> ParallelIterator for ParSetIter<'g, K, G>
+where
+    K: Sync,
+    G: Guard + Sync,
+{
+    type Item = &'g K;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        self.iter.map(|(key, _)| key).drive_unindexed(consumer)
+    }
+}
+
+impl<K, G> fmt::Debug for ParSetIter<'_, K, G>
+where
+    K: fmt::Debug,
+    G: Guard,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.iter.raw.clone().map(|(key, _)| key))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{HashMap, HashSet};
+    use rayon::prelude::*;
+
+    const LEN: usize = if cfg!(miri) { 256 } else { 1 << 14 };
+
+    #[test]
+    fn test_map() {
+        let map: HashMap<usize, usize> = (0..LEN).into_par_iter().map(|x| (x, x)).collect();
+        assert_eq!(map.len(), LEN);
+
+        let map = map.pin_owned();
+        assert_eq!(map.par_iter().count(), LEN);
+        assert_eq!(map.par_keys().sum::<usize>(), (0..LEN).sum::<usize>());
+        assert_eq!(map.par_values().max(), Some(&(LEN - 1)));
+        assert!(map.par_iter().all(|(k, v)| k == v));
+
+        map.par_retain(|k, _| k % 2 == 0);
+        assert_eq!(map.len(), LEN / 2);
+        for i in 0..LEN {
+            assert_eq!(map.get(&i).is_some(), i % 2 == 0);
+        }
+    }
+
+    #[test]
+    fn test_map_extend() {
+        let mut map: HashMap<usize, usize> = HashMap::new();
+        map.par_extend((0..LEN).into_par_iter().map(|x| (x, x)));
+        (&map).par_extend((LEN..LEN * 2).into_par_iter().map(|x| (x, x)));
+        assert_eq!(map.len(), LEN * 2);
+
+        map.par_retain(|_, _| false, &map.guard());
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn test_set() {
+        let set: HashSet<usize> = (0..LEN).into_par_iter().collect();
+        assert_eq!(set.len(), LEN);
+
+        let set = set.pin_owned();
+        assert_eq!(set.par_iter().count(), LEN);
+        assert_eq!(set.par_iter().sum::<usize>(), (0..LEN).sum::<usize>());
+
+        set.par_retain(|k| k % 2 == 0);
+        assert_eq!(set.len(), LEN / 2);
+        for i in 0..LEN {
+            assert_eq!(set.contains(&i), i % 2 == 0);
+        }
+    }
+}
@@ -20,7 +20,7 @@ pub struct HashSet<K, S = RandomState, C = Collector>
 where
     C: Borrow<Collector>,
 {
-    raw: raw::HashMap<K, (), S, C>,
+    pub(crate) raw: raw::HashMap<K, (), S, C>,
 }
 
 // Safety: We only ever hand out &K through shared references to the map,
@@ -547,6 +547,57 @@ where
         self.raw.reserve(additional, self.raw.verify(guard))
     }
 
+    /// Shrinks the capacity of the set as much as possible.
+    ///
+    /// The set will shrink to fit the number of elements it currently holds, while
+    /// maintaining its internal load factor. Note that the set is never shrunk below
+    /// the initial capacity it was created with.
+    ///
+    /// The set also shrinks automatically when enough elements are removed. This method
+    /// can be used to reclaim memory eagerly after a large number of removals.
+    ///
+    /// Note that this method will block until the resize is completed, regardless of
+    /// the configured [`ResizeMode`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashSet;
+    ///
+    /// let set: HashSet<i32> = (0..1000).collect();
+    /// set.pin().retain(|&k| k < 10);
+    /// set.pin().shrink_to_fit();
+    /// assert_eq!(set.len(), 10);
+    /// ```
+    #[inline]
+    pub fn shrink_to_fit(&self, guard: &impl Guard) {
+        self.raw.shrink_to(0, self.raw.verify(guard))
+    }
+
+    /// Shrinks the capacity of the set with a lower limit.
+    ///
+    /// The capacity will remain at least as large as both the number of elements
+    /// in the set and the supplied value, as well as the initial capacity of the set.
+    /// If the current capacity is less than the lower limit, this is a no-op.
+    ///
+    /// Note that this method will block until the resize is completed, regardless of
+    /// the configured [`ResizeMode`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashSet;
+    ///
+    /// let set: HashSet<i32> = (0..1000).collect();
+    /// set.pin().retain(|&k| k < 10);
+    /// set.pin().shrink_to(100);
+    /// assert_eq!(set.len(), 10);
+    /// ```
+    #[inline]
+    pub fn shrink_to(&self, capacity: usize, guard: &impl Guard) {
+        self.raw.shrink_to(capacity, self.raw.verify(guard))
+    }
+
     /// Clears the set, removing all values.
     ///
     /// Note that this method will block until any in-progress resizes are
@@ -629,6 +680,129 @@ where
             raw: self.raw.iter(self.raw.verify(guard)),
         }
     }
+
+    /// An iterator visiting the values in a single partition of the set.
+    ///
+    /// The underlying table is split into `of` contiguous ranges of slots, and the returned
+    /// iterator visits the values in the range at the given `index`. Together, the partitions
+    /// yield every value exactly once if the set is not concurrently modified. See
+    /// [`HashMap::iter_partition`](crate::HashMap::iter_partition) for details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than `of`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashSet;
+    ///
+    /// let set: HashSet<i32> = (0..1000).collect();
+    /// let set = set.pin();
+    ///
+    /// let count: usize = (0..4).map(|i| set.iter_partition(i, 4).count()).sum();
+    /// assert_eq!(count, 1000);
+    /// ```
+    #[inline]
+    pub fn iter_partition<'g, G>(&self, index: usize, of: usize, guard: &'g G) -> Iter<'g, K, G>
+    where
+        G: Guard,
+    {
+        Iter {
+            raw: self.raw.iter_partition(index, of, self.raw.verify(guard)),
+        }
+    }
+}
+
+/// Exclusive access operations.
+///
+/// These operations take `&mut self`, and so do not require a guard. Exclusive access to a set
+/// that owns its collector statically guarantees that no other thread holds references into the
+/// set, so keys can be accessed and reclaimed directly.
+///
+/// Note that these operations are not available for sets using a
+/// [shared collector](HashSetBuilder::shared_collector), as guards for a shared collector
+/// may outlive a borrow of the set.
+impl<K, S> HashSet<K, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    /// Inserts a value into the set.
+    ///
+    /// If the set did not have this key present, `true` is returned.
+    ///
+    /// If the set did have this key present, `false` is returned and the old
+    /// key is not updated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashSet;
+    ///
+    /// let mut set = HashSet::new();
+    /// assert!(set.insert_mut(37));
+    /// assert!(!set.insert_mut(37));
+    /// assert!(set.pin().contains(&37));
+    /// ```
+    #[inline]
+    pub fn insert_mut(&mut self, key: K) -> bool {
+        // Safety: We own the collector, so any guards would borrow the set.
+        unsafe { self.raw.insert_mut(key, ()) }.is_none()
+    }
+
+    /// Removes a key from the set, returning the owned key if it was
+    /// previously in the set.
+    ///
+    /// The key may be any borrowed form of the set's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashSet;
+    ///
+    /// let mut set = HashSet::new();
+    /// set.insert_mut(String::from("a"));
+    /// assert_eq!(set.remove_owned("a"), Some(String::from("a")));
+    /// assert_eq!(set.remove_owned("a"), None);
+    /// ```
+    #[inline]
+    pub fn remove_owned<Q>(&mut self, key: &Q) -> Option<K>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        // Safety: We own the collector, so any guards would borrow the set.
+        let (key, _) = unsafe { self.raw.remove_mut(key) }?;
+        Some(key)
+    }
+
+    /// Clears the set, returning all keys as an iterator.
+    ///
+    /// If the returned iterator is dropped before being fully consumed, it
+    /// drops the remaining keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashSet;
+    ///
+    /// let mut set = HashSet::from([1, 2]);
+    ///
+    /// let mut drained = set.drain().collect::<Vec<_>>();
+    /// drained.sort();
+    ///
+    /// assert_eq!(drained, [1, 2]);
+    /// assert!(set.is_empty());
+    /// ```
+    #[inline]
+    pub fn drain(&mut self) -> Drain<'_, K> {
+        Drain {
+            // Safety: We own the collector, so any guards would borrow the set.
+            raw: unsafe { self.raw.drain() },
+        }
+    }
 }
 
 impl<K, S, C> PartialEq for HashSet<K, S, C>
@@ -669,6 +843,35 @@ where
     }
 }
 
+impl<K, S, C> IntoIterator for HashSet<K, S, C>
+where
+    C: Borrow<Collector>,
+{
+    type Item = K;
+    type IntoIter = IntoIter<K>;
+
+    /// Creates a consuming iterator, that is, one that moves each key out
+    /// of the set in arbitrary order. The set cannot be used after calling this.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use papaya::HashSet;
+    ///
+    /// let set = HashSet::from(["a", "b", "c"]);
+    ///
+    /// let mut vec: Vec<&str> = set.into_iter().collect();
+    /// vec.sort_unstable();
+    /// assert_eq!(vec, ["a", "b", "c"]);
+    /// ```
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            raw: self.raw.into_entries(),
+        }
+    }
+}
+
 impl<K, S, C> Extend<K> for &HashSet<K, S, C>
 where
     K: Hash + Eq,
@@ -812,8 +1015,8 @@ pub struct HashSetRef<'set, K, S, C, G>
 where
     C: Borrow<Collector>,
 {
-    guard: MapGuard<G>,
-    set: &'set HashSet<K, S, C>,
+    pub(crate) guard: MapGuard<G>,
+    pub(crate) set: &'set HashSet<K, S, C>,
 }
 
 impl<'set, K, S, C, G> HashSetRef<'set, K, S, C, G>
@@ -925,6 +1128,22 @@ where
         self.set.raw.reserve(additional, &self.guard)
     }
 
+    /// Shrinks the capacity of the set as much as possible.
+    ///
+    /// See [`HashSet::shrink_to_fit`] for details.
+    #[inline]
+    pub fn shrink_to_fit(&self) {
+        self.set.raw.shrink_to(0, &self.guard)
+    }
+
+    /// Shrinks the capacity of the set with a lower limit.
+    ///
+    /// See [`HashSet::shrink_to`] for details.
+    #[inline]
+    pub fn shrink_to(&self, capacity: usize) {
+        self.set.raw.shrink_to(capacity, &self.guard)
+    }
+
     /// An iterator visiting all values in arbitrary order.
     /// The iterator element type is `(&K, &V)`.
     ///
@@ -935,6 +1154,16 @@ where
             raw: self.set.raw.iter(&self.guard),
         }
     }
+
+    /// An iterator visiting the values in a single partition of the set.
+    ///
+    /// See [`HashSet::iter_partition`] for details.
+    #[inline]
+    pub fn iter_partition(&self, index: usize, of: usize) -> Iter<'_, K, G> {
+        Iter {
+            raw: self.set.raw.iter_partition(index, of, &self.guard),
+        }
+    }
 }
 
 impl<K, S, C, G> fmt::Debug for HashSetRef<'_, K, S, C, G>
@@ -968,7 +1197,7 @@ where
 ///
 /// This struct is created by the [`iter`](HashSet::iter) method on [`HashSet`]. See its documentation for details.
 pub struct Iter<'g, K, G> {
-    raw: raw::Iter<'g, K, (), MapGuard<G>>,
+    pub(crate) raw: raw::Iter<'g, K, (), MapGuard<G>>,
 }
 
 impl<'g, K: 'g, G> Iterator for Iter<'g, K, G>
@@ -996,3 +1225,63 @@ where
             .finish()
     }
 }
+
+/// A draining iterator over a set's keys.
+///
+/// This struct is created by the [`drain`](HashSet::drain) method on [`HashSet`]. See its documentation for details.
+pub struct Drain<'a, K> {
+    raw: raw::Drain<'a, K, ()>,
+}
+
+impl<K> Iterator for Drain<'_, K> {
+    type Item = K;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw.next().map(|(k, _)| k)
+    }
+}
+
+impl<K> fmt::Debug for Drain<'_, K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.raw.remaining().map(|(k, _)| k))
+            .finish()
+    }
+}
+
+/// An owning iterator over a set's keys.
+///
+/// This struct is created by the [`into_iter`](IntoIterator::into_iter) method on [`HashSet`]
+/// (provided by the [`IntoIterator`] trait). See its documentation for details.
+pub struct IntoIter<K> {
+    raw: raw::IntoIter<K, ()>,
+}
+
+impl<K> Iterator for IntoIter<K> {
+    type Item = K;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.raw.next().map(|(k, _)| k)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.raw.size_hint()
+    }
+}
+
+impl<K> fmt::Debug for IntoIter<K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.raw.remaining().map(|(k, _)| k))
+            .finish()
+    }
+}
@@ -0,0 +1,535 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::sync::atomic::{self, AtomicIsize, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError, RwLock};
+use std::{ptr, thread};
+
+use crate::raw::utils::{Counter, MapGuard};
+use crate::raw::{self, Entry};
+use crate::Equivalent;
+
+use seize::{Collector, Guard, LocalGuard};
+
+/// A point-in-time view of a [`HashMap`](crate::HashMap).
+///
+/// This type is created by [`HashMap::snapshot`](crate::HashMap::snapshot). See its
+/// documentation for details.
+///
+/// A snapshot reflects the state of the map at a single point in time, regardless of any
+/// writes made to the map after it was created. Entries that are modified after the snapshot
+/// was taken are preserved until the snapshot is dropped.
+pub struct Snapshot<'g, K, V, S = RandomState, C = Collector, G = LocalGuard<'g>>
+where
+    C: Borrow<Collector>,
+{
+    map: &'g raw::HashMap<K, V, S, C>,
+    guard: &'g MapGuard<G>,
+    records: Arc<Records<K, V>>,
+    version: u64,
+    entries: OnceLock<Entries<K, V>>,
+}
+
+/// The entries of a snapshot, collected on first use.
+struct Entries<K, V>(Vec<*const Entry<K, V>>);
+
+// Safety: The entries are only used to yield shared references to keys and values.
+unsafe impl<K: Sync, V: Sync> Send for Entries<K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for Entries<K, V> {}
+
+impl<'g, K, V, S, C, G> Snapshot<'g, K, V, S, C, G>
+where
+    K: Hash + Eq + 'g,
+    V: 'g,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+    G: Guard,
+{
+    /// Creates a snapshot of the map.
+    pub(crate) fn new(
+        map: &'g raw::HashMap<K, V, S, C>,
+        guard: &'g MapGuard<G>,
+    ) -> Snapshot<'g, K, V, S, C, G> {
+        let (records, version) = map.snapshot();
+
+        Snapshot {
+            map,
+            guard,
+            records,
+            version,
+            entries: OnceLock::new(),
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key, as of the snapshot.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    #[inline]
+    pub fn get<Q>(&self, key: &Q) -> Option<&'g V>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    /// Returns the key-value pair corresponding to the supplied key, as of the snapshot.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    #[inline]
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&'g K, &'g V)>
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        let entry = self.map.get_entry(key, self.guard);
+
+        // The entry has not been modified since the snapshot was taken.
+        if let Some(entry) = entry {
+            if self.unmodified(entry) {
+                return Some((&entry.key, &entry.value));
+            }
+        }
+
+        // Otherwise, the entry may have been preserved by a later write.
+        let (hash, _) = self.map.hash(key);
+        let entry = match self.records.find(hash, |k| key.equivalent(k)) {
+            Some(preserved) => preserved,
+            None => entry.map_or(ptr::null(), |entry| entry as *const _),
+        };
+
+        // Safety: Preserved entries were retired after the snapshot was taken, and so are valid
+        // for reads as long as we hold the guard.
+        unsafe { entry.as_ref() }.map(|entry| (&entry.key, &entry.value))
+    }
+
+    /// Returns `true` if the snapshot contains a value for the specified key.
+    ///
+    /// The key may be any borrowed form of the map's key type, but
+    /// [`Hash`] and [`Eq`] on the borrowed form *must* match those for
+    /// the key type.
+    #[inline]
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        Q: Equivalent<K> + Hash + ?Sized,
+    {
+        self.get_key_value(key).is_some()
+    }
+
+    /// Returns an iterator over the entries of the snapshot, in arbitrary order.
+    ///
+    /// Note that the entries are collected the first time this method or [`len`](Snapshot::len)
+    /// is called, which takes time proportional to the size of the map. Later calls reuse the
+    /// collected entries.
+    pub fn iter(&self) -> SnapshotIter<'g, K, V> {
+        SnapshotIter {
+            entries: self.entries().to_vec().into_iter(),
+            _guard: PhantomData,
+        }
+    }
+
+    /// Returns the number of entries in the snapshot.
+    ///
+    /// Note that the entries are collected the first time this method or
+    /// [`iter`](Snapshot::iter) is called, which takes time proportional to the size of the map.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries().len()
+    }
+
+    /// Returns `true` if the snapshot is empty.
+    ///
+    /// See [`len`](Snapshot::len) for details.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the entries of the snapshot, collecting them if this is the first call.
+    ///
+    /// The snapshot reflects a single point in time, so the collected entries never change.
+    fn entries(&self) -> &[*const Entry<K, V>] {
+        &self.entries.get_or_init(|| Entries(self.collect())).0
+    }
+
+    /// Collects the entries of the snapshot.
+    fn collect(&self) -> Vec<*const Entry<K, V>> {
+        let mut entries = Vec::new();
+        let mut seen = HashSet::new();
+
+        let mut iter = self.map.iter(self.guard);
+        while let Some(entry) = iter.next_entry() {
+            let entry = if self.unmodified(entry) {
+                entry as *const Entry<K, V>
+            } else {
+                // The entry may have been modified since the snapshot was taken.
+                let (hash, _) = self.map.hash(&entry.key);
+                match self.records.find(hash, |k| *k == entry.key) {
+                    Some(preserved) if preserved.is_null() => continue,
+                    Some(preserved) => preserved,
+                    None => entry,
+                }
+            };
+
+            if seen.insert(entry) {
+                entries.push(entry);
+            }
+        }
+
+        // Collect any entries that were removed or replaced after we visited them.
+        for entry in self.records.entries() {
+            if !entry.is_null() && seen.insert(entry) {
+                entries.push(entry);
+            }
+        }
+
+        entries
+    }
+
+    /// Returns `true` if the live entry was written before the snapshot was taken, and so could
+    /// not have been preserved.
+    #[inline]
+    fn unmodified(&self, entry: &Entry<K, V>) -> bool {
+        // Safety: The entry was read from this map.
+        unsafe { self.map.version(entry) <= self.version }
+    }
+}
+
+impl<K, V, S, C, G> Drop for Snapshot<'_, K, V, S, C, G>
+where
+    C: Borrow<Collector>,
+{
+    fn drop(&mut self) {
+        self.map.release_snapshot(&self.records);
+    }
+}
+
+impl<K, V, S, C, G> fmt::Debug for Snapshot<'_, K, V, S, C, G>
+where
+    K: Hash + Eq + fmt::Debug,
+    V: fmt::Debug,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+    G: Guard,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// An iterator over the entries of a [`Snapshot`].
+///
+/// This struct is created by the [`iter`](Snapshot::iter) method on [`Snapshot`]. See its
+/// documentation for details.
+pub struct SnapshotIter<'g, K, V> {
+    entries: std::vec::IntoIter<*const Entry<K, V>>,
+    _guard: PhantomData<&'g ()>,
+}
+
+impl<'g, K: 'g, V: 'g> Iterator for SnapshotIter<'g, K, V> {
+    type Item = (&'g K, &'g V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // Safety: The entries were either reachable from the map or retired after the snapshot
+        // was taken, and so are valid for reads as long as the guard is held.
+        let entry = unsafe { &*self.entries.next()? };
+        Some((&entry.key, &entry.value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<'g, K: 'g, V: 'g> ExactSizeIterator for SnapshotIter<'g, K, V> {}
+
+// Safety: The iterator only yields shared references to keys and values.
+unsafe impl<K: Sync, V: Sync> Send for SnapshotIter<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for SnapshotIter<'_, K, V> {}
+
+impl<K, V> fmt::Debug for SnapshotIter<'_, K, V>
+where
+    K: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.entries.as_slice().iter().map(|&entry| {
+                // Safety: See `SnapshotIter::next`.
+                let entry = unsafe { &*entry };
+                (&entry.key, &entry.value)
+            }))
+            .finish()
+    }
+}
+
+/// The number of locks used to serialize writes to preserved keys.
+const STRIPES: usize = 64;
+
+/// The set of snapshots taken of a map.
+///
+/// Writers to a map with active snapshots preserve the state of a key before modifying it,
+/// allowing snapshots to see through any writes made after they were taken. Writers that did
+/// not observe a snapshot being registered are waited on before the snapshot is taken, so
+/// every write is either complete before the snapshot, or preserved.
+pub(crate) struct Snapshots<K, V> {
+    /// The number of live snapshots.
+    active: AtomicUsize,
+
+    /// The current writer epoch, used to wait for in-progress writes.
+    epoch: AtomicUsize,
+
+    /// The number of in-progress writes that entered each epoch.
+    writers: [Counter; 2],
+
+    /// The records of every live snapshot.
+    records: RwLock<Vec<Arc<Records<K, V>>>>,
+
+    /// Locks held across a write and the preservation of the key it modifies.
+    stripes: Box<[Mutex<()>]>,
+
+    /// A lock held while registering a snapshot.
+    registering: Mutex<()>,
+}
+
+impl<K, V> Snapshots<K, V> {
+    /// Creates an empty set of snapshots.
+    pub fn new() -> Snapshots<K, V> {
+        Snapshots {
+            active: AtomicUsize::new(0),
+            epoch: AtomicUsize::new(0),
+            writers: [Counter::default(), Counter::default()],
+            records: RwLock::new(Vec::new()),
+            stripes: (0..STRIPES).map(|_| Mutex::new(())).collect(),
+            registering: Mutex::new(()),
+        }
+    }
+
+    /// Registers a new snapshot, returning its records and the version it was taken at.
+    ///
+    /// Entries stamped with a version less than or equal to the returned version were written
+    /// before the snapshot was taken.
+    pub fn register(&self, clock: &AtomicU64) -> (Arc<Records<K, V>>, u64) {
+        let _registering = lock(&self.registering);
+
+        // Note that any writes that observe the registration below are guaranteed to be stamped
+        // with a later version.
+        let version = clock.load(Ordering::Relaxed);
+
+        let records = Arc::new(Records::new());
+        (self.records.write().unwrap_or_else(PoisonError::into_inner)).push(records.clone());
+        self.active.fetch_add(1, Ordering::SeqCst);
+
+        // Advance the epoch and wait for any writes that entered the previous epoch to complete.
+        //
+        // Writes that enter the new epoch are guaranteed to observe the registration.
+        let epoch = self.epoch.fetch_add(1, Ordering::SeqCst);
+
+        // Establish a total order with the `SeqCst` increment in `Snapshots::write`, ensuring
+        // that either we observe the in-progress write, or it observes our registration.
+        atomic::fence(Ordering::SeqCst);
+        while self.writers[epoch & 1].sum() != 0 {
+            thread::yield_now();
+        }
+
+        // Synchronize with the completed writes.
+        atomic::fence(Ordering::Acquire);
+
+        (records, version)
+    }
+
+    /// Deregisters a snapshot.
+    pub fn release(&self, records: &Arc<Records<K, V>>) {
+        (self.records.write().unwrap_or_else(PoisonError::into_inner))
+            .retain(|other| !Arc::ptr_eq(other, records));
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Begins a write to the key with the given primary hash.
+    ///
+    /// The write must be completed before the returned guard is dropped. If there are any
+    /// active snapshots, the state of the key must be preserved with `Write::preserve`
+    /// before it is modified.
+    ///
+    /// Every write is counted with an increment of the current thread's shard of the writer
+    /// counter. The hash is only computed, and the stripe lock for the key only acquired, while
+    /// there are active snapshots.
+    #[inline]
+    pub fn write(&self, hash: impl FnOnce() -> usize, guard: &impl Guard) -> Write<'_, K, V> {
+        let writers = loop {
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            let writers = self.writers[epoch & 1].get(guard);
+
+            // Note that `SeqCst` is necessary here to participate in the total order established
+            // by the `SeqCst` fence in `Snapshots::register`.
+            writers.fetch_add(1, Ordering::SeqCst);
+
+            // Ensure the epoch was not advanced before we were counted, in which case the
+            // registration may not have waited for us.
+            if self.epoch.load(Ordering::SeqCst) == epoch {
+                break writers;
+            }
+
+            writers.fetch_sub(1, Ordering::Release);
+        };
+
+        // Acquire the lock for the key if there are any active snapshots.
+        let (hash, lock) = if self.active.load(Ordering::SeqCst) != 0 {
+            let hash = hash();
+            (hash, Some(lock(&self.stripes[hash % STRIPES])))
+        } else {
+            (0, None)
+        };
+
+        Write {
+            snapshots: self,
+            writers,
+            hash,
+            lock,
+        }
+    }
+}
+
+/// An in-progress write to a map that supports snapshots.
+pub(crate) struct Write<'a, K, V> {
+    snapshots: &'a Snapshots<K, V>,
+    writers: &'a AtomicIsize,
+    hash: usize,
+    lock: Option<MutexGuard<'a, ()>>,
+}
+
+impl<K, V> Write<'_, K, V> {
+    /// Returns `true` if the key must be preserved before it is modified.
+    #[inline]
+    pub fn is_preserving(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    /// Preserves the current state of the key for every active snapshot, where `entry` is the
+    /// current entry for the key, or null if the key is not present.
+    ///
+    /// Returns `true` if the key was not already preserved by any snapshot. Keys are only
+    /// preserved by the first write after a snapshot is taken.
+    ///
+    /// # Safety
+    ///
+    /// The key and entry must remain valid for reads until the snapshot guard is dropped,
+    /// unless the preservation is undone with `Write::unpreserve`.
+    pub unsafe fn preserve(&self, key: &K, entry: *const Entry<K, V>) -> bool
+    where
+        K: Eq,
+    {
+        if !self.is_preserving() {
+            return false;
+        }
+
+        let hash = self.hash;
+
+        let mut preserved = false;
+        let records = (self.snapshots.records.read()).unwrap_or_else(PoisonError::into_inner);
+        for records in records.iter() {
+            let mut stripe = records.stripe(hash);
+            let found = stripe
+                .iter()
+                .any(|record| record.hash == hash && unsafe { *record.key == *key });
+
+            if !found {
+                stripe.push(Record { hash, key, entry });
+                preserved = true;
+            }
+        }
+
+        preserved
+    }
+
+    /// Undoes the preservation of a key that was not modified, where `key` and `entry` are the
+    /// arguments that were passed to `Write::preserve`.
+    ///
+    /// Records of the key that were preserved by earlier writes are left untouched.
+    pub fn unpreserve(&self, key: &K, entry: *const Entry<K, V>) {
+        let hash = self.hash;
+
+        let records = (self.snapshots.records.read()).unwrap_or_else(PoisonError::into_inner);
+        for records in records.iter() {
+            records
+                .stripe(hash)
+                .retain(|record| !(ptr::eq(record.key, key) && record.entry == entry));
+        }
+    }
+}
+
+impl<K, V> Drop for Write<'_, K, V> {
+    #[inline]
+    fn drop(&mut self) {
+        self.writers.fetch_sub(1, Ordering::Release);
+    }
+}
+
+/// The keys preserved for a single snapshot.
+pub(crate) struct Records<K, V> {
+    stripes: Box<[Mutex<Stripe<K, V>>]>,
+}
+
+/// The records for keys in a given stripe.
+type Stripe<K, V> = Vec<Record<K, V>>;
+
+/// The state of a key at the time a snapshot was taken.
+struct Record<K, V> {
+    hash: usize,
+    key: *const K,
+
+    /// The entry for the key, or null if it was not present.
+    entry: *const Entry<K, V>,
+}
+
+// Safety: Records only hold shared references to keys and entries.
+unsafe impl<K: Sync, V: Sync> Send for Record<K, V> {}
+
+impl<K, V> Records<K, V> {
+    fn new() -> Records<K, V> {
+        Records {
+            stripes: (0..STRIPES).map(|_| Mutex::default()).collect(),
+        }
+    }
+
+    /// Returns the records for keys with the given hash.
+    #[inline]
+    fn stripe(&self, hash: usize) -> MutexGuard<'_, Stripe<K, V>> {
+        lock(&self.stripes[hash % STRIPES])
+    }
+
+    /// Returns the preserved entry for a key, or null if the key was preserved as absent.
+    #[inline]
+    fn find(&self, hash: usize, eq: impl Fn(&K) -> bool) -> Option<*const Entry<K, V>> {
+        self.stripe(hash)
+            .iter()
+            // Safety: Preserved keys are valid for reads until the snapshot is dropped.
+            .find(|record| record.hash == hash && eq(unsafe { &*record.key }))
+            .map(|record| record.entry)
+    }
+
+    /// Returns the preserved entries of every key.
+    fn entries(&self) -> Vec<*const Entry<K, V>> {
+        self.stripes
+            .iter()
+            .flat_map(|stripe| {
+                lock(stripe)
+                    .iter()
+                    .map(|record| record.entry)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+}
+
+// Lock a mutex, ignoring poisoning.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
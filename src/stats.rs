@@ -0,0 +1,63 @@
+/// Statistics about the internal state of a [`HashMap`](crate::HashMap).
+///
+/// This type is returned by [`HashMap::stats`](crate::HashMap::stats). The statistics are
+/// collected by walking the table without blocking writers, so they are only a best-effort
+/// estimate if the map is modified concurrently.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MapStats {
+    /// The number of entries in the map, as returned by [`HashMap::len`](crate::HashMap::len).
+    pub len: usize,
+
+    /// The number of slots in the root table.
+    pub capacity: usize,
+
+    /// The number of slots in the root table that contain an entry.
+    pub live: usize,
+
+    /// The number of slots in the root table that contain a deleted entry.
+    ///
+    /// Tombstones continue to occupy a slot until the table is resized.
+    pub tombstones: usize,
+
+    /// A histogram of the probe lengths of entries in the root table.
+    ///
+    /// The value at index `i` is the number of entries found `i` probes away from the start
+    /// of their probe sequence. The histogram has one bucket for every probe length up to
+    /// and including [`probe_limit`](MapStats::probe_limit).
+    pub probe_lengths: Vec<usize>,
+
+    /// The maximum probe length of the root table.
+    ///
+    /// The table is resized when an insert exceeds the probe limit.
+    pub probe_limit: usize,
+
+    /// The progress of the resize of the root table, if one is in progress.
+    pub resize: Option<ResizeStats>,
+
+    /// The number of tables nested after the root table.
+    ///
+    /// Nested tables are allocated for resizes, and are removed once a resize completes.
+    pub depth: usize,
+
+    /// The number of entries whose reclamation has been deferred until a resize completes.
+    pub deferred: usize,
+
+    /// The number of times a table has been promoted to the root table.
+    pub promotions: u64,
+}
+
+/// The progress of a resize, reported by [`MapStats`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ResizeStats {
+    /// The number of slots in the table being resized into.
+    pub capacity: usize,
+
+    /// The number of slots that have been copied from the root table.
+    pub copied: usize,
+
+    /// The number of slots that have been claimed by copying threads, but not necessarily
+    /// copied.
+    pub claimed: usize,
+}
@@ -0,0 +1,513 @@
+//! Multi-key optimistic transactions.
+//!
+//! A [`transaction`] runs a closure that reads and writes any number of keys, across one or
+//! more [`HashMap`]s, and commits all of its writes atomically with respect to other
+//! transactions. Reads are recorded as the exact entries that were observed, and writes are
+//! buffered until the closure returns. On commit, the transaction locks the keys it accessed,
+//! validates that none of the observed entries have changed, and applies its writes. If
+//! validation fails, the closure is retried.
+//!
+//! Entries are identified by their allocation, which is kept alive by the guard the transaction
+//! runs with. This means validation is not subject to ABA, even if a key is reinserted with an
+//! equal value. However, it also means all maps accessed by a transaction must share the
+//! collector of its guard, see [`HashMapBuilder::shared_collector`].
+//!
+//! # Consistency
+//!
+//! Transactions never observe the partial writes of another transaction. Each read blocks
+//! while a conflicting commit is in progress, and revalidates all previous reads, returning a
+//! [`Conflict`] error if the transaction can no longer commit successfully.
+//!
+//! Note that writes are only applied atomically with respect to other transactions. Operations
+//! outside of a transaction, such as [`HashMap::get`], may observe a partially applied commit,
+//! and concurrent modifications made outside of a transaction may be lost. Keys that require
+//! atomic multi-key updates should only be modified through transactions.
+//!
+//! # Examples
+//!
+//! ```
+//! use papaya::{txn, HashMap};
+//!
+//! let stock = HashMap::new();
+//! stock.pin().insert("a", 10);
+//! stock.pin().insert("b", 0);
+//!
+//! // Move 5 units from `a` to `b`.
+//! let guard = stock.guard();
+//! let moved = txn::transaction(&guard, |tx| {
+//!     let a = *tx.get(&stock, &"a")?.unwrap_or(&0);
+//!     let b = *tx.get(&stock, &"b")?.unwrap_or(&0);
+//!
+//!     if a < 5 {
+//!         return Ok(false);
+//!     }
+//!
+//!     tx.insert(&stock, "a", a - 5);
+//!     tx.insert(&stock, "b", b + 5);
+//!     Ok(true)
+//! });
+//!
+//! assert!(moved);
+//! assert_eq!(stock.pin().get(&"a"), Some(&5));
+//! assert_eq!(stock.pin().get(&"b"), Some(&5));
+//! ```
+//!
+//! [`HashMapBuilder::shared_collector`]: crate::HashMapBuilder::shared_collector
+
+use crate::raw::{self, utils::MapGuard};
+use crate::HashMap;
+use seize::{Collector, Guard};
+
+use std::any::TypeId;
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::hash::{BuildHasher, Hash};
+use std::marker::PhantomData;
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+use std::{error, fmt, mem, ptr};
+
+/// Runs a transaction, retrying until it commits successfully.
+///
+/// The closure is called with a [`Transaction`] that can be used to read and write keys.
+/// If the closure returns `Ok`, the transaction attempts to commit its writes. If the commit
+/// fails, or the closure returns a [`Conflict`] error, the closure is called again with a
+/// fresh transaction. Note that the closure may be called many times under contention, and
+/// should not have side effects other than through the transaction.
+///
+/// # Panics
+///
+/// Panics if a map accessed by the transaction does not share the collector of `guard`.
+///
+/// # Examples
+///
+/// ```
+/// use papaya::{txn, HashMap};
+///
+/// let map = HashMap::new();
+/// let guard = map.guard();
+///
+/// txn::transaction(&guard, |tx| {
+///     tx.insert(&map, 1, "a");
+///     tx.insert(&map, 2, "b");
+///     Ok(())
+/// });
+///
+/// assert_eq!(map.len(), 2);
+/// ```
+pub fn transaction<'g, G, F, T>(guard: &'g G, mut f: F) -> T
+where
+    G: Guard,
+    F: FnMut(&Transaction<'g, G>) -> Result<T, Conflict>,
+{
+    loop {
+        let tx = Transaction::new(guard);
+
+        if let Ok(value) = f(&tx) {
+            if tx.commit() {
+                return value;
+            }
+        }
+
+        // Give the conflicting transaction a chance to complete.
+        std::thread::yield_now();
+    }
+}
+
+/// An error indicating that a transaction conflicted with a concurrent commit.
+///
+/// This error is returned by [`Transaction::get`] when a previous read is no longer valid,
+/// and should be propagated out of the transaction closure to retry it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Conflict;
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("transaction conflicted with a concurrent commit")
+    }
+}
+
+impl error::Error for Conflict {}
+
+/// An in-progress transaction.
+///
+/// See the [module-level documentation](self) for details.
+pub struct Transaction<'g, G> {
+    /// The guard the transaction runs with.
+    guard: &'g G,
+
+    /// The entries observed by the transaction.
+    reads: RefCell<Vec<Box<dyn Read + 'g>>>,
+
+    /// The buffered writes, in the order they were made.
+    writes: RefCell<Vec<Box<dyn Write + 'g>>>,
+}
+
+impl<'g, G> Transaction<'g, G>
+where
+    G: Guard,
+{
+    /// Creates a new transaction.
+    fn new(guard: &'g G) -> Transaction<'g, G> {
+        Transaction {
+            guard,
+            reads: RefCell::default(),
+            writes: RefCell::default(),
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// Writes made earlier in the transaction are visible to subsequent reads. Otherwise, the
+    /// observed entry is recorded and validated when the transaction commits.
+    ///
+    /// If a previous read is no longer valid, a [`Conflict`] error is returned. The error should
+    /// be propagated out of the transaction closure, which will then be retried.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map does not share the collector of the transaction's guard.
+    pub fn get<'t, K, V, S, C>(
+        &'t self,
+        map: &'g HashMap<K, V, S, C>,
+        key: &K,
+    ) -> Result<Option<&'t V>, Conflict>
+    where
+        K: Hash + Eq + Clone + 'g,
+        V: 'g,
+        S: BuildHasher + 'g,
+        C: Borrow<Collector> + 'g,
+        G: 'g,
+    {
+        let guard = map.raw.verify(self.guard);
+        let hash = map.raw.hasher.hash_one(key);
+
+        // Check for a buffered write to this key.
+        for write in self.writes.borrow().iter().rev() {
+            if write.map() != map as *const _ as *const ()
+                || write.map_type() != type_id::<HashMap<K, V, S, C>>()
+                || write.hash() != hash
+            {
+                continue;
+            }
+
+            // Safety: The write was made to a map of the same type at the same address. A map
+            // cannot contain another map of the same type, so this must be the same map, and the
+            // write has the same key, value, hasher, and collector types. The transaction guard
+            // type is the same for all writes.
+            let write =
+                unsafe { &*(&**write as *const dyn Write as *const WriteEntry<'g, K, V, S, C, G>) };
+
+            if write.key == *key {
+                // Safety: Buffered writes are boxed and are never dropped or mutated until the
+                // transaction is consumed, so the value is valid for the lifetime of `self`.
+                let value = write.value.as_ref().map(|value| value as *const V);
+                return Ok(value.map(|value| unsafe { &*value }));
+            }
+        }
+
+        let stripe = stripe(map, hash);
+
+        // Wait for any conflicting commits to complete.
+        drop(lock(stripe));
+
+        let observed = match map.raw.get_entry(key, guard) {
+            Some(entry) => Observed::Present(entry),
+            None => Observed::Absent(key.clone()),
+        };
+
+        let value = match observed {
+            Observed::Present(entry) => Some(&entry.value),
+            Observed::Absent(_) => None,
+        };
+
+        let mut reads = self.reads.borrow_mut();
+
+        // Ensure the transaction's view of the maps is still consistent.
+        if !reads.iter().all(|read| read.validate()) {
+            return Err(Conflict);
+        }
+
+        reads.push(Box::new(ReadEntry {
+            map,
+            guard,
+            stripe,
+            observed,
+        }));
+
+        Ok(value)
+    }
+
+    /// Inserts a key-value pair into the map when the transaction commits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map does not share the collector of the transaction's guard.
+    pub fn insert<K, V, S, C>(&self, map: &'g HashMap<K, V, S, C>, key: K, value: V)
+    where
+        K: Hash + Eq + 'g,
+        V: 'g,
+        S: BuildHasher + 'g,
+        C: Borrow<Collector> + 'g,
+        G: 'g,
+    {
+        self.write(map, key, Some(value));
+    }
+
+    /// Removes a key from the map when the transaction commits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the map does not share the collector of the transaction's guard.
+    pub fn remove<K, V, S, C>(&self, map: &'g HashMap<K, V, S, C>, key: &K)
+    where
+        K: Hash + Eq + Clone + 'g,
+        V: 'g,
+        S: BuildHasher + 'g,
+        C: Borrow<Collector> + 'g,
+        G: 'g,
+    {
+        self.write(map, key.clone(), None);
+    }
+
+    /// Buffers a write to the given key.
+    fn write<K, V, S, C>(&self, map: &'g HashMap<K, V, S, C>, key: K, value: Option<V>)
+    where
+        K: Hash + Eq + 'g,
+        V: 'g,
+        S: BuildHasher + 'g,
+        C: Borrow<Collector> + 'g,
+        G: 'g,
+    {
+        let guard = map.raw.verify(self.guard);
+        let hash = map.raw.hasher.hash_one(&key);
+
+        self.writes.borrow_mut().push(Box::new(WriteEntry {
+            map,
+            guard,
+            hash,
+            stripe: stripe(map, hash),
+            key,
+            value,
+        }));
+    }
+
+    /// Attempts to commit the transaction, returning `true` if it succeeded.
+    fn commit(self) -> bool {
+        let reads = self.reads.into_inner();
+        let writes = self.writes.into_inner();
+
+        // Read-only transactions are validated on every read.
+        if writes.is_empty() {
+            return true;
+        }
+
+        // Lock all accessed keys, in a consistent order to avoid deadlocks.
+        let mut stripes = (reads.iter().map(|read| read.stripe()))
+            .chain(writes.iter().map(|write| write.stripe()))
+            .collect::<Vec<_>>();
+        stripes.sort_unstable();
+        stripes.dedup();
+
+        let _locks = stripes.into_iter().map(lock).collect::<Vec<_>>();
+
+        // Ensure none of the observed entries have changed.
+        if !reads.iter().all(|read| read.validate()) {
+            return false;
+        }
+
+        for write in writes {
+            write.apply();
+        }
+
+        true
+    }
+}
+
+impl<G> fmt::Debug for Transaction<'_, G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Transaction")
+            .field("reads", &self.reads.borrow().len())
+            .field("writes", &self.writes.borrow().len())
+            .finish()
+    }
+}
+
+/// The number of commit locks.
+const STRIPES: usize = 1024;
+
+/// Returns the commit lock stripe for a key in the given map.
+fn stripe<T>(map: &T, hash: u64) -> usize {
+    let addr = map as *const T as usize as u64;
+    (hash ^ addr.wrapping_mul(0x9E37_79B9_7F4A_7C15)) as usize & (STRIPES - 1)
+}
+
+/// Returns the `TypeId` of a type that is not necessarily `'static`.
+///
+/// Lifetimes are erased from type IDs, so this is equal for two types if they only differ
+/// by lifetimes.
+fn type_id<T: ?Sized>() -> TypeId {
+    trait NonStaticAny {
+        fn type_id(&self) -> TypeId
+        where
+            Self: 'static;
+    }
+
+    impl<T: ?Sized> NonStaticAny for PhantomData<T> {
+        fn type_id(&self) -> TypeId
+        where
+            Self: 'static,
+        {
+            TypeId::of::<T>()
+        }
+    }
+
+    let marker = PhantomData::<T>;
+
+    // Safety: `TypeId::of` does not depend on lifetimes, and the trait object is only used
+    // to call `type_id`, which does not access any data.
+    let marker =
+        unsafe { mem::transmute::<&dyn NonStaticAny, &(dyn NonStaticAny + 'static)>(&marker) };
+
+    marker.type_id()
+}
+
+/// Acquires the commit lock for the given stripe.
+fn lock(stripe: usize) -> MutexGuard<'static, ()> {
+    static LOCKS: OnceLock<Box<[Mutex<()>]>> = OnceLock::new();
+
+    let locks = LOCKS.get_or_init(|| (0..STRIPES).map(|_| Mutex::new(())).collect());
+
+    // The lock does not protect any data, so poisoning can be ignored.
+    locks[stripe].lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A read recorded by a transaction.
+trait Read {
+    /// Returns the commit lock stripe of the key.
+    fn stripe(&self) -> usize;
+
+    /// Returns `true` if the observed entry is still current.
+    fn validate(&self) -> bool;
+}
+
+/// The state of a key observed by a transaction.
+enum Observed<'g, K, V> {
+    /// The key was present with the given entry.
+    Present(&'g raw::Entry<K, V>),
+
+    /// The key was not present.
+    Absent(K),
+}
+
+/// A read of a key in a specific map.
+struct ReadEntry<'g, K, V, S, C, G>
+where
+    C: Borrow<Collector>,
+{
+    map: &'g HashMap<K, V, S, C>,
+    guard: &'g MapGuard<G>,
+    stripe: usize,
+    observed: Observed<'g, K, V>,
+}
+
+impl<K, V, S, C, G> Read for ReadEntry<'_, K, V, S, C, G>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+    G: Guard,
+{
+    fn stripe(&self) -> usize {
+        self.stripe
+    }
+
+    fn validate(&self) -> bool {
+        match self.observed {
+            // Entry allocations are not reclaimed while the guard is held, so pointer identity
+            // implies the entry has not been modified.
+            Observed::Present(entry) => self
+                .map
+                .raw
+                .get_entry(&entry.key, self.guard)
+                .is_some_and(|current| ptr::eq(current, entry)),
+
+            Observed::Absent(ref key) => self.map.raw.get_entry(key, self.guard).is_none(),
+        }
+    }
+}
+
+/// A write buffered by a transaction.
+trait Write {
+    /// Returns the commit lock stripe of the key.
+    fn stripe(&self) -> usize;
+
+    /// Returns the address of the map being written to.
+    fn map(&self) -> *const ();
+
+    /// Returns the type of the map being written to.
+    fn map_type(&self) -> TypeId;
+
+    /// Returns the hash of the key.
+    fn hash(&self) -> u64;
+
+    /// Applies the write to the map.
+    fn apply(self: Box<Self>);
+}
+
+/// A write to a key in a specific map.
+struct WriteEntry<'g, K, V, S, C, G>
+where
+    C: Borrow<Collector>,
+{
+    map: &'g HashMap<K, V, S, C>,
+    guard: &'g MapGuard<G>,
+    hash: u64,
+    stripe: usize,
+    key: K,
+
+    /// The value to insert, or `None` if the key should be removed.
+    value: Option<V>,
+}
+
+impl<K, V, S, C, G> Write for WriteEntry<'_, K, V, S, C, G>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+    C: Borrow<Collector>,
+    G: Guard,
+{
+    fn stripe(&self) -> usize {
+        self.stripe
+    }
+
+    fn map(&self) -> *const () {
+        self.map as *const _ as *const ()
+    }
+
+    fn map_type(&self) -> TypeId {
+        type_id::<HashMap<K, V, S, C>>()
+    }
+
+    fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    fn apply(self: Box<Self>) {
+        let WriteEntry {
+            map,
+            guard,
+            key,
+            value,
+            ..
+        } = *self;
+
+        match value {
+            Some(value) => {
+                map.raw.insert(key, value, true, guard);
+            }
+            None => {
+                map.raw.remove(&key, guard);
+            }
+        }
+    }
+}
@@ -1,12 +1,18 @@
 // Adapted from: https://github.com/jonhoo/flurry/blob/main/tests/basic.rs
 
-use papaya::{Compute, HashMap, OccupiedError, Operation};
+use papaya::{
+    CompareExchangeError, Compute, HashMap, HashedKey, MapObserver, OccupiedError, Operation,
+    ResizeMode,
+};
 
 use std::hash::{BuildHasher, BuildHasherDefault, Hasher};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Duration;
 
 mod common;
-use common::with_map;
+use common::{threads, with_map};
 
 #[test]
 fn new() {
@@ -357,6 +363,97 @@ fn get_or_insert_with() {
     });
 }
 
+#[test]
+fn get_or_init() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let map = map.pin();
+
+        assert_eq!(map.get_or_init(42, || 0), &0);
+        assert_eq!(map.get_or_init(42, || unreachable!()), &0);
+        assert_eq!(map.len(), 1);
+
+        // A panicking initializer allows the next caller to retry.
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            map.get_or_init(0, || panic!());
+        }));
+        assert!(result.is_err());
+        assert_eq!(map.get(&0), None);
+        assert_eq!(map.get_or_init(0, || 1), &1);
+    });
+}
+
+#[test]
+fn concurrent_get_or_init() {
+    const KEYS: usize = if cfg!(miri) { 8 } else { 64 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let inits = AtomicUsize::new(0);
+
+        let threads = threads();
+        let barrier = Barrier::new(threads);
+
+        thread::scope(|s| {
+            for _ in 0..threads {
+                let (map, inits, barrier) = (&map, &inits, &barrier);
+                s.spawn(move || {
+                    barrier.wait();
+                    let map = map.pin();
+                    for i in 0..KEYS {
+                        let value = map.get_or_init(i, || {
+                            inits.fetch_add(1, Ordering::Relaxed);
+                            thread::yield_now();
+                            i
+                        });
+
+                        assert_eq!(value, &i);
+                    }
+                });
+            }
+        });
+
+        // Every key was initialized exactly once.
+        assert_eq!(inits.load(Ordering::Relaxed), KEYS);
+    });
+}
+
+#[test]
+fn get_or_init_colliding() {
+    #[derive(Default)]
+    struct ZeroHasher;
+
+    impl Hasher for ZeroHasher {
+        fn finish(&self) -> u64 {
+            0
+        }
+
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    let map = HashMap::<usize, usize, _>::with_hasher(BuildHasherDefault::<ZeroHasher>::default());
+    let (claimed, loaded) = (Barrier::new(2), Barrier::new(2));
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            let map = map.pin();
+            let value = map.get_or_init(0, || {
+                claimed.wait();
+                loaded.wait();
+                0
+            });
+            assert_eq!(value, &0);
+        });
+
+        // Initialize a key with the same hash while the other key is being initialized.
+        claimed.wait();
+        assert_eq!(map.pin().get_or_init(1, || 1), &1);
+        loaded.wait();
+    });
+
+    assert_eq!(map.pin().get(&0), Some(&0));
+}
+
 #[test]
 fn compute() {
     with_map::<usize, usize>(|map| {
@@ -382,6 +479,8 @@ fn compute() {
                 Compute::Updated {
                     old: (&i, &1),
                     new: (&i, &2),
+                    old_version: 0,
+                    new_version: 0,
                 }
             );
             assert_eq!(map.compute(i, compute), Compute::Removed(&i, &2));
@@ -390,6 +489,250 @@ fn compute() {
     });
 }
 
+#[test]
+fn compare_exchange() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let map = map.pin();
+
+        assert_eq!(
+            map.compare_exchange(1, &0, 1),
+            Err(CompareExchangeError {
+                current: None,
+                new: 1
+            })
+        );
+        assert_eq!(map.get(&1), None);
+
+        for i in 0..100 {
+            map.insert(i, i);
+            assert_eq!(map.compare_exchange(i, &i, i + 1), Ok(&(i + 1)));
+            assert_eq!(
+                map.compare_exchange(i, &i, i + 2),
+                Err(CompareExchangeError {
+                    current: Some(&(i + 1)),
+                    new: i + 2
+                })
+            );
+            assert_eq!(map.get(&i), Some(&(i + 1)));
+        }
+        assert_eq!(map.len(), 100);
+    });
+}
+
+#[test]
+fn compare_exchange_remove() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let map = map.pin();
+
+        assert_eq!(map.compare_exchange_remove(&1, &1), Err(None));
+
+        for i in 0..100 {
+            map.insert(i, i);
+            assert_eq!(map.compare_exchange_remove(&i, &(i + 1)), Err(Some(&i)));
+            assert_eq!(map.get(&i), Some(&i));
+            assert_eq!(map.compare_exchange_remove(&i, &i), Ok(&i));
+            assert_eq!(map.get(&i), None);
+            assert_eq!(map.compare_exchange_remove(&i, &i), Err(None));
+        }
+        assert_eq!(map.len(), 0);
+    });
+}
+
+#[test]
+fn compare_exchange_dropped() {
+    with_map::<usize, Arc<usize>>(|map| {
+        let map = map();
+        let value = Arc::new(0);
+
+        map.pin().insert(0, value.clone());
+
+        // The value that was not inserted is returned.
+        let guard = map.guard();
+        let err = map
+            .compare_exchange(0, &Arc::new(1), value.clone(), &guard)
+            .unwrap_err();
+        assert_eq!(Arc::strong_count(&value), 3);
+        drop(err);
+        drop(guard);
+        assert_eq!(Arc::strong_count(&value), 2);
+
+        map.pin()
+            .compare_exchange(0, &Arc::new(0), value.clone())
+            .unwrap();
+        drop(map);
+        assert_eq!(Arc::strong_count(&value), 1);
+    });
+}
+
+#[test]
+fn concurrent_compare_exchange() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let map = Arc::new(map);
+
+        {
+            let guard = map.guard();
+            for i in 0..64 {
+                map.insert(i, i, &guard);
+            }
+        }
+
+        let increment = |map: Arc<HashMap<usize, usize>>| {
+            move || {
+                let guard = map.guard();
+                for i in 0..64 {
+                    let mut current = *map.get(&i, &guard).unwrap();
+                    loop {
+                        match map.compare_exchange(i, &current, current + 1, &guard) {
+                            Ok(_) => break,
+                            Err(err) => current = *err.current.unwrap(),
+                        }
+                    }
+                }
+            }
+        };
+
+        let t1 = std::thread::spawn(increment(map.clone()));
+        let t2 = std::thread::spawn(increment(map.clone()));
+
+        t1.join().unwrap();
+        t2.join().unwrap();
+
+        let guard = map.guard();
+        for i in 0..64 {
+            assert_eq!(map.get(&i, &guard), Some(&(i + 2)));
+        }
+    });
+}
+
+#[test]
+fn get_entry() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let guard = map.guard();
+        assert!(map.get_entry(&1, &guard).is_none());
+
+        for i in 0..100 {
+            map.insert(i, i + 1, &guard);
+        }
+
+        for i in 0..100 {
+            let entry = map.get_entry(&i, &guard).unwrap();
+            assert_eq!((entry.key(), entry.value()), (&i, &(i + 1)));
+        }
+        assert!(map.get_entry(&100, &guard).is_none());
+    });
+}
+
+#[test]
+fn replace_if_current() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let guard = map.guard();
+
+        for i in 0..100 {
+            map.insert(i, i, &guard);
+        }
+
+        for i in 0..100 {
+            let entry = map.get_entry(&i, &guard).unwrap();
+            let new = map.replace_if_current(entry, i + 1, &guard).unwrap();
+            assert_eq!((new.key(), new.value()), (&i, &(i + 1)));
+
+            // The original entry is no longer current.
+            assert_eq!(
+                map.replace_if_current(entry, i + 2, &guard).unwrap_err(),
+                i + 2
+            );
+
+            // Reinserting an equal value creates a new entry.
+            map.insert(i, i + 1, &guard);
+            assert_eq!(
+                map.replace_if_current(new, i + 2, &guard).unwrap_err(),
+                i + 2
+            );
+            assert_eq!(map.get(&i, &guard), Some(&(i + 1)));
+        }
+
+        // The entry was removed.
+        let entry = map.get_entry(&0, &guard).unwrap();
+        map.remove(&0, &guard);
+        assert_eq!(map.replace_if_current(entry, 0, &guard).unwrap_err(), 0);
+        assert_eq!(map.get(&0, &guard), None);
+        assert_eq!(map.len(), 99);
+    });
+}
+
+#[test]
+fn remove_if_current() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let guard = map.guard();
+
+        for i in 0..100 {
+            map.insert(i, i, &guard);
+        }
+
+        for i in 0..100 {
+            let entry = map.get_entry(&i, &guard).unwrap();
+            map.insert(i, i, &guard);
+            assert!(!map.remove_if_current(entry, &guard));
+            assert_eq!(map.get(&i, &guard), Some(&i));
+
+            let entry = map.get_entry(&i, &guard).unwrap();
+            assert!(map.remove_if_current(entry, &guard));
+            assert_eq!(map.get(&i, &guard), None);
+            assert!(!map.remove_if_current(entry, &guard));
+        }
+        assert_eq!(map.len(), 0);
+    });
+}
+
+#[test]
+fn concurrent_replace_if_current() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let map = Arc::new(map);
+
+        {
+            let guard = map.guard();
+            for i in 0..64 {
+                map.insert(i, i, &guard);
+            }
+        }
+
+        let increment = |map: Arc<HashMap<usize, usize>>| {
+            move || {
+                for i in 0..64 {
+                    loop {
+                        let guard = map.guard();
+                        let entry = map.get_entry(&i, &guard).unwrap();
+                        if map
+                            .replace_if_current(entry, entry.value() + 1, &guard)
+                            .is_ok()
+                        {
+                            break;
+                        }
+                    }
+                }
+            }
+        };
+
+        let t1 = std::thread::spawn(increment(map.clone()));
+        let t2 = std::thread::spawn(increment(map.clone()));
+
+        t1.join().unwrap();
+        t2.join().unwrap();
+
+        let guard = map.guard();
+        for i in 0..64 {
+            assert_eq!(map.get(&i, &guard), Some(&(i + 2)));
+        }
+    });
+}
+
 #[test]
 fn concurrent_insert() {
     with_map::<usize, usize>(|map| {
@@ -850,6 +1193,960 @@ fn retain_some() {
     });
 }
 
+#[test]
+fn shrink_to_fit() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+        map.pin().retain(|k, _| *k < 10);
+        map.pin().shrink_to_fit();
+        assert_eq!(map.len(), 10);
+        for i in 0..LEN {
+            assert_eq!(map.pin().get(&i), (i < 10).then_some(&i));
+        }
+
+        // Shrinking an empty map.
+        map.pin().clear();
+        map.pin().shrink_to_fit();
+        assert!(map.is_empty());
+        map.pin().insert(1, 1);
+        assert_eq!(map.pin().get(&1), Some(&1));
+    });
+}
+
+#[test]
+fn shrink_to() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+        map.pin().shrink_to(0);
+        assert_eq!(map.len(), LEN);
+        for i in 0..LEN {
+            assert_eq!(map.pin().get(&i), Some(&i));
+        }
+
+        map.pin().retain(|k, _| *k < LEN / 2);
+        map.pin().shrink_to(LEN);
+        for i in 0..LEN {
+            assert_eq!(map.pin().get(&i), (i < LEN / 2).then_some(&i));
+        }
+    });
+}
+
+#[test]
+fn shrink_after_remove() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 1 << 14 };
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        for _ in 0..3 {
+            for i in 0..LEN {
+                assert_eq!(map.pin().insert(i, i + 1), None);
+            }
+            for i in 0..LEN {
+                if i % 32 != 0 {
+                    assert_eq!(map.pin().remove(&i), Some(&(i + 1)));
+                }
+            }
+            for i in 0..LEN {
+                let expected = i + 1;
+                assert_eq!(map.pin().get(&i), (i % 32 == 0).then_some(&expected));
+            }
+            assert_eq!(map.len(), LEN / 32);
+            map.pin().clear();
+        }
+    });
+}
+
+#[test]
+fn shrink_capacity() {
+    // Tables are never resized in stress tests.
+    if cfg!(papaya_stress) {
+        return;
+    }
+
+    for resize_mode in [ResizeMode::Blocking, ResizeMode::Incremental(64)] {
+        let map = HashMap::builder().resize_mode(resize_mode).build();
+        for i in 0..4096 {
+            map.pin().insert(i, i);
+        }
+
+        let grown = map.pin().stats().capacity;
+
+        // Removing most entries automatically shrinks the table.
+        for i in 64..4096 {
+            assert!(map.pin().remove(&i).is_some());
+        }
+
+        // Complete any in-progress incremental resizes.
+        assert_eq!(map.pin().iter().count(), 64);
+        assert!(map.pin().stats().capacity < grown);
+
+        // Explicitly shrink the table to the smallest capacity that fits the entries.
+        map.pin().shrink_to(0);
+        assert_eq!(map.pin().stats().capacity, 128);
+
+        for i in 0..4096 {
+            assert_eq!(map.pin().contains_key(&i), i < 64);
+        }
+    }
+}
+
+#[test]
+fn get_mut() {
+    with_map::<usize, usize>(|map| {
+        let mut map = map();
+        assert_eq!(map.get_mut(&1), None);
+        map.pin().insert(1, 1);
+        *map.get_mut(&1).unwrap() += 1;
+        assert_eq!(map.pin().get(&1), Some(&2));
+        assert_eq!(map.get_mut(&2), None);
+    });
+}
+
+#[test]
+fn insert_mut() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_map::<usize, usize>(|map| {
+        let mut map = map();
+        for i in 0..LEN {
+            assert_eq!(map.insert_mut(i, i), None);
+        }
+        for i in 0..LEN {
+            assert_eq!(map.insert_mut(i, i + 1), Some(i));
+        }
+        assert_eq!(map.len(), LEN);
+        for i in 0..LEN {
+            assert_eq!(map.pin().get(&i), Some(&(i + 1)));
+        }
+    });
+}
+
+#[test]
+fn remove_owned() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_map::<usize, usize>(|map| {
+        let mut map = map();
+        assert_eq!(map.remove_owned(&0), None);
+
+        // Leave the map mid-resize.
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+        for i in (0..LEN).step_by(2) {
+            assert_eq!(map.remove_owned(&i), Some((i, i)));
+            assert_eq!(map.remove_owned(&i), None);
+        }
+        assert_eq!(map.len(), LEN / 2);
+        for i in 0..LEN {
+            assert_eq!(map.pin().get(&i), (i % 2 == 1).then_some(&i));
+        }
+    });
+}
+
+#[test]
+fn iter_mut() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_map::<usize, usize>(|map| {
+        let mut map = map();
+        assert_eq!(map.iter_mut().count(), 0);
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+        for (k, v) in map.iter_mut() {
+            assert_eq!(k, v);
+            *v += 1;
+        }
+        for v in map.values_mut() {
+            *v *= 2;
+        }
+        for i in 0..LEN {
+            assert_eq!(map.pin().get(&i), Some(&((i + 1) * 2)));
+        }
+    });
+}
+
+#[test]
+fn drain() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_map::<usize, Arc<usize>>(|map| {
+        let mut map = map();
+        assert_eq!(map.drain().count(), 0);
+
+        let value = Arc::new(0);
+        for i in 0..LEN {
+            map.pin().insert(i, value.clone());
+        }
+        let mut drained = map.drain().map(|(k, _)| k).collect::<Vec<_>>();
+        drained.sort_unstable();
+        assert_eq!(drained, (0..LEN).collect::<Vec<_>>());
+        assert!(map.is_empty());
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        // Dropping a partially consumed iterator drains the rest.
+        for i in 0..LEN {
+            map.insert_mut(i, value.clone());
+        }
+        assert_eq!(map.drain().take(10).count(), 10);
+        assert!(map.is_empty());
+        assert_eq!(map.pin().iter().count(), 0);
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        // The map is still usable.
+        for i in 0..LEN {
+            assert_eq!(map.insert_mut(i, value.clone()), None);
+        }
+        assert_eq!(map.len(), LEN);
+    });
+}
+
+#[test]
+fn into_iter() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_map::<usize, Arc<usize>>(|new_map| {
+        let value = Arc::new(0);
+
+        let map = new_map();
+        assert_eq!(map.into_iter().count(), 0);
+
+        // Leave the map mid-resize, with entries removed and replaced in the new table.
+        let map = new_map();
+        for i in 0..LEN {
+            map.pin().insert(i, value.clone());
+        }
+        for i in (0..LEN).step_by(3) {
+            map.pin().remove(&i);
+        }
+        for i in (1..LEN).step_by(3) {
+            map.pin().insert(i, value.clone());
+        }
+
+        let expected = (0..LEN).filter(|i| i % 3 != 0).collect::<Vec<_>>();
+        let iter = map.into_iter();
+        assert_eq!(iter.size_hint(), (expected.len(), Some(expected.len())));
+        let mut keys = iter.map(|(k, _)| k).collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(keys, expected);
+        assert_eq!(Arc::strong_count(&value), 1);
+
+        // Dropping a partially consumed iterator drops the rest.
+        let map = new_map();
+        for i in 0..LEN {
+            map.pin().insert(i, value.clone());
+        }
+        let mut iter = map.into_iter();
+        assert!(iter.next().is_some());
+        drop(iter);
+        assert_eq!(Arc::strong_count(&value), 1);
+    });
+}
+
+#[test]
+fn get_many() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        map.pin().insert(1, 10);
+        map.pin().insert(2, 20);
+
+        let guard = map.guard();
+        assert_eq!(
+            map.get_many([&1, &2, &3], &guard),
+            [Some(&10), Some(&20), None]
+        );
+        assert_eq!(map.get_many([&2, &2], &guard), [Some(&20), Some(&20)]);
+        assert_eq!(map.get_many::<usize, 0>([], &guard), []);
+        assert_eq!(map.pin().get_many([&3, &1]), [None, Some(&10)]);
+    });
+}
+
+#[test]
+fn get_batch() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        for i in 0..LEN {
+            map.pin().insert(i, i + 1);
+        }
+
+        let keys = (0..LEN * 2).collect::<Vec<_>>();
+        let guard = map.guard();
+        let values = map.get_batch(&keys, &guard);
+        assert_eq!(values.len(), LEN * 2);
+        for (i, value) in values.into_iter().enumerate() {
+            if i < LEN {
+                assert_eq!(value, Some(&(i + 1)));
+            } else {
+                assert_eq!(value, None);
+            }
+        }
+
+        assert!(map.pin().get_batch(&[] as &[usize]).is_empty());
+    });
+}
+
+#[test]
+fn insert_batch() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        map.pin().insert_batch((0..LEN).map(|i| (i, i)));
+        assert_eq!(map.len(), LEN);
+
+        // Existing values are replaced.
+        map.pin()
+            .insert_batch((0..LEN).step_by(2).map(|i| (i, i + 1)));
+        assert_eq!(map.len(), LEN);
+
+        let guard = map.guard();
+        for i in 0..LEN {
+            let expected = if i % 2 == 0 { i + 1 } else { i };
+            assert_eq!(map.get(&i, &guard), Some(&expected));
+        }
+    });
+}
+
+#[test]
+fn versioned() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+    for resize_mode in [ResizeMode::Blocking, ResizeMode::Incremental(1)] {
+        let map = HashMap::<usize, usize>::builder()
+            .resize_mode(resize_mode)
+            .versioned(true)
+            .build();
+        let map = map.pin();
+
+        // Insert enough entries to trigger resizes, so that entries are copied.
+        let mut versions = Vec::new();
+        for i in 0..LEN {
+            map.insert(i, i);
+            let (_, _, version) = map.get_versioned(&i).unwrap();
+            assert!(versions.last().map_or(true, |&last| version > last));
+            versions.push(version);
+        }
+
+        // Copied entries retain their version.
+        for (i, &version) in versions.iter().enumerate() {
+            assert_eq!(map.get_versioned(&i), Some((&i, &i, version)));
+        }
+
+        let mut last = versions[LEN - 1];
+        for i in 0..LEN {
+            // Replacing an entry with an equal value still changes the version.
+            map.insert(i, i);
+            let (_, _, version) = map.get_versioned(&i).unwrap();
+            assert!(version > last);
+            last = version;
+
+            match map.compute(i, |_| Operation::Insert::<_, ()>(i + 1)) {
+                Compute::Updated {
+                    old_version,
+                    new_version,
+                    ..
+                } => {
+                    assert_eq!(old_version, last);
+                    assert!(new_version > old_version);
+                    last = new_version;
+                }
+                _ => panic!("expected update"),
+            }
+
+            // Reinserted entries receive a newer version.
+            map.remove(&i);
+            assert_eq!(map.get_versioned(&i), None);
+            map.insert(i, i);
+            let (_, _, version) = map.get_versioned(&i).unwrap();
+            assert!(version > last);
+            last = version;
+        }
+
+        // Cloned maps preserve versioning.
+        let other = map.map().clone();
+        let (_, _, version) = other.pin().get_versioned(&0).unwrap();
+        assert_ne!(version, 0);
+    }
+}
+
+#[test]
+fn versioned_mut() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+    for resize_mode in [ResizeMode::Blocking, ResizeMode::Incremental(1)] {
+        let mut map = HashMap::<usize, usize>::builder()
+            .resize_mode(resize_mode)
+            .versioned(true)
+            .build();
+
+        for i in 0..LEN {
+            map.insert_mut(i, i);
+        }
+
+        let (_, _, v1) = map.pin().get_versioned(&0).unwrap();
+        map.insert_mut(0, 1);
+        let (_, _, v2) = map.pin().get_versioned(&0).unwrap();
+        assert!(v2 > v1);
+
+        *map.get_mut(&0).unwrap() += 1;
+        let (_, _, v3) = map.pin().get_versioned(&0).unwrap();
+        assert!(v3 > v2);
+
+        for (_, value) in map.iter_mut() {
+            *value += 1;
+        }
+        let (_, _, v4) = map.pin().get_versioned(&0).unwrap();
+        assert!(v4 > v3);
+
+        assert_eq!(map.remove_owned(&0), Some((0, 3)));
+        assert_eq!(map.drain().count(), LEN - 1);
+
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+        assert_eq!(map.into_iter().count(), LEN);
+    }
+}
+
+#[test]
+fn unversioned() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let map = map.pin();
+        map.insert(0, 0);
+        assert_eq!(map.get_versioned(&0), Some((&0, &0, 0)));
+        map.insert(0, 1);
+        assert_eq!(map.get_versioned(&0), Some((&0, &1, 0)));
+        assert_eq!(map.get_versioned(&1), None);
+    });
+}
+
+#[test]
+fn modification_count() {
+    for resize_mode in [ResizeMode::Blocking, ResizeMode::Incremental(1)] {
+        let mut map = HashMap::<usize, usize>::builder()
+            .resize_mode(resize_mode)
+            .versioned(true)
+            .build();
+        assert_eq!(map.modification_count(), 0);
+
+        map.pin().insert(0, 0);
+        map.pin().insert(0, 1);
+        map.pin().update(0, |v| v + 1);
+        assert_eq!(map.modification_count(), 3);
+
+        // Failed operations are not modifications.
+        map.pin().try_insert(0, 0).unwrap_err();
+        map.pin().remove(&1);
+        map.pin().update(1, |v| v + 1);
+        assert_eq!(map.modification_count(), 3);
+
+        map.pin().remove(&0);
+        assert_eq!(map.modification_count(), 4);
+
+        map.pin().insert(0, 0);
+        map.pin().insert(1, 1);
+        map.pin().clear();
+        assert_eq!(map.modification_count(), 8);
+
+        map.insert_mut(0, 0);
+        map.remove_owned(&0);
+        assert_eq!(map.modification_count(), 10);
+
+        // Modifications are never reset.
+        map.pin().insert(0, 0);
+        map.pin().insert(1, 1);
+        map.drain().for_each(drop);
+        assert_eq!(map.modification_count(), 14);
+        assert!(map.is_empty());
+    }
+
+    // Modifications are only counted by versioned maps.
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        map.pin().insert(0, 0);
+        map.pin().remove(&0);
+        assert_eq!(map.modification_count(), 0);
+    });
+}
+
+#[test]
+fn hashed() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let map = map.pin();
+
+        let key = map.hash_key(1);
+        assert_eq!(key.hash(), map.hash_key(&1_usize).hash());
+        assert_eq!(key.as_ref().hash(), key.hash());
+        assert_eq!(map.get_hashed(key.as_ref()), None);
+        assert!(!map.contains_key_hashed(key.as_ref()));
+
+        assert_eq!(map.insert_hashed(key, 10), None);
+        assert_eq!(map.get(&1), Some(&10));
+        assert_eq!(map.get_hashed(key.as_ref()), Some(&10));
+        assert_eq!(map.get_key_value_hashed(key.as_ref()), Some((&1, &10)));
+
+        let result = map.compute_hashed(key, |entry| match entry {
+            Some((_, value)) => Operation::Insert::<_, ()>(value + 1),
+            None => Operation::Abort(()),
+        });
+        assert!(matches!(result, Compute::Updated { .. }));
+        assert_eq!(map.get(&1), Some(&11));
+
+        assert_eq!(map.remove_hashed(key.as_ref()), Some(&11));
+        assert_eq!(map.remove_hashed(key.as_ref()), None);
+        assert!(!map.contains_key(&1));
+    });
+}
+
+#[test]
+fn hashed_wrong_hash() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let map = map.pin();
+
+        // Wrong hashes are a logic error, but must not cause undefined behavior, including
+        // across resizes.
+        for i in 0..LEN {
+            map.insert_hashed(HashedKey::new(i, i as u64), i);
+            map.insert(i, i + 1);
+            map.compute_hashed(HashedKey::new(i, !(i as u64)), |_| {
+                Operation::Insert::<_, ()>(i)
+            });
+        }
+
+        for i in 0..LEN {
+            let _ = map.get_hashed(HashedKey::new(&i, i as u64));
+            let _ = map.remove_hashed(HashedKey::new(&i, !(i as u64)));
+        }
+
+        for (key, value) in map.iter() {
+            assert!(*value == *key || *value == *key + 1);
+        }
+
+        map.clear();
+        assert!(map.is_empty());
+    });
+}
+
+#[test]
+fn cache_hashes() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    // A hasher that counts the number of keys hashed.
+    #[derive(Clone, Default)]
+    struct CountingHasher(Arc<AtomicUsize>);
+
+    impl BuildHasher for CountingHasher {
+        type Hasher = std::collections::hash_map::DefaultHasher;
+
+        fn build_hasher(&self) -> Self::Hasher {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Default::default()
+        }
+    }
+
+    for cache_hashes in [false, true] {
+        for resize_mode in [ResizeMode::Blocking, ResizeMode::Incremental(1)] {
+            let hasher = CountingHasher::default();
+            let map = HashMap::builder()
+                .hasher(hasher.clone())
+                .resize_mode(resize_mode)
+                .cache_hashes(cache_hashes)
+                .build();
+            let map = map.pin();
+
+            for i in 0..LEN {
+                assert_eq!(map.insert(i.to_string(), i), None);
+            }
+
+            // Keys are only rehashed during resizes if hashes are not cached.
+            let hashes = hasher.0.load(Ordering::Relaxed);
+            if cache_hashes {
+                assert_eq!(hashes, LEN);
+            } else {
+                assert!(hashes > LEN);
+            }
+
+            for i in 0..LEN {
+                assert_eq!(map.get(&i.to_string()), Some(&i));
+            }
+
+            for i in (0..LEN).step_by(2) {
+                assert_eq!(map.remove(&i.to_string()), Some(&i));
+            }
+
+            for i in 0..LEN {
+                let expected = (i % 2 == 1).then_some(i);
+                assert_eq!(map.get(&i.to_string()).copied(), expected);
+            }
+
+            assert_eq!(map.len(), LEN / 2);
+
+            // Scans use the cached hashes.
+            let hashes = hasher.0.load(Ordering::Relaxed);
+            let (mut cursor, mut scanned) = (0, 0);
+            loop {
+                let (next, entries) = map.scan(cursor, 64);
+                scanned += entries.len();
+
+                cursor = next;
+                if cursor == 0 {
+                    break;
+                }
+            }
+
+            assert_eq!(scanned, LEN / 2);
+            if cache_hashes {
+                assert_eq!(hasher.0.load(Ordering::Relaxed), hashes);
+            }
+        }
+    }
+}
+
+#[test]
+fn cache_hashes_replace_if_current() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    let plain = HashMap::new();
+    let map = HashMap::builder()
+        .resize_mode(ResizeMode::Blocking)
+        .cache_hashes(true)
+        .build();
+
+    let (plain_guard, guard) = (plain.guard(), map.guard());
+    plain.insert(0, 0, &plain_guard);
+    map.insert(0, 0, &guard);
+
+    // An entry from a map without cached hashes is never current.
+    let foreign = plain.get_entry(&0, &plain_guard).unwrap();
+    assert_eq!(map.replace_if_current(foreign, 1, &guard).unwrap_err(), 1);
+    assert!(!map.remove_if_current(foreign, &guard));
+    assert_eq!(map.get(&0, &guard), Some(&0));
+
+    // Replaced entries are found after a resize, which relies on their cached hash.
+    let entry = map.get_entry(&0, &guard).unwrap();
+    map.replace_if_current(entry, 1, &guard).unwrap();
+    for i in 1..LEN {
+        map.insert(i, i, &guard);
+    }
+    assert_eq!(map.get(&0, &guard), Some(&1));
+}
+
+#[test]
+fn stats() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+
+        let stats = map.pin().stats();
+        assert_eq!(stats.len, 0);
+        assert_eq!(stats.live, 0);
+        assert_eq!(stats.promotions, 0);
+
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+
+        let stats = map.pin().stats();
+        assert_eq!(stats.len, LEN);
+        assert!(stats.promotions > 0);
+        assert!(stats.live + stats.tombstones <= stats.capacity);
+        assert_eq!(stats.probe_lengths.len(), stats.probe_limit + 1);
+        assert_eq!(stats.probe_lengths.iter().sum::<usize>(), stats.live);
+        if let Some(resize) = &stats.resize {
+            assert!(stats.depth > 0);
+            assert!(resize.copied <= stats.capacity);
+        } else {
+            assert_eq!(stats.depth, 0);
+            assert_eq!(stats.live, LEN);
+        }
+
+        for i in 0..LEN / 2 {
+            map.pin().remove(&i);
+        }
+
+        let stats = map.pin().stats();
+        assert_eq!(stats.len, LEN / 2);
+        if stats.resize.is_none() {
+            assert_eq!(stats.live, LEN / 2);
+            assert!(stats.tombstones >= LEN / 2);
+        }
+    });
+}
+
+#[test]
+fn observer() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    #[derive(Default)]
+    struct Events {
+        started: AtomicUsize,
+        promoted: AtomicUsize,
+        copied: AtomicUsize,
+    }
+
+    impl MapObserver for Events {
+        fn resize_started(&self, _capacity: usize, _next_capacity: usize) {
+            self.started.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn promoted(&self, _capacity: usize, _elapsed: Duration) {
+            self.promoted.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn copied(&self, copied: usize, _elapsed: Duration) {
+            self.copied.fetch_add(copied, Ordering::Relaxed);
+        }
+    }
+
+    for resize_mode in [ResizeMode::Blocking, ResizeMode::Incremental(1)] {
+        let events = Arc::new(Events::default());
+        let map = HashMap::builder()
+            .resize_mode(resize_mode)
+            .observer(events.clone())
+            .build();
+
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+
+        let stats = map.pin().stats();
+        let started = events.started.load(Ordering::Relaxed);
+        let promoted = events.promoted.load(Ordering::Relaxed);
+
+        assert!(promoted > 0);
+        assert_eq!(promoted as u64, stats.promotions);
+        assert!(started >= promoted);
+        assert!(events.copied.load(Ordering::Relaxed) > 0);
+
+        // The observer is shared with clones of the map.
+        let clone = map.clone();
+        for i in LEN..LEN * 4 {
+            clone.pin().insert(i, i);
+        }
+        assert!(events.promoted.load(Ordering::Relaxed) > promoted);
+    }
+}
+
+#[test]
+fn iter_partition() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        assert_eq!(map.pin().iter_partition(0, 4).count(), 0);
+
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+
+        // The partitions cover every entry exactly once, including when there are more
+        // partitions than slots in the table.
+        for of in [1, 2, 3, 7, 64, LEN * 4] {
+            let map = map.pin();
+            let mut seen = vec![false; LEN];
+            for i in 0..of {
+                for (&key, &value) in map.iter_partition(i, of) {
+                    assert_eq!(key, value);
+                    assert!(!seen[key]);
+                    seen[key] = true;
+                }
+            }
+
+            assert!(seen.iter().all(|&seen| seen));
+        }
+    });
+}
+
+#[test]
+#[should_panic]
+fn iter_partition_out_of_range() {
+    let map = HashMap::<usize, usize>::new();
+    let _ = map.pin().iter_partition(4, 4);
+}
+
+#[test]
+fn scan() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        assert_eq!(map.pin().scan(0, 16), (0, Vec::new()));
+
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+
+        // Every entry is returned exactly once when the map is not modified.
+        let mut seen = vec![false; LEN];
+        let mut cursor = 0;
+        loop {
+            let map = map.pin();
+            let (next, entries) = map.scan(cursor, 16);
+            assert!(next == 0 || entries.len() >= 16);
+
+            for (&key, &value) in entries {
+                assert_eq!(key, value);
+                assert!(!seen[key]);
+                seen[key] = true;
+            }
+
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+
+        assert!(seen.iter().all(|&seen| seen));
+    });
+}
+
+#[test]
+fn scan_resize() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+
+        let mut seen = vec![false; LEN];
+        let mut cursor = 0;
+        let mut batch = 0;
+        loop {
+            let (next, entries) = {
+                let map = map.pin();
+                let (next, entries) = map.scan(cursor, 8);
+                (next, entries.iter().map(|(&k, _)| k).collect::<Vec<_>>())
+            };
+
+            for key in entries {
+                if key < LEN {
+                    seen[key] = true;
+                }
+            }
+
+            // Alternate between growing and shrinking the table between batches.
+            batch += 1;
+            if batch % 2 == 0 {
+                for i in LEN..LEN * 2 {
+                    map.pin().insert(i, i);
+                }
+            } else {
+                for i in LEN..LEN * 2 {
+                    map.pin().remove(&i);
+                }
+                map.pin().shrink_to_fit();
+            }
+
+            cursor = next;
+            if cursor == 0 {
+                break;
+            }
+        }
+
+        // Every entry present for the entire scan is returned at least once.
+        assert!(seen.iter().all(|&seen| seen));
+    });
+}
+
+#[test]
+fn scan_concurrent() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+
+        let threads = threads();
+        let barrier = Barrier::new(threads + 1);
+
+        thread::scope(|s| {
+            for t in 0..threads {
+                let (map, barrier) = (&map, &barrier);
+                s.spawn(move || {
+                    barrier.wait();
+                    for i in 0..LEN {
+                        let key = LEN * (t + 1) + i;
+                        map.pin().insert(key, key);
+                        if i % 3 == 0 {
+                            map.pin().remove(&key);
+                        }
+                    }
+                });
+            }
+
+            barrier.wait();
+
+            let mut seen = vec![false; LEN];
+            let mut cursor = 0;
+            loop {
+                let map = map.pin();
+                let (next, entries) = map.scan(cursor, 32);
+                for (&key, &value) in entries {
+                    assert_eq!(key, value);
+                    if key < LEN {
+                        seen[key] = true;
+                    }
+                }
+
+                cursor = next;
+                if cursor == 0 {
+                    break;
+                }
+            }
+
+            assert!(seen.iter().all(|&seen| seen));
+        });
+    });
+}
+
+#[test]
+fn iter_refreshing() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        for i in 0..LEN {
+            map.pin().insert(i, i);
+        }
+
+        let mut seen = vec![false; LEN];
+        map.pin().iter_refreshing(16, |&key, &value| {
+            assert_eq!(key, value);
+            assert!(!seen[key]);
+            seen[key] = true;
+        });
+        assert!(seen.iter().all(|&seen| seen));
+
+        // Resize the table during iteration.
+        let mut seen = vec![false; LEN];
+        let mut visited = 0;
+        map.pin_owned().iter_refreshing(8, |&key, _| {
+            if key < LEN {
+                seen[key] = true;
+            }
+
+            visited += 1;
+            if visited == LEN / 2 {
+                for i in LEN..LEN * 4 {
+                    map.pin().insert(i, i);
+                }
+            }
+        });
+        assert!(seen.iter().all(|&seen| seen));
+    });
+}
+
 #[test]
 fn mixed() {
     const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
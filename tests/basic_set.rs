@@ -458,6 +458,26 @@ fn iter() {
     });
 }
 
+#[test]
+fn iter_partition() {
+    with_set::<usize>(|set| {
+        let set = set();
+        let len = if cfg!(miri) { 100 } else { 1024 };
+        for i in 0..len {
+            assert!(set.pin().insert(i));
+        }
+
+        for of in [1, 3, 64, len * 4] {
+            let set = set.pin();
+            let mut got: Vec<_> = (0..of)
+                .flat_map(|i| set.iter_partition(i, of).copied())
+                .collect();
+            got.sort();
+            assert_eq!(got, (0..len).collect::<Vec<_>>());
+        }
+    });
+}
+
 #[test]
 fn retain_empty() {
     with_set::<usize>(|set| {
@@ -506,6 +526,76 @@ fn retain_some() {
     });
 }
 
+#[test]
+fn shrink_to_fit() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_set::<usize>(|set| {
+        let set = set();
+        for i in 0..LEN {
+            set.pin().insert(i);
+        }
+        set.pin().retain(|&k| k < 10);
+        set.pin().shrink_to_fit();
+        assert_eq!(set.len(), 10);
+        for i in 0..LEN {
+            assert_eq!(set.pin().contains(&i), i < 10);
+        }
+    });
+}
+
+#[test]
+fn insert_mut_remove_owned() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_set::<usize>(|set| {
+        let mut set = set();
+        for i in 0..LEN {
+            assert!(set.insert_mut(i));
+            assert!(!set.insert_mut(i));
+        }
+        for i in (0..LEN).step_by(2) {
+            assert_eq!(set.remove_owned(&i), Some(i));
+            assert_eq!(set.remove_owned(&i), None);
+        }
+        assert_eq!(set.len(), LEN / 2);
+        for i in 0..LEN {
+            assert_eq!(set.pin().contains(&i), i % 2 == 1);
+        }
+    });
+}
+
+#[test]
+fn drain() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_set::<usize>(|set| {
+        let mut set = set();
+        for i in 0..LEN {
+            set.pin().insert(i);
+        }
+        let mut drained = set.drain().collect::<Vec<_>>();
+        drained.sort_unstable();
+        assert_eq!(drained, (0..LEN).collect::<Vec<_>>());
+        assert!(set.is_empty());
+        assert!(set.pin().insert(0));
+    });
+}
+
+#[test]
+fn into_iter() {
+    const LEN: usize = if cfg!(miri) { 256 } else { 4096 };
+    with_set::<usize>(|set| {
+        let set = set();
+        for i in 0..LEN {
+            set.pin().insert(i);
+        }
+        for i in (0..LEN).step_by(2) {
+            set.pin().remove(&i);
+        }
+        let mut keys = set.into_iter().collect::<Vec<_>>();
+        keys.sort_unstable();
+        assert_eq!(keys, (1..LEN).step_by(2).collect::<Vec<_>>());
+    });
+}
+
 #[test]
 fn mixed() {
     const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
@@ -0,0 +1,280 @@
+use papaya::{Cache, Compute, Operation};
+
+use std::sync::Barrier;
+use std::thread;
+
+mod common;
+use common::threads;
+
+#[test]
+fn bounded() {
+    const CAPACITY: usize = if cfg!(miri) { 16 } else { 256 };
+
+    let cache = Cache::new(CAPACITY);
+    let cache = cache.pin();
+
+    for i in 0..CAPACITY * 8 {
+        assert_eq!(cache.insert(i, i), None);
+        assert!(cache.len() <= CAPACITY);
+    }
+
+    assert_eq!(cache.len(), CAPACITY);
+    assert_eq!(cache.iter().count(), CAPACITY);
+    for (key, value) in cache.iter() {
+        assert_eq!(key, value);
+    }
+}
+
+#[test]
+fn replace() {
+    let cache = Cache::new(4);
+    let cache = cache.pin();
+
+    for i in 0..4 {
+        cache.insert(i, i);
+    }
+
+    // Replacing an entry does not evict.
+    for i in 0..4 {
+        assert_eq!(cache.insert(i, i + 1), Some(&i));
+    }
+
+    assert_eq!(cache.len(), 4);
+    for i in 0..4 {
+        assert_eq!(cache.get(&i), Some(&(i + 1)));
+    }
+}
+
+#[test]
+fn scan_resistance() {
+    const CAPACITY: usize = if cfg!(miri) { 16 } else { 256 };
+
+    let cache = Cache::new(CAPACITY);
+    let cache = cache.pin();
+
+    // Insert a hot set of entries that are read frequently.
+    let hot = CAPACITY / 4;
+    for i in 0..hot {
+        cache.insert(i, i);
+    }
+
+    // Scan over a large number of entries that are never read.
+    for i in hot..CAPACITY * 16 {
+        for j in 0..hot {
+            assert_eq!(cache.get(&j), Some(&j));
+        }
+
+        cache.insert(i, i);
+    }
+
+    assert_eq!(cache.len(), CAPACITY);
+}
+
+#[test]
+fn remove_and_clear() {
+    let cache = Cache::new(8);
+    let cache = cache.pin();
+
+    for i in 0..8 {
+        cache.insert(i, i);
+    }
+
+    assert_eq!(cache.remove(&0), Some(&0));
+    assert_eq!(cache.remove(&0), None);
+    assert!(!cache.contains_key(&0));
+    assert_eq!(cache.len(), 7);
+
+    // The removed entry frees up space.
+    cache.insert(8, 8);
+    assert_eq!(cache.len(), 8);
+    for i in 1..=8 {
+        assert_eq!(cache.get(&i), Some(&i));
+    }
+
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn weighted() {
+    const MAX_WEIGHT: u64 = if cfg!(miri) { 64 } else { 4096 };
+
+    let cache = Cache::builder()
+        .max_weight(MAX_WEIGHT)
+        .weigher(|_: &usize, value: &Vec<u8>| value.len() as u64)
+        .build();
+    let cache = cache.pin();
+
+    for i in 0..MAX_WEIGHT as usize {
+        cache.insert(i, vec![0; i % 32]);
+        assert!(cache.total_weight() <= MAX_WEIGHT);
+    }
+
+    let weight = cache
+        .iter()
+        .map(|(_, value)| value.len() as u64)
+        .sum::<u64>();
+    assert_eq!(cache.total_weight(), weight);
+
+    cache.clear();
+    assert_eq!(cache.total_weight(), 0);
+}
+
+#[test]
+fn weighted_update() {
+    let cache = Cache::builder()
+        .max_weight(100)
+        .weigher(|_: &usize, value: &u64| *value)
+        .build();
+    let cache = cache.pin();
+
+    cache.insert(0, 10);
+    cache.insert(1, 20);
+    assert_eq!(cache.total_weight(), 30);
+
+    // Replacing a value adjusts the weight.
+    assert_eq!(cache.insert(0, 30), Some(&10));
+    assert_eq!(cache.total_weight(), 50);
+
+    assert_eq!(cache.update(1, |v| v * 2), Some(&40));
+    assert_eq!(cache.total_weight(), 70);
+
+    assert_eq!(
+        cache.compute(0, |_| Operation::Remove::<_, ()>),
+        Compute::Removed(&0, &30)
+    );
+    assert_eq!(cache.total_weight(), 40);
+
+    assert_eq!(cache.remove(&1), Some(&40));
+    assert_eq!(cache.total_weight(), 0);
+
+    // Aborted operations do not affect the weight.
+    assert_eq!(
+        cache.compute(0, |_| Operation::Abort(())),
+        Compute::Aborted(())
+    );
+    assert_eq!(cache.total_weight(), 0);
+
+    // Growing an entry past the maximum weight evicts other entries.
+    cache.insert(0, 50);
+    cache.insert(1, 50);
+    assert_eq!(cache.total_weight(), 100);
+    cache.update(0, |v| v + 1);
+    assert!(cache.total_weight() <= 100);
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn oversized() {
+    let cache = Cache::builder()
+        .max_weight(100)
+        .weigher(|_: &usize, value: &u64| *value)
+        .build();
+    let cache = cache.pin();
+
+    cache.insert(0, 10);
+    cache.insert(1, 10);
+
+    // Entries that can never fit are not inserted, and do not evict other entries.
+    assert_eq!(cache.insert(2, 101), None);
+    assert_eq!(cache.get(&2), None);
+
+    // The previous value is left untouched.
+    assert_eq!(cache.insert(0, 101), None);
+    assert_eq!(cache.get(&0), Some(&10));
+    assert_eq!(cache.get(&1), Some(&10));
+    assert_eq!(cache.total_weight(), 20);
+}
+
+#[test]
+fn unweighted() {
+    let cache = Cache::new(8);
+    let cache = cache.pin();
+
+    for i in 0..4 {
+        cache.insert(i, i);
+    }
+
+    // Entries have a weight of 1.
+    assert_eq!(cache.total_weight(), 4);
+    assert_eq!(cache.cache().max_weight(), 8);
+}
+
+#[test]
+#[should_panic]
+fn zero_capacity() {
+    let _ = Cache::<usize, usize>::new(0);
+}
+
+#[test]
+fn concurrent() {
+    const CAPACITY: usize = if cfg!(miri) { 16 } else { 128 };
+    const OPERATIONS: usize = if cfg!(miri) { 64 } else { 4096 };
+
+    let cache = Cache::new(CAPACITY);
+    let threads = threads();
+    let barrier = Barrier::new(threads);
+
+    thread::scope(|s| {
+        for t in 0..threads {
+            let (cache, barrier) = (&cache, &barrier);
+            s.spawn(move || {
+                barrier.wait();
+                let cache = cache.pin();
+                for i in 0..OPERATIONS {
+                    let key = t * OPERATIONS + i;
+                    cache.insert(key, key);
+                    if let Some(value) = cache.get(&(key - i / 2)) {
+                        assert_eq!(*value, key - i / 2);
+                    }
+                }
+            });
+        }
+    });
+
+    // Concurrent inserts may briefly exceed the capacity, but always evict before returning.
+    assert!(cache.pin().len() <= CAPACITY);
+}
+
+#[test]
+fn concurrent_same_key() {
+    const KEYS: usize = 4;
+    const OPERATIONS: usize = if cfg!(miri) { 64 } else { 4096 };
+
+    let cache = Cache::builder()
+        .max_weight(u64::MAX)
+        .weigher(|_: &usize, value: &u64| *value)
+        .build();
+    let threads = threads();
+    let barrier = Barrier::new(threads);
+
+    thread::scope(|s| {
+        for t in 0..threads {
+            let (cache, barrier) = (&cache, &barrier);
+            s.spawn(move || {
+                barrier.wait();
+                let cache = cache.pin();
+                for i in 0..OPERATIONS {
+                    let key = (i / 3) % KEYS;
+
+                    // Race inserts against updates and removals of the same keys.
+                    match (t + i) % 3 {
+                        0 => {
+                            cache.insert(key, (i % 8) as u64 + 1);
+                        }
+                        1 => {
+                            cache.update(key, |v| v % 8 + 1);
+                        }
+                        _ => {
+                            cache.remove(&key);
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let cache = cache.pin();
+    let weight = cache.iter().map(|(_, value)| *value).sum::<u64>();
+    assert_eq!(cache.total_weight(), weight);
+}
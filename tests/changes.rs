@@ -0,0 +1,204 @@
+use papaya::{Change, ChangeStream, HashMap, Operation};
+
+use std::collections::HashMap as StdHashMap;
+use std::sync::Barrier;
+use std::thread;
+
+mod common;
+use common::{threads, with_map};
+
+#[test]
+fn mutations() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let changes = map.subscribe();
+        let map = map.pin();
+
+        map.insert(0, 0);
+        map.insert(0, 1);
+        map.update(0, |v| v + 1);
+        map.compute(1, |_| Operation::Insert::<_, ()>(10));
+        map.remove_if(&1, |_, _| true).unwrap();
+        map.remove(&0);
+
+        assert_eq!(
+            received(&changes),
+            [
+                Change::Inserted { key: 0, value: 0 },
+                Change::Updated {
+                    key: 0,
+                    old: 0,
+                    new: 1
+                },
+                Change::Updated {
+                    key: 0,
+                    old: 1,
+                    new: 2
+                },
+                Change::Inserted { key: 1, value: 10 },
+                Change::Removed { key: 1, value: 10 },
+                Change::Removed { key: 0, value: 2 },
+            ]
+        );
+
+        // Failed operations are not reported.
+        map.remove(&0);
+        assert!(map.try_insert(2, 2).is_ok());
+        assert!(map.try_insert(2, 3).is_err());
+        assert_eq!(received(&changes), [Change::Inserted { key: 2, value: 2 }]);
+    });
+}
+
+#[test]
+fn bulk() {
+    const ENTRIES: usize = if cfg!(miri) { 16 } else { 256 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let changes = map.subscribe();
+
+        (&map).extend((0..ENTRIES).map(|i| (i, i)));
+        map.pin().retain(|k, _| k % 2 == 0);
+        map.pin().clear();
+
+        let mut live = StdHashMap::new();
+        for change in received(&changes) {
+            match change {
+                Change::Inserted { key, value } => assert_eq!(live.insert(key, value), None),
+                Change::Removed { key, value } => assert_eq!(live.remove(&key), Some(value)),
+                Change::Updated { .. } => panic!("unexpected update"),
+            }
+        }
+
+        // Replaying the changes results in an empty map.
+        assert!(live.is_empty());
+    });
+}
+
+#[test]
+fn mutable_access() {
+    with_map::<usize, usize>(|map| {
+        let mut map = map();
+        let changes = map.subscribe();
+
+        map.insert_mut(0, 0);
+        map.insert_mut(0, 1);
+        map.insert_mut(1, 1);
+        assert_eq!(map.remove_owned(&0), Some((0, 1)));
+        assert_eq!(map.drain().count(), 1);
+
+        assert_eq!(
+            received(&changes),
+            [
+                Change::Inserted { key: 0, value: 0 },
+                Change::Updated {
+                    key: 0,
+                    old: 0,
+                    new: 1
+                },
+                Change::Inserted { key: 1, value: 1 },
+                Change::Removed { key: 0, value: 1 },
+                Change::Removed { key: 1, value: 1 },
+            ]
+        );
+    });
+}
+
+#[test]
+fn unsubscribe() {
+    let map = HashMap::new();
+
+    // Changes made before subscribing are not reported.
+    map.pin().insert(0, 0);
+
+    let first = map.subscribe();
+    let second = map.subscribe();
+    map.pin().insert(1, 1);
+
+    drop(second);
+    map.pin().insert(2, 2);
+
+    assert_eq!(
+        received(&first),
+        [
+            Change::Inserted { key: 1, value: 1 },
+            Change::Inserted { key: 2, value: 2 }
+        ]
+    );
+
+    // Resubscribing after all streams are dropped.
+    drop(first);
+    map.pin().insert(3, 3);
+
+    let third = map.subscribe();
+    map.pin().insert(4, 4);
+    assert_eq!(received(&third), [Change::Inserted { key: 4, value: 4 }]);
+}
+
+#[test]
+fn disconnect() {
+    let map = HashMap::new();
+    let mut changes = map.subscribe();
+
+    map.pin().insert(0, 0);
+    drop(map);
+
+    // Buffered changes are received after the map is dropped.
+    assert_eq!(changes.next(), Some(Change::Inserted { key: 0, value: 0 }));
+    assert_eq!(changes.next(), None);
+}
+
+#[test]
+fn per_key_order() {
+    const KEYS: usize = 8;
+    const OPERATIONS: usize = if cfg!(miri) { 16 } else { 512 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let changes = map.subscribe();
+
+        let threads = threads();
+        let barrier = Barrier::new(threads);
+
+        thread::scope(|s| {
+            for _ in 0..threads {
+                let (map, barrier) = (&map, &barrier);
+                s.spawn(move || {
+                    barrier.wait();
+                    let map = map.pin();
+                    for i in 0..OPERATIONS {
+                        let key = i % KEYS;
+                        if i % 7 == 0 {
+                            map.remove(&key);
+                        } else {
+                            map.update_or_insert(key, |v| v + 1, 0);
+                        }
+                    }
+                });
+            }
+        });
+
+        // Replaying the changes to each key in order reproduces the final state of the map.
+        let mut live = StdHashMap::new();
+        for change in received(&changes) {
+            match change {
+                Change::Inserted { key, value } => assert_eq!(live.insert(key, value), None),
+                Change::Updated { key, old, new } => {
+                    assert_eq!(live.insert(key, new), Some(old))
+                }
+                Change::Removed { key, value } => assert_eq!(live.remove(&key), Some(value)),
+            }
+        }
+
+        let map = map.pin();
+        assert_eq!(live.len(), map.len());
+        for (key, value) in live {
+            assert_eq!(map.get(&key), Some(&value));
+        }
+    });
+}
+
+// Returns all changes that have been buffered by the stream.
+fn received<K, V>(changes: &ChangeStream<K, V>) -> Vec<Change<K, V>> {
+    std::iter::from_fn(|| changes.try_recv()).collect()
+}
@@ -39,6 +39,17 @@ pub fn with_map<K, V>(mut test: impl FnMut(&dyn Fn() -> HashMap<K, V>)) {
                 .build()
         }),
     );
+
+    // Incremental resize mode with cached hashes, which are used when copying entries.
+    test(
+        &(|| {
+            HashMap::builder()
+                .collector(collector())
+                .resize_mode(ResizeMode::Incremental(1))
+                .cache_hashes(true)
+                .build()
+        }),
+    );
 }
 
 // Run the test on different configurations of a `HashSet`.
@@ -0,0 +1,205 @@
+use papaya::expiring::{ExpiringMap, ManualClock, SweepMode};
+
+use std::collections::hash_map::RandomState;
+use std::sync::Barrier;
+use std::thread;
+use std::time::Duration;
+
+mod common;
+use common::threads;
+
+const SECOND: Duration = Duration::from_secs(1);
+
+fn map(
+    clock: &ManualClock,
+    sweep_mode: SweepMode,
+) -> ExpiringMap<usize, usize, RandomState, ManualClock> {
+    ExpiringMap::builder()
+        .clock(clock.clone())
+        .sweep_mode(sweep_mode)
+        .build()
+}
+
+#[test]
+fn expire() {
+    let clock = ManualClock::new();
+    let map = map(&clock, SweepMode::Manual);
+    let map = map.pin();
+
+    assert_eq!(map.insert_with_ttl(0, 0, SECOND), None);
+    assert_eq!(map.insert_with_ttl(1, 1, SECOND * 2), None);
+    assert_eq!(map.insert(2, 2), None);
+    assert_eq!(map.get(&0), Some(&0));
+
+    clock.advance(SECOND);
+    assert_eq!(map.get(&0), None);
+    assert!(!map.contains_key(&0));
+    assert_eq!(map.get(&1), Some(&1));
+    assert_eq!(map.iter().count(), 2);
+
+    // Expired entries are still counted until they are removed.
+    assert_eq!(map.len(), 3);
+
+    // Expired values are not returned by writes.
+    assert_eq!(map.remove(&0), None);
+    assert_eq!(map.insert(1, 10), Some(&1));
+
+    clock.advance(SECOND * 1000);
+    assert_eq!(map.get(&1), Some(&10));
+    assert_eq!(map.get(&2), Some(&2));
+}
+
+#[test]
+fn default_ttl() {
+    let clock = ManualClock::new();
+    let map: ExpiringMap<usize, usize, _, _> = ExpiringMap::builder()
+        .clock(clock.clone())
+        .default_ttl(SECOND)
+        .build();
+    let map = map.pin();
+
+    map.insert(0, 0);
+    map.insert_with_ttl(1, 1, SECOND * 2);
+
+    clock.advance(SECOND);
+    assert_eq!(map.get(&0), None);
+    assert_eq!(map.get(&1), Some(&1));
+}
+
+#[test]
+fn refresh_ttl() {
+    let clock = ManualClock::new();
+    let map = map(&clock, SweepMode::Manual);
+    let map = map.pin();
+
+    assert!(!map.refresh_ttl(&0, SECOND));
+    map.insert_with_ttl(0, 0, SECOND);
+
+    for _ in 0..4 {
+        clock.advance(SECOND / 2);
+        assert!(map.refresh_ttl(&0, SECOND));
+    }
+    assert_eq!(map.get(&0), Some(&0));
+
+    // Entries without a TTL can be given one.
+    map.insert(1, 1);
+    assert!(map.refresh_ttl(&1, SECOND));
+
+    clock.advance(SECOND);
+    assert_eq!(map.get(&0), None);
+    assert_eq!(map.get(&1), None);
+
+    // Expired entries cannot be revived.
+    assert!(!map.refresh_ttl(&0, SECOND));
+    assert_eq!(map.get(&0), None);
+}
+
+#[test]
+fn reap_expired() {
+    const LEN: usize = if cfg!(miri) { 64 } else { 1024 };
+
+    let clock = ManualClock::new();
+    let map = map(&clock, SweepMode::Manual);
+    let map = map.pin();
+
+    for i in 0..LEN {
+        if i % 2 == 0 {
+            map.insert_with_ttl(i, i, SECOND);
+        } else {
+            map.insert(i, i);
+        }
+    }
+
+    map.reap_expired();
+    assert_eq!(map.len(), LEN);
+
+    clock.advance(SECOND);
+    map.reap_expired();
+    assert_eq!(map.len(), LEN / 2);
+
+    for i in 0..LEN {
+        assert_eq!(map.get(&i), (i % 2 == 1).then_some(&i));
+    }
+}
+
+#[test]
+fn incremental_sweep() {
+    const LEN: usize = if cfg!(miri) { 64 } else { 1024 };
+
+    let clock = ManualClock::new();
+    let map = map(&clock, SweepMode::Incremental(16));
+    let map = map.pin();
+
+    for i in 0..LEN {
+        map.insert_with_ttl(i, i, SECOND);
+    }
+    assert_eq!(map.len(), LEN);
+
+    clock.advance(SECOND);
+
+    // Writes to unrelated keys eventually sweep every expired entry.
+    let mut writes = 0;
+    while map.len() > 1 {
+        map.insert(LEN, writes);
+        writes += 1;
+        assert!(writes < LEN * 4, "expired entries were not swept");
+    }
+
+    assert_eq!(map.get(&LEN), Some(&(writes - 1)));
+}
+
+#[test]
+fn manual_sweep() {
+    let clock = ManualClock::new();
+    let map = map(&clock, SweepMode::Manual);
+    let map = map.pin();
+
+    for i in 0..64 {
+        map.insert_with_ttl(i, i, SECOND);
+    }
+
+    clock.advance(SECOND);
+    for i in 0..64 {
+        map.insert(64 + i, i);
+    }
+
+    // Entries are only removed explicitly.
+    assert_eq!(map.len(), 128);
+    map.reap_expired();
+    assert_eq!(map.len(), 64);
+}
+
+#[test]
+fn concurrent_refresh() {
+    const OPERATIONS: usize = if cfg!(miri) { 16 } else { 1024 };
+
+    let clock = ManualClock::new();
+    let map = map(&clock, SweepMode::Incremental(8));
+
+    let threads = threads().max(2);
+    let barrier = Barrier::new(threads);
+
+    thread::scope(|s| {
+        for t in 0..threads {
+            let (map, clock, barrier) = (&map, &clock, &barrier);
+            s.spawn(move || {
+                barrier.wait();
+                for i in 0..OPERATIONS {
+                    let map = map.pin();
+                    if t == 0 {
+                        clock.advance(SECOND);
+                        map.reap_expired();
+                    } else {
+                        let key = t * 16 + i % 16;
+                        map.insert_with_ttl(key, i, SECOND);
+
+                        // A successful refresh means the entry has not been reaped.
+                        if map.refresh_ttl(&key, SECOND * 1_000_000) {
+                            assert_eq!(map.get(&key), Some(&i));
+                        }
+                    }
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,136 @@
+#![cfg(feature = "metrics")]
+
+use papaya::{HashMap, ResizeMode};
+
+use metrics::{
+    Counter, CounterFn, Gauge, GaugeFn, Histogram, Key, KeyName, Metadata, Recorder, SharedString,
+    Unit,
+};
+
+use std::collections::HashMap as StdHashMap;
+use std::sync::{Arc, Mutex};
+
+// A recorder that stores the latest value of every metric.
+#[derive(Default)]
+struct TestRecorder {
+    values: Mutex<StdHashMap<String, Arc<Value>>>,
+}
+
+#[derive(Default)]
+struct Value(Mutex<f64>);
+
+impl TestRecorder {
+    fn value(&self, name: &str, map: &str) -> Option<f64> {
+        let values = self.values.lock().unwrap();
+        let value = values.get(&format!("{name}{{map={map}}}"))?;
+        let value = *value.0.lock().unwrap();
+        Some(value)
+    }
+
+    fn register(&self, key: &Key) -> Arc<Value> {
+        let labels = key
+            .labels()
+            .map(|label| format!("{}={}", label.key(), label.value()))
+            .collect::<Vec<_>>();
+
+        let name = format!("{}{{{}}}", key.name(), labels.join(","));
+        self.values.lock().unwrap().entry(name).or_default().clone()
+    }
+}
+
+impl Recorder for TestRecorder {
+    fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+    fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+    fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+        Counter::from_arc(self.register(key))
+    }
+
+    fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+        Gauge::from_arc(self.register(key))
+    }
+
+    fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+        Histogram::noop()
+    }
+}
+
+impl CounterFn for Value {
+    fn increment(&self, value: u64) {
+        *self.0.lock().unwrap() += value as f64;
+    }
+
+    fn absolute(&self, value: u64) {
+        *self.0.lock().unwrap() = value as f64;
+    }
+}
+
+impl GaugeFn for Value {
+    fn increment(&self, value: f64) {
+        *self.0.lock().unwrap() += value;
+    }
+
+    fn decrement(&self, value: f64) {
+        *self.0.lock().unwrap() -= value;
+    }
+
+    fn set(&self, value: f64) {
+        *self.0.lock().unwrap() = value;
+    }
+}
+
+#[test]
+fn resize_metrics() {
+    const LEN: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    for resize_mode in [ResizeMode::Blocking, ResizeMode::Incremental(1)] {
+        let recorder = TestRecorder::default();
+
+        metrics::with_local_recorder(&recorder, || {
+            let map = HashMap::builder()
+                .resize_mode(resize_mode)
+                .metrics("test")
+                .build();
+
+            for i in 0..LEN {
+                map.pin().insert(i, i);
+            }
+
+            let stats = map.pin().stats();
+
+            let promotions = recorder.value("papaya_promotions", "test").unwrap();
+            assert!(promotions > 0.0);
+            assert_eq!(promotions as u64, stats.promotions);
+
+            let resizes = recorder
+                .value("papaya_resizes_in_progress", "test")
+                .unwrap();
+            assert_eq!(resizes, stats.depth as f64);
+
+            // The length is only sampled, so record it explicitly.
+            map.record_metrics(&map.guard());
+            assert_eq!(recorder.value("papaya_len", "test"), Some(LEN as f64));
+            assert_eq!(
+                recorder.value("papaya_capacity", "test"),
+                Some(map.pin().stats().capacity as f64)
+            );
+
+            // Dropping the map resets its gauges.
+            drop(map);
+            assert_eq!(recorder.value("papaya_len", "test"), Some(0.0));
+            assert_eq!(recorder.value("papaya_capacity", "test"), Some(0.0));
+            assert_eq!(
+                recorder.value("papaya_resizes_in_progress", "test"),
+                Some(0.0)
+            );
+
+            // Metrics are not recorded for maps without a name.
+            let map = HashMap::builder().resize_mode(ResizeMode::Blocking).build();
+            for i in 0..LEN {
+                map.pin().insert(i, i);
+            }
+            assert_eq!(recorder.values.lock().unwrap().len(), 4);
+        });
+    }
+}
@@ -0,0 +1,252 @@
+use papaya::{HashMap, Operation, ResizeMode};
+
+use std::collections::HashMap as StdHashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Barrier;
+use std::thread;
+
+mod common;
+use common::threads;
+
+// Run the test on versioned maps with different resize modes.
+fn with_versioned_map(mut test: impl FnMut(HashMap<usize, usize>)) {
+    for resize_mode in [ResizeMode::Blocking, ResizeMode::Incremental(1)] {
+        test(
+            HashMap::builder()
+                .resize_mode(resize_mode)
+                .versioned(true)
+                .build(),
+        );
+    }
+}
+
+#[test]
+fn isolation() {
+    with_versioned_map(|map| {
+        let map = map.pin();
+        for i in 0..4 {
+            map.insert(i, i);
+        }
+
+        let snapshot = map.snapshot();
+
+        map.insert(0, 10);
+        map.update(1, |v| v + 10);
+        map.remove(&2);
+        map.compute(4, |_| Operation::Insert::<_, ()>(4));
+        map.remove(&4);
+        map.insert(5, 5);
+
+        for i in 0..4 {
+            assert_eq!(snapshot.get(&i), Some(&i));
+        }
+        assert_eq!(snapshot.get(&4), None);
+        assert_eq!(snapshot.get(&5), None);
+
+        let mut entries = snapshot.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(entries, [(0, 0), (1, 1), (2, 2), (3, 3)]);
+        assert_eq!(snapshot.len(), 4);
+
+        // The live map is unaffected.
+        assert_eq!(map.get(&0), Some(&10));
+        assert_eq!(map.get(&1), Some(&11));
+        assert_eq!(map.get(&2), None);
+        assert_eq!(map.get(&5), Some(&5));
+        assert_eq!(map.len(), 4);
+    });
+}
+
+#[test]
+fn clear_and_retain() {
+    const ENTRIES: usize = if cfg!(miri) { 16 } else { 256 };
+
+    with_versioned_map(|map| {
+        let map = map.pin();
+        for i in 0..ENTRIES {
+            map.insert(i, i);
+        }
+
+        let snapshot = map.snapshot();
+        map.retain(|k, _| k % 2 == 0);
+        assert_eq!(snapshot.len(), ENTRIES);
+
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(snapshot.len(), ENTRIES);
+        for i in 0..ENTRIES {
+            assert_eq!(snapshot.get(&i), Some(&i));
+        }
+    });
+}
+
+#[test]
+fn resize() {
+    const ENTRIES: usize = if cfg!(miri) { 48 } else { 1024 };
+
+    with_versioned_map(|map| {
+        let map = map.pin();
+        for i in 0..ENTRIES {
+            map.insert(i, i);
+        }
+
+        let snapshot = map.snapshot();
+
+        // Grow the map enough to trigger resizes, modifying existing entries along the way.
+        for i in 0..ENTRIES * 4 {
+            map.insert(i, i + 1);
+        }
+
+        assert_eq!(snapshot.len(), ENTRIES);
+        for i in 0..ENTRIES * 4 {
+            let expected = (i < ENTRIES).then_some(i);
+            assert_eq!(snapshot.get(&i).copied(), expected);
+        }
+    });
+}
+
+#[test]
+fn multiple() {
+    with_versioned_map(|map| {
+        let map = map.pin();
+        map.insert(0, 0);
+
+        let first = map.snapshot();
+        map.insert(0, 1);
+
+        let second = map.snapshot();
+        map.insert(0, 2);
+
+        assert_eq!(first.get(&0), Some(&0));
+        assert_eq!(second.get(&0), Some(&1));
+
+        // Dropping a snapshot does not affect others.
+        drop(first);
+        map.insert(0, 3);
+        assert_eq!(second.get(&0), Some(&1));
+
+        drop(second);
+        let third = map.snapshot();
+        assert_eq!(third.get(&0), Some(&3));
+    });
+}
+
+#[test]
+#[should_panic]
+fn unversioned() {
+    let map = HashMap::<usize, usize>::new();
+    let _ = map.pin().snapshot();
+}
+
+#[test]
+fn concurrent() {
+    const KEYS: usize = 8;
+    const OPERATIONS: usize = if cfg!(miri) { 64 } else { 4096 };
+
+    with_versioned_map(|map| {
+        let threads = threads();
+        let barrier = Barrier::new(threads + 1);
+        let finished = AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            for t in 0..threads {
+                let (map, barrier, finished) = (&map, &barrier, &finished);
+                s.spawn(move || {
+                    barrier.wait();
+
+                    // Slide a window of consecutive keys, so that the map only ever contains
+                    // a contiguous range of keys written by each thread.
+                    let map = map.pin();
+                    for i in 0..OPERATIONS {
+                        if i >= KEYS {
+                            map.remove(&(t * OPERATIONS + i - KEYS));
+                        }
+                        map.insert(t * OPERATIONS + i, t);
+                    }
+
+                    finished.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+
+            barrier.wait();
+            while finished.load(Ordering::Relaxed) < threads {
+                let map = map.pin();
+                let snapshot = map.snapshot();
+
+                let mut keys = StdHashMap::<usize, Vec<usize>>::new();
+                for (&key, &t) in snapshot.iter() {
+                    assert_eq!(snapshot.get(&key), Some(&t));
+                    keys.entry(t).or_default().push(key);
+                }
+
+                // The snapshot only observes states that the map was actually in.
+                for keys in keys.values_mut() {
+                    keys.sort();
+                    assert!(keys.len() <= KEYS);
+                    assert!(keys.windows(2).all(|w| w[1] == w[0] + 1), "{keys:?}");
+                }
+            }
+        });
+    });
+}
+
+#[test]
+fn racing_writers() {
+    const KEYS: usize = 4;
+    const OPERATIONS: usize = if cfg!(miri) { 64 } else { 4096 };
+
+    with_versioned_map(|map| {
+        let threads = threads();
+        let barrier = Barrier::new(threads + 1);
+        let finished = AtomicUsize::new(0);
+
+        thread::scope(|s| {
+            for t in 0..threads {
+                let (map, barrier, finished) = (&map, &barrier, &finished);
+                s.spawn(move || {
+                    barrier.wait();
+
+                    // Race writers on the same keys, so that writes fail and are retried.
+                    let map = map.pin();
+                    for i in 0..OPERATIONS {
+                        let key = (t + i) % KEYS;
+                        match i % 3 {
+                            0 => {
+                                map.insert(key, i);
+                            }
+                            1 => {
+                                map.update(key, |v| v + 1);
+                            }
+                            _ => {
+                                map.remove(&key);
+                            }
+                        }
+                    }
+
+                    finished.fetch_add(1, Ordering::Relaxed);
+                });
+            }
+
+            barrier.wait();
+            while finished.load(Ordering::Relaxed) < threads {
+                let map = map.pin();
+                let snapshot = map.snapshot();
+
+                let before = (0..KEYS)
+                    .map(|key| snapshot.get(&key).copied())
+                    .collect::<Vec<_>>();
+                thread::yield_now();
+
+                // The snapshot is unaffected by the concurrent writes.
+                for (key, &value) in before.iter().enumerate() {
+                    assert_eq!(snapshot.get(&key).copied(), value);
+                }
+
+                assert_eq!(snapshot.len(), before.iter().flatten().count());
+                for (&key, &value) in snapshot.iter() {
+                    assert_eq!(before[key], Some(value));
+                }
+            }
+        });
+    });
+}
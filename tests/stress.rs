@@ -231,6 +231,71 @@ fn update_stress() {
     });
 }
 
+// Call `compare_exchange` in parallel for a small shared set of keys.
+#[test]
+#[ignore]
+fn compare_exchange_stress() {
+    const ENTRIES: usize = if cfg!(miri) { 64 } else { 256 };
+    const OPERATIONS: usize = match () {
+        _ if cfg!(miri) => 1,
+        _ if cfg!(papaya_stress) || cfg!(papaya_asan) => 1 << 9,
+        _ => 1 << 10,
+    };
+    const ITERATIONS: usize = if cfg!(miri) { 1 } else { 48 };
+
+    let entries = || {
+        let mut entries = (0..(OPERATIONS))
+            .flat_map(|_| 0..ENTRIES)
+            .collect::<Vec<_>>();
+        let mut rng = rand::thread_rng();
+        entries.shuffle(&mut rng);
+        entries
+    };
+
+    with_map(|map| {
+        for _ in (0..ITERATIONS).inspect(|e| debug!("{e}/{ITERATIONS}")) {
+            let map = map();
+
+            {
+                let guard = map.guard();
+                for i in 0..ENTRIES {
+                    map.insert(i, 0, &guard);
+                }
+            }
+
+            let threads = threads();
+            let barrier = Barrier::new(threads);
+
+            thread::scope(|s| {
+                for _ in 0..threads {
+                    s.spawn(|| {
+                        let entries = entries();
+                        barrier.wait();
+                        for i in entries {
+                            let guard = map.guard();
+                            let mut current = *map.get(&i, &guard).unwrap();
+                            loop {
+                                match map.compare_exchange(i, &current, current + 1, &guard) {
+                                    Ok(new) => {
+                                        assert_eq!(*new, current + 1);
+                                        break;
+                                    }
+                                    Err(err) => current = *err.current.unwrap(),
+                                }
+                            }
+                        }
+                    });
+                }
+            });
+
+            let guard = map.guard();
+            for i in 0..ENTRIES {
+                assert_eq!(*map.get(&i, &guard).unwrap(), threads * OPERATIONS);
+            }
+        }
+    });
+}
+
 // Call `update` in parallel for a shared set of keys, with a single thread dedicated
 // to inserting unrelated keys. This is likely to cause interference with incremental resizing.
 #[test]
@@ -0,0 +1,191 @@
+use papaya::txn::{self, Conflict};
+use papaya::{HashMap, ResizeMode};
+use seize::Collector;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+mod common;
+use common::{threads, with_map};
+
+#[test]
+fn read_write() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let guard = map.guard();
+
+        let value = txn::transaction(&guard, |tx| {
+            assert_eq!(tx.get(&map, &1)?, None);
+            tx.insert(&map, 1, 1);
+            tx.insert(&map, 2, 2);
+
+            // Buffered writes are visible to the transaction.
+            assert_eq!(tx.get(&map, &1)?, Some(&1));
+            tx.insert(&map, 1, 3);
+            assert_eq!(tx.get(&map, &1)?, Some(&3));
+
+            // But not outside of it.
+            assert_eq!(map.get(&1, &guard), None);
+            Ok(4)
+        });
+
+        assert_eq!(value, 4);
+        assert_eq!(map.get(&1, &guard), Some(&3));
+        assert_eq!(map.get(&2, &guard), Some(&2));
+
+        txn::transaction(&guard, |tx| {
+            tx.remove(&map, &1);
+            assert_eq!(tx.get(&map, &1)?, None);
+            assert_eq!(tx.get(&map, &2)?, Some(&2));
+            Ok(())
+        });
+
+        assert_eq!(map.get(&1, &guard), None);
+        assert_eq!(map.len(), 1);
+    });
+}
+
+#[test]
+fn retry_on_conflict() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let guard = map.guard();
+        map.insert(0, 0, &guard);
+        map.insert(1, 0, &guard);
+
+        let mut attempts = 0;
+        txn::transaction(&guard, |tx| {
+            attempts += 1;
+
+            let value = *tx.get(&map, &0)?.unwrap();
+
+            // Interfere with the first attempt.
+            if attempts == 1 {
+                map.insert(0, 10, &guard);
+                assert_eq!(tx.get(&map, &1), Err(Conflict));
+            }
+
+            tx.insert(&map, 1, value + 1);
+            Ok(())
+        });
+
+        assert_eq!(attempts, 2);
+        assert_eq!(map.get(&1, &guard), Some(&11));
+
+        // Reinserting an equal value still invalidates the read.
+        let mut attempts = 0;
+        txn::transaction(&guard, |tx| {
+            attempts += 1;
+
+            let value = *tx.get(&map, &0)?.unwrap();
+            if attempts == 1 {
+                map.insert(0, 10, &guard);
+            }
+
+            tx.insert(&map, 1, value + 1);
+            Ok(())
+        });
+
+        assert_eq!(attempts, 2);
+    });
+}
+
+#[test]
+fn multiple_maps() {
+    let collector = Arc::new(Collector::new());
+    let map1: HashMap<usize, usize, _, _> = HashMap::builder()
+        .shared_collector(collector.clone())
+        .build();
+    let map2: HashMap<usize, usize, _, _> = HashMap::builder()
+        .shared_collector(collector.clone())
+        .resize_mode(ResizeMode::Blocking)
+        .build();
+
+    let guard = collector.enter();
+    map1.insert(0, 10, &guard);
+
+    txn::transaction(&guard, |tx| {
+        let value = *tx.get(&map1, &0)?.unwrap();
+
+        // The same key in different maps is independent.
+        assert_eq!(tx.get(&map2, &0)?, None);
+
+        tx.remove(&map1, &0);
+        tx.insert(&map2, 0, value);
+        Ok(())
+    });
+
+    assert_eq!(map1.get(&0, &guard), None);
+    assert_eq!(map2.get(&0, &guard), Some(&10));
+}
+
+#[test]
+#[should_panic]
+fn incorrect_collector() {
+    let map1 = HashMap::<usize, usize>::new();
+    let map2 = HashMap::<usize, usize>::new();
+
+    let guard = map1.guard();
+    txn::transaction(&guard, |tx| {
+        tx.insert(&map2, 0, 0);
+        Ok(())
+    });
+}
+
+#[test]
+fn concurrent_transfer() {
+    const ACCOUNTS: usize = 16;
+    const TOTAL: usize = ACCOUNTS * 100;
+    const OPERATIONS: usize = if cfg!(miri) { 16 } else { 512 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        for i in 0..ACCOUNTS {
+            map.pin().insert(i, TOTAL / ACCOUNTS);
+        }
+
+        let threads = threads().max(2);
+        let barrier = Barrier::new(threads);
+
+        thread::scope(|s| {
+            for t in 0..threads {
+                let (map, barrier) = (&map, &barrier);
+                s.spawn(move || {
+                    barrier.wait();
+                    for i in 0..OPERATIONS {
+                        let (from, to) = ((i + t) % ACCOUNTS, (i * 7 + t + 1) % ACCOUNTS);
+                        if from == to {
+                            continue;
+                        }
+
+                        let guard = map.guard();
+                        txn::transaction(&guard, |tx| {
+                            let a = *tx.get(map, &from)?.unwrap();
+                            let b = *tx.get(map, &to)?.unwrap();
+                            let amount = a.min(3);
+                            tx.insert(map, from, a - amount);
+                            tx.insert(map, to, b + amount);
+                            Ok(())
+                        });
+
+                        // Transactions observe a consistent view of all accounts.
+                        let total = txn::transaction(&guard, |tx| {
+                            let mut total = 0;
+                            for i in 0..ACCOUNTS {
+                                total += *tx.get(map, &i)?.unwrap();
+                            }
+                            Ok(total)
+                        });
+                        assert_eq!(total, TOTAL);
+                    }
+                });
+            }
+        });
+
+        let guard = map.guard();
+        let total = (0..ACCOUNTS)
+            .map(|i| *map.get(&i, &guard).unwrap())
+            .sum::<usize>();
+        assert_eq!(total, TOTAL);
+    });
+}
@@ -0,0 +1,271 @@
+use papaya::{HashMap, Operation};
+
+use std::future::{self, Future};
+use std::hash::{BuildHasherDefault, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Barrier;
+use std::task::{Context, RawWaker, RawWakerVTable, Waker};
+use std::thread;
+use std::time::Duration;
+
+mod common;
+use common::{threads, with_map};
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+#[test]
+fn ready() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        map.pin().insert(0, 0);
+
+        let map = map.pin();
+        assert_eq!(block_on(map.wait_for(&0)), &0);
+        assert_eq!(block_on(map.wait_until(&0, |&v| v == 0)), &0);
+    });
+}
+
+#[test]
+fn wait_for_insert() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                for i in 0..64 {
+                    map.pin().insert(i, i);
+                }
+            });
+
+            let map = map.pin_owned();
+            assert_eq!(block_on(map.wait_for(&63)), &63);
+        });
+    });
+}
+
+#[test]
+fn wait_until_update() {
+    const UPDATES: usize = if cfg!(miri) { 16 } else { 1024 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                map.pin().insert(0, 0);
+                for _ in 0..UPDATES {
+                    map.pin().compute(0, |entry| match entry {
+                        Some((_, v)) => Operation::Insert::<_, ()>(v + 1),
+                        None => unreachable!(),
+                    });
+                }
+            });
+
+            let map = map.pin_owned();
+            let value = block_on(map.wait_until(&0, |&v| v == UPDATES));
+            assert_eq!(value, &UPDATES);
+        });
+    });
+}
+
+#[test]
+fn cancel() {
+    let map = HashMap::<usize, usize>::new();
+    let map = map.pin();
+
+    // Poll a future once and drop it, deregistering its waker.
+    {
+        let mut future = Box::pin(map.wait_for(&0));
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+    }
+
+    map.insert(0, 0);
+    assert_eq!(block_on(map.wait_for(&0)), &0);
+}
+
+#[test]
+fn send() {
+    fn assert_send<T: Send>(_: &T) {}
+
+    let map = HashMap::<usize, usize>::new();
+    let map = map.pin_owned();
+    assert_send(&map.wait_for(&0));
+    assert_send(&map.wait_until(&0, |_| true));
+}
+
+#[test]
+fn concurrent() {
+    const KEYS: usize = if cfg!(miri) { 8 } else { 128 };
+
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let threads = threads();
+        let barrier = Barrier::new(threads + 1);
+
+        thread::scope(|s| {
+            for t in 0..threads {
+                let (map, barrier) = (&map, &barrier);
+                s.spawn(move || {
+                    let map = map.pin_owned();
+                    barrier.wait();
+                    for i in (t..KEYS).step_by(threads) {
+                        assert_eq!(block_on(map.wait_for(&i)), &i);
+                    }
+                });
+            }
+
+            barrier.wait();
+            for i in (0..KEYS).rev() {
+                map.pin().insert(i, i);
+            }
+        });
+    });
+}
+
+#[test]
+fn single_flight() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let loads = AtomicUsize::new(0);
+
+        let threads = threads();
+        let barrier = Barrier::new(threads);
+
+        thread::scope(|s| {
+            for _ in 0..threads {
+                let (map, loads, barrier) = (&map, &loads, &barrier);
+                s.spawn(move || {
+                    let map = map.pin_owned();
+                    barrier.wait();
+
+                    let value = block_on(map.get_or_insert_async(0, || async {
+                        loads.fetch_add(1, Ordering::Relaxed);
+                        thread::sleep(Duration::from_millis(10));
+                        42
+                    }));
+
+                    assert_eq!(value, &42);
+                });
+            }
+        });
+
+        assert_eq!(loads.load(Ordering::Relaxed), 1);
+    });
+}
+
+#[test]
+fn load_failure() {
+    with_map::<usize, usize>(|map| {
+        let map = map();
+        let map = map.pin_owned();
+
+        let value = block_on(map.get_or_try_insert_async(0, || async { Err("failed") }));
+        assert_eq!(value, Err("failed"));
+        assert_eq!(map.get(&0), None);
+
+        let value = block_on(map.get_or_try_insert_async(0, || async { Ok::<_, ()>(1) }));
+        assert_eq!(value, Ok(&1));
+
+        // The value is not loaded again.
+        let value = block_on(map.get_or_try_insert_async(0, || async { Err(()) }));
+        assert_eq!(value, Ok(&1));
+    });
+}
+
+#[test]
+fn load_takeover() {
+    let map = HashMap::<usize, usize>::new();
+    let barrier = Barrier::new(2);
+
+    thread::scope(|s| {
+        s.spawn(|| {
+            let map = map.pin_owned();
+            let value = block_on(map.get_or_try_insert_async(0, || async {
+                barrier.wait();
+                thread::sleep(Duration::from_millis(10));
+                Err(())
+            }));
+            assert_eq!(value, Err(()));
+        });
+
+        // Wait on the failed load, then take it over.
+        barrier.wait();
+        let map = map.pin_owned();
+        let value = block_on(map.get_or_insert_async(0, || async { 1 }));
+        assert_eq!(value, &1);
+    });
+}
+
+#[test]
+fn load_cancel() {
+    let map = HashMap::<usize, usize>::new();
+    let map = map.pin();
+
+    // Claim the key and drop the future before it completes.
+    {
+        let mut future = Box::pin(map.get_or_insert_async(0, future::pending));
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        assert!(future.as_mut().poll(&mut cx).is_pending());
+    }
+
+    assert_eq!(block_on(map.get_or_insert_async(0, || async { 1 })), &1);
+}
+
+#[test]
+fn load_colliding() {
+    #[derive(Default)]
+    struct ZeroHasher;
+
+    impl Hasher for ZeroHasher {
+        fn finish(&self) -> u64 {
+            0
+        }
+
+        fn write(&mut self, _: &[u8]) {}
+    }
+
+    let map = HashMap::<usize, usize, _>::with_hasher(BuildHasherDefault::<ZeroHasher>::default());
+    let map = map.pin();
+
+    // Claim a key, and load a different key with the same hash while the claim is held.
+    let mut future = Box::pin(map.get_or_insert_async(0, future::pending));
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    assert!(future.as_mut().poll(&mut cx).is_pending());
+
+    assert_eq!(block_on(map.get_or_insert_async(1, || async { 1 })), &1);
+    assert!(future.as_mut().poll(&mut cx).is_pending());
+
+    drop(future);
+    assert_eq!(block_on(map.get_or_insert_async(0, || async { 0 })), &0);
+}
+
+#[test]
+fn load_send() {
+    fn assert_send<T: Send>(_: &T) {}
+
+    let map = HashMap::<usize, usize>::new();
+    let map = map.pin_owned();
+    assert_send(&map.get_or_insert_async(0, || async { 0 }));
+}
+
+// A waker that does nothing when woken.
+fn noop_waker() -> Waker {
+    const VTABLE: RawWakerVTable = RawWakerVTable::new(
+        |_| RawWaker::new(std::ptr::null(), &VTABLE),
+        |_| {},
+        |_| {},
+        |_| {},
+    );
+
+    // Safety: The vtable functions do not access the data pointer.
+    unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+}